@@ -0,0 +1,362 @@
+use crate::garbage::GarbageRecognizer;
+use crate::AppState;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+/// Fixed part of the end of central directory record, the archive comment follows it.
+const END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 22;
+
+/// A file or directory stored in an archive, as listed by its central directory.
+#[derive(Debug, PartialEq)]
+pub struct ArchiveEntry {
+    /// Path inside the archive with `/` separators and without a trailing one.
+    pub name: String,
+    pub compressed_size: u64,
+}
+
+/// Garbage of a project found inside an archive, it's only reported and never cleaned.
+#[derive(Debug, PartialEq)]
+pub struct ArchiveGarbage {
+    pub archive: PathBuf,
+    pub recognizer_name: String,
+    /// Project directory inside the archive, empty for the archive root.
+    pub directory: String,
+    pub deletable: Vec<String>,
+    /// Compressed size of the deletables in the archive.
+    pub size: u64,
+}
+
+/// Lists the entries of a zip archive from its central directory, nothing is extracted.
+/// Zip64 archives aren't supported.
+pub fn read_zip_listing(mut archive: impl Read + Seek) -> io::Result<Vec<ArchiveEntry>> {
+    let length = archive.seek(SeekFrom::End(0))?;
+    // the record is at the very end, only followed by a comment of at most 64 KiB
+    let tail_length = length.min(END_OF_CENTRAL_DIRECTORY_SIZE + u16::MAX as u64);
+    archive.seek(SeekFrom::Start(length - tail_length))?;
+    let mut tail = vec![0; tail_length as usize];
+    archive.read_exact(&mut tail)?;
+    if tail.len() < END_OF_CENTRAL_DIRECTORY_SIZE as usize {
+        return Err(invalid("too short for a zip archive"));
+    }
+
+    let last_start = tail
+        .len()
+        .saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE as usize);
+    let record = (0..=last_start)
+        .rev()
+        .find(|&offset| read_u32(&tail, offset) == END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+        .ok_or_else(|| invalid("no end of central directory"))?;
+    let entries = read_u16(&tail, record + 10) as usize;
+    let directory_size = read_u32(&tail, record + 12);
+    let directory_offset = read_u32(&tail, record + 16);
+    if directory_offset == u32::MAX || entries == u16::MAX as usize {
+        return Err(invalid("zip64 archives are not supported"));
+    }
+
+    // the sizes come from the archive, a corrupt one must not allocate more than it holds
+    if directory_offset as u64 + directory_size as u64 > length {
+        return Err(invalid("central directory beyond the end of the archive"));
+    }
+    archive.seek(SeekFrom::Start(directory_offset as u64))?;
+    let mut directory = vec![0; directory_size as usize];
+    archive.read_exact(&mut directory)?;
+
+    let mut listing = Vec::with_capacity(entries);
+    let mut offset = 0;
+    for _ in 0..entries {
+        if directory.len() < offset + 46
+            || read_u32(&directory, offset) != CENTRAL_DIRECTORY_HEADER_SIGNATURE
+        {
+            return Err(invalid("truncated central directory"));
+        }
+        let compressed_size = read_u32(&directory, offset + 20) as u64;
+        let name_length = read_u16(&directory, offset + 28) as usize;
+        let extra_length = read_u16(&directory, offset + 30) as usize;
+        let comment_length = read_u16(&directory, offset + 32) as usize;
+        let name = directory
+            .get(offset + 46..offset + 46 + name_length)
+            .ok_or_else(|| invalid("truncated central directory"))?;
+        listing.push(ArchiveEntry {
+            name: String::from_utf8_lossy(name)
+                .trim_end_matches('/')
+                .to_string(),
+            compressed_size,
+        });
+        offset += 46 + name_length + extra_length + comment_length;
+    }
+    Ok(listing)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Runs the recognizers against the directories of an archive listing. A marker or
+/// deletable has to be a direct entry of the directory to count.
+pub fn recognize_in_listing<'a>(
+    archive: &Path,
+    listing: &[ArchiveEntry],
+    recognizers: impl IntoIterator<Item = &'a GarbageRecognizer>,
+) -> Vec<ArchiveGarbage> {
+    // every directory with the names of its direct entries, parents of files included
+    let mut directories: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for entry in listing {
+        let mut path = entry.name.as_str();
+        while let Some((parent, name)) = path.rsplit_once('/') {
+            directories.entry(parent).or_default().insert(name);
+            path = parent;
+        }
+        directories.entry("").or_default().insert(path);
+    }
+    let recognizers: Vec<&GarbageRecognizer> = recognizers.into_iter().collect();
+
+    let mut garbage = Vec::new();
+    for (directory, names) in &directories {
+        let listed: HashSet<String> = names.iter().map(|name| name.to_string()).collect();
+        for recognizer in &recognizers {
+            let recognized = recognizer
+                .markers()
+                .iter()
+                .any(|marker| marker.is_listed_in(&listed) == Some(true));
+            if !recognized || recognizer.command.is_some() {
+                continue;
+            }
+
+            let deletable: Vec<String> = names
+                .iter()
+                .filter(|name| {
+                    let name = HashSet::from([name.to_string()]);
+                    recognizer
+                        .delete
+                        .iter()
+                        .any(|target| target.is_listed_in(&name) == Some(true))
+                })
+                .map(|name| join(directory, name))
+                .collect();
+            let size = listing
+                .iter()
+                .filter(|entry| {
+                    deletable.iter().any(|path| {
+                        entry.name == *path || entry.name.starts_with(&format!("{}/", path))
+                    })
+                })
+                .map(|entry| entry.compressed_size)
+                .sum();
+            if deletable.is_empty() || size == 0 {
+                continue;
+            }
+            garbage.push(ArchiveGarbage {
+                archive: archive.to_path_buf(),
+                recognizer_name: recognizer.name.clone(),
+                directory: directory.to_string(),
+                deletable,
+                size,
+            });
+        }
+    }
+    garbage
+}
+
+fn join(directory: &str, name: &str) -> String {
+    match directory {
+        "" => name.to_string(),
+        directory => format!("{}/{}", directory, name),
+    }
+}
+
+/// Whether the file at `path` is looked into as a zip archive.
+pub fn is_zip_archive(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+}
+
+/// Reports the garbage of the projects in the archives the scan came across, archives
+/// that can't be read are skipped.
+pub fn find_garbage_in_archives(archives: &[PathBuf], state: &AppState) -> Vec<ArchiveGarbage> {
+    let mut recognizers: Vec<&GarbageRecognizer> = state.garbage_recognizer.iter().collect();
+    recognizers.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut archives = archives.to_vec();
+    archives.sort();
+    let mut garbage = Vec::new();
+    for archive in &archives {
+        let Ok(listing) = File::open(archive).and_then(read_zip_listing) else {
+            continue;
+        };
+        garbage.extend(recognize_in_listing(
+            archive,
+            &listing,
+            recognizers.iter().copied(),
+        ));
+    }
+    garbage
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::{
+        find_garbage_in_archives, read_zip_listing, recognize_in_listing, ArchiveEntry,
+        ArchiveGarbage,
+    };
+    use crate::garbage::{find_garbage_in_directory_with_stats, FileType, GarbageRecognizer};
+    use crate::AppState;
+    use std::env::temp_dir;
+    use std::fs;
+    use std::io::{self, Cursor};
+    use std::path::Path;
+
+    /// A zip archive storing the files uncompressed, with directory entries for `/` names.
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        fn crc32(data: &[u8]) -> u32 {
+            !data.iter().fold(!0u32, |crc, byte| {
+                (0..8).fold(crc ^ *byte as u32, |crc, _| {
+                    (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg())
+                })
+            })
+        }
+
+        let mut archive = Vec::new();
+        let mut directory = Vec::new();
+        for (name, data) in files {
+            let offset = archive.len() as u32;
+            let header = |signature: u32| {
+                let mut header = signature.to_le_bytes().to_vec();
+                header.extend([20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+                header.extend(crc32(data).to_le_bytes());
+                header.extend((data.len() as u32).to_le_bytes());
+                header.extend((data.len() as u32).to_le_bytes());
+                header.extend((name.len() as u16).to_le_bytes());
+                header.extend([0, 0]);
+                header
+            };
+            archive.extend(header(0x0403_4b50));
+            archive.extend(name.as_bytes());
+            archive.extend(*data);
+
+            // the central header also has the creating version and the comment, disk,
+            // attribute and offset fields
+            let mut central = 0x0201_4b50u32.to_le_bytes().to_vec();
+            central.extend([20, 0]);
+            central.extend(&header(0)[4..]);
+            central.extend([0; 6]);
+            central.extend([0; 4]);
+            central.extend(offset.to_le_bytes());
+            central.extend(name.as_bytes());
+            directory.push(central);
+        }
+        let directory_offset = archive.len() as u32;
+        let directory: Vec<u8> = directory.concat();
+        archive.extend(&directory);
+        archive.extend(0x0605_4b50u32.to_le_bytes());
+        archive.extend([0; 4]);
+        archive.extend((files.len() as u16).to_le_bytes());
+        archive.extend((files.len() as u16).to_le_bytes());
+        archive.extend((directory.len() as u32).to_le_bytes());
+        archive.extend(directory_offset.to_le_bytes());
+        archive.extend([0, 0]);
+        archive
+    }
+
+    #[test]
+    fn test_recognize_rust_project_in_zip() {
+        let archive = zip(&[
+            ("backup/", b""),
+            ("backup/app/Cargo.toml", b"[package]"),
+            ("backup/app/src/main.rs", b"fn main() {}"),
+            ("backup/app/target/debug/app", &[0; 300]),
+            ("backup/app/target/debug/app.d", &[0; 50]),
+            ("backup/notes.txt", b"old projects"),
+        ]);
+
+        let listing = read_zip_listing(Cursor::new(archive)).expect("Failed to read zip");
+        assert_eq!(listing.len(), 6);
+        assert_eq!(
+            listing[3],
+            ArchiveEntry {
+                name: "backup/app/target/debug/app".to_string(),
+                compressed_size: 300,
+            }
+        );
+
+        let recognizer = GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        );
+        let archive_path = Path::new("/backups/2019.zip");
+        assert_eq!(
+            recognize_in_listing(archive_path, &listing, [&recognizer]),
+            vec![ArchiveGarbage {
+                archive: archive_path.to_path_buf(),
+                recognizer_name: "Rust".to_string(),
+                directory: "backup/app".to_string(),
+                deletable: vec!["backup/app/target".to_string()],
+                size: 350,
+            }]
+        );
+
+        assert!(read_zip_listing(Cursor::new(b"not a zip".to_vec())).is_err());
+        assert!(read_zip_listing(Cursor::new(vec![0; 100])).is_err());
+    }
+
+    #[test]
+    fn test_central_directory_beyond_the_archive_is_rejected() {
+        let mut archive = zip(&[("app/Cargo.toml", b"[package]")]);
+        // claim a 4 GiB central directory
+        let size_field = archive.len() - 10;
+        archive[size_field..size_field + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let error = read_zip_listing(Cursor::new(archive)).expect_err("A corrupt zip must fail");
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_archives_come_from_the_scan_walk() {
+        let path = temp_dir().join("wsg_dev_archives_walk");
+        let project = path.join("app");
+        fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+        fs::write(project.join("Cargo.toml"), "").expect("Failed to create test file");
+        let archive = zip(&[
+            ("old/Cargo.toml", b"[package]"),
+            ("old/target/app", &[0; 100]),
+        ]);
+        fs::write(path.join("backup.zip"), &archive).expect("Failed to create test file");
+        // deleted along with the target directory, not reported on its own
+        fs::write(project.join("target").join("bundle.zip"), &archive)
+            .expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        state.inside_archives = true;
+        let scan = find_garbage_in_directory_with_stats(&path, &state).expect("Failed to scan");
+        let canonical = fs::canonicalize(&path).expect("Failed to canonicalize");
+        assert_eq!(scan.archives, vec![canonical.join("backup.zip")]);
+
+        let garbage = find_garbage_in_archives(&scan.archives, &state);
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].deletable, vec!["old/target".to_string()]);
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+}
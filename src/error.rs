@@ -1,4 +1,6 @@
+use crate::utils::format_count;
 use std::fmt::{Debug, Display, Formatter};
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum GarbageError {
@@ -6,6 +8,14 @@ pub enum GarbageError {
     WalkdirError(walkdir::Error),
     SerializationError(serde_json::Error),
     InvalidCache,
+    CacheLocked,
+    CommandFailed(String),
+    RecognizerFileError(String),
+    ScanTimedOut,
+    PromptTimedOut,
+    PartialClean(usize),
+    Strict(String),
+    GarbageFound(usize),
 }
 
 impl Display for GarbageError {
@@ -15,6 +25,61 @@ impl Display for GarbageError {
             GarbageError::WalkdirError(error) => write!(f, "Directory recursive error: {}", error),
             GarbageError::SerializationError(error) => write!(f, "Serialization error: {}", error),
             GarbageError::InvalidCache => write!(f, "Invalid cache"),
+            GarbageError::CacheLocked => write!(
+                f,
+                "Another wsg process is scanning this path, try again later or use --wait"
+            ),
+            GarbageError::RecognizerFileError(message) => {
+                write!(f, "Invalid recognizer file: {}", message)
+            }
+            GarbageError::ScanTimedOut => {
+                write!(f, "Scan timed out, results are partial and were not cached")
+            }
+            GarbageError::PromptTimedOut => {
+                write!(f, "No response to the prompt, nothing was deleted")
+            }
+            GarbageError::Strict(warning) => write!(f, "{}, failing because of --strict", warning),
+            GarbageError::GarbageFound(projects) => {
+                write!(
+                    f,
+                    "Garbage found in {}, the check failed",
+                    format_count(*projects as u64, "project", "projects")
+                )
+            }
+            GarbageError::PartialClean(failed) => {
+                write!(
+                    f,
+                    "{} could not be deleted, the clean is partial",
+                    format_count(*failed as u64, "path", "paths")
+                )
+            }
+            GarbageError::CommandFailed(program) => {
+                write!(
+                    f,
+                    "The command {} did not report a reclaimable size",
+                    program
+                )
+            }
+        }
+    }
+}
+
+impl GarbageError {
+    /// A stable identifier of the error kind to branch on instead of the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            GarbageError::IOError(_) => "io",
+            GarbageError::WalkdirError(_) => "walk",
+            GarbageError::SerializationError(_) => "serialization",
+            GarbageError::InvalidCache => "invalid_cache",
+            GarbageError::CacheLocked => "cache_locked",
+            GarbageError::CommandFailed(_) => "command_failed",
+            GarbageError::RecognizerFileError(_) => "recognizer_file",
+            GarbageError::ScanTimedOut => "scan_timed_out",
+            GarbageError::PromptTimedOut => "prompt_timed_out",
+            GarbageError::PartialClean(_) => "partial_clean",
+            GarbageError::Strict(_) => "strict",
+            GarbageError::GarbageFound(_) => "garbage_found",
         }
     }
 }
@@ -40,7 +105,14 @@ impl From<serde_json::Error> for GarbageError {
 pub enum ApplicationError {
     MissingArgumentPath,
     InvalidArgumentPath,
+    NotADirectory(PathBuf),
+    UnknownRecognizer(String),
+    DangerousRecognizer(String, String),
     IdNotExists(String),
+    NotInteractive(String),
+    NoFreshListing(PathBuf),
+    NothingToClean(PathBuf),
+    OutsideScanRoot(PathBuf, PathBuf),
     GarbageError(GarbageError),
 }
 
@@ -49,19 +121,74 @@ impl Display for ApplicationError {
         match &self {
             ApplicationError::MissingArgumentPath => write!(f, "A path must be specified"),
             ApplicationError::InvalidArgumentPath => write!(f, "It must be a valid path"),
+            ApplicationError::NotADirectory(path) => write!(
+                f,
+                "{} is not a directory, please specify the directory to scan",
+                path.display()
+            ),
+            ApplicationError::UnknownRecognizer(name) => write!(
+                f,
+                "The recognizer {} does not exists, please check the available ones with --list-recognizer",
+                name
+            ),
+            ApplicationError::DangerousRecognizer(name, target) => write!(
+                f,
+                "The recognizer {} would delete {:?}, which could destroy source code, refusing to run",
+                name, target
+            ),
             ApplicationError::IdNotExists(id) => write!(
                 f,
                 "The id {} does not exists, please check if the id exists with --list",
                 id
             ),
+            ApplicationError::NotInteractive(option) => write!(
+                f,
+                "{} asks questions and needs an interactive terminal",
+                option
+            ),
+            ApplicationError::NoFreshListing(path) => write!(
+                f,
+                "There is no recent listing of {}, run --list first to see what would be deleted, or add --force to rescan and clean",
+                path.display()
+            ),
+            ApplicationError::NothingToClean(path) => {
+                write!(f, "Nothing to clean for {}.", path.display())
+            }
+            ApplicationError::OutsideScanRoot(deletable, root) => write!(
+                f,
+                "The listing of {} would delete {}, which is outside of it, refusing to clean, rescan with --list --force",
+                root.display(),
+                deletable.display()
+            ),
             ApplicationError::GarbageError(error) => write!(f, "{}", error),
         }
     }
 }
 
+impl ApplicationError {
+    /// A stable identifier of the error kind to branch on instead of the message, errors
+    /// of a scan or clean keep the code of their [`GarbageError`].
+    #[cfg(test)]
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApplicationError::MissingArgumentPath => "missing_path",
+            ApplicationError::InvalidArgumentPath => "invalid_path",
+            ApplicationError::NotADirectory(_) => "not_a_directory",
+            ApplicationError::UnknownRecognizer(_) => "unknown_recognizer",
+            ApplicationError::DangerousRecognizer(_, _) => "dangerous_recognizer",
+            ApplicationError::IdNotExists(_) => "id_not_exists",
+            ApplicationError::NotInteractive(_) => "not_interactive",
+            ApplicationError::NoFreshListing(_) => "no_fresh_listing",
+            ApplicationError::NothingToClean(_) => "nothing_to_clean",
+            ApplicationError::OutsideScanRoot(_, _) => "outside_scan_root",
+            ApplicationError::GarbageError(error) => error.code(),
+        }
+    }
+}
+
 impl Debug for ApplicationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.to_string())
+        write!(f, "{}", self)
     }
 }
 
@@ -70,3 +197,85 @@ impl From<GarbageError> for ApplicationError {
         ApplicationError::GarbageError(error)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{ApplicationError, GarbageError};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_error_codes() {
+        let walkdir_error = walkdir::WalkDir::new("/wsg_dev_error_codes_missing")
+            .into_iter()
+            .next()
+            .expect("Missing root yields an entry")
+            .expect_err("Missing root yields an error");
+        let serialization_error =
+            serde_json::from_str::<u64>("nope").expect_err("Invalid JSON parses");
+        let garbage_errors = [
+            (GarbageError::IOError(std::io::Error::other("io")), "io"),
+            (GarbageError::WalkdirError(walkdir_error), "walk"),
+            (
+                GarbageError::SerializationError(serialization_error),
+                "serialization",
+            ),
+            (GarbageError::InvalidCache, "invalid_cache"),
+            (GarbageError::CacheLocked, "cache_locked"),
+            (
+                GarbageError::CommandFailed("docker".into()),
+                "command_failed",
+            ),
+            (
+                GarbageError::RecognizerFileError("bad".into()),
+                "recognizer_file",
+            ),
+            (GarbageError::ScanTimedOut, "scan_timed_out"),
+            (GarbageError::PromptTimedOut, "prompt_timed_out"),
+            (GarbageError::PartialClean(2), "partial_clean"),
+            (GarbageError::Strict("warning".into()), "strict"),
+            (GarbageError::GarbageFound(1), "garbage_found"),
+        ];
+        for (error, code) in garbage_errors {
+            assert_eq!(error.code(), code);
+        }
+
+        let path = PathBuf::from("/projects");
+        let application_errors = [
+            (ApplicationError::MissingArgumentPath, "missing_path"),
+            (ApplicationError::InvalidArgumentPath, "invalid_path"),
+            (
+                ApplicationError::NotADirectory(path.clone()),
+                "not_a_directory",
+            ),
+            (
+                ApplicationError::UnknownRecognizer("Cobol".into()),
+                "unknown_recognizer",
+            ),
+            (
+                ApplicationError::DangerousRecognizer("Rust".into(), "src".into()),
+                "dangerous_recognizer",
+            ),
+            (ApplicationError::IdNotExists("7".into()), "id_not_exists"),
+            (
+                ApplicationError::NotInteractive("--interactive".into()),
+                "not_interactive",
+            ),
+            (
+                ApplicationError::NoFreshListing(path.clone()),
+                "no_fresh_listing",
+            ),
+            (
+                ApplicationError::NothingToClean(path.clone()),
+                "nothing_to_clean",
+            ),
+            (
+                ApplicationError::OutsideScanRoot(PathBuf::from("/elsewhere/target"), path),
+                "outside_scan_root",
+            ),
+            (GarbageError::InvalidCache.into(), "invalid_cache"),
+        ];
+        for (error, code) in application_errors {
+            assert_eq!(error.code(), code);
+        }
+    }
+}
@@ -1,20 +1,158 @@
+use crate::archive::is_zip_archive;
 use crate::error::GarbageError;
-use crate::utils::{dir_size, read_garbage_result_vec_cache};
+use crate::utils::{
+    dir_file_count, dir_size_with_parallelism, dir_size_with_strategy, disk_dir_size, format_bytes,
+    format_count, format_size, glob_match, long_path, newest_modified_in, os_path, parse_size,
+    read_garbage_result_vec_cache, scan_checkpoint_path, DuBackend, IoThrottle, SizeBackend,
+    SizeFormat, SizeParallelism, WalkBackend, DEFAULT_CACHE_DURABILITY, PARALLEL_SIZE_THRESHOLD,
+};
 use crate::AppState;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ffi::OsString;
 use std::fmt::{Display, Formatter};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{Read, Write};
 use std::num::ParseIntError;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use std::{fmt, fs, io};
 use walkdir::WalkDir;
 
-#[derive(Eq, PartialEq, Hash, Debug)]
+#[derive(Serialize, Deserialize, Eq, PartialEq, Hash, Debug)]
 pub struct GarbageRecognizer {
     pub name: String,
+    /// Other names `--include-recognizer` and `--exclude-recognizer` accept, e.g. `node`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    #[serde(default)]
     pub recognize: Vec<FileType>,
+    #[serde(default)]
     pub delete: Vec<FileType>,
+    #[serde(default)]
+    pub command: Option<RecognizerCommand>,
+    #[serde(default)]
+    pub size_strategy: SizeStrategy,
+    /// Recognizers with a higher priority are applied first and claim shared deletables.
+    #[serde(default)]
+    pub priority: i32,
+    /// How long listings with results of this recognizer stay cached, e.g. `"1h"`.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "duration_string"
+    )]
+    pub cache_ttl: Option<Duration>,
+    /// How many directories below the marker deletables are looked for, 0 only looks
+    /// next to the marker.
+    #[serde(default)]
+    pub delete_max_depth: usize,
+    /// Entries a deletable needs inside or next to it before it counts as garbage, by the
+    /// name of the deletable, e.g. a `build` directory only with a `pubspec.lock`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub requires: BTreeMap<String, Vec<FileType>>,
+}
+
+/// Name of the file in a project directory that adjusts the deletables of that project.
+pub const PROJECT_CONFIG_FILE_NAME: &str = ".wsg.toml";
+
+/// Per project adjustments of the deletables, e.g.
+///
+/// ```toml
+/// delete = [{ directory = ".cache" }]
+/// keep = ["dist"]
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Garbage of this project on top of what the recognizer deletes.
+    #[serde(default)]
+    pub delete: Vec<FileType>,
+    /// Deletables of the recognizer this project keeps, by name or path in the project.
+    #[serde(default)]
+    pub keep: Vec<String>,
+}
+
+impl ProjectConfig {
+    /// Reads the config of the project, `None` without one. Additions that could delete
+    /// the sources are refused like those of a recognizer.
+    pub fn read(project: &Path) -> Result<Option<Self>, GarbageError> {
+        let path = project.join(PROJECT_CONFIG_FILE_NAME);
+        let content = match fs::read_to_string(&path) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            content => content?,
+        };
+        let invalid = |message: String| {
+            GarbageError::RecognizerFileError(format!("{}: {}", path.display(), message))
+        };
+
+        let config: ProjectConfig =
+            toml::from_str(&content).map_err(|error| invalid(error.to_string()))?;
+        let additions = GarbageRecognizer::new(PROJECT_CONFIG_FILE_NAME, None, Some(config.delete));
+        if let Some(target) = additions.dangerous_delete_target() {
+            return Err(invalid(format!("refusing to delete {:?}", target)));
+        }
+
+        Ok(Some(ProjectConfig {
+            delete: additions.delete,
+            keep: config.keep,
+        }))
+    }
+
+    /// The deletables of the recognizer in `project` with this config applied.
+    pub fn apply(&self, project: &Path, mut deletables: Vec<PathBuf>) -> Vec<PathBuf> {
+        let kept = |path: &Path| {
+            self.keep.iter().any(|keep| {
+                path.strip_prefix(project)
+                    .is_ok_and(|relative| relative == Path::new(keep))
+                    || path.file_name().is_some_and(|name| name == keep.as_str())
+            })
+        };
+        deletables.retain(|path| !kept(path));
+
+        for path in self
+            .delete
+            .iter()
+            .flat_map(|target| target.paths_in(project))
+        {
+            if !deletables.contains(&path) {
+                deletables.push(path);
+            }
+        }
+        deletables
+    }
+}
+
+/// (De)serializes an optional duration in the format of [`parse_duration`].
+mod duration_string {
+    use crate::utils::parse_duration;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(duration) => serializer.serialize_str(&format!("{}s", duration.as_secs())),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|value| parse_duration(&value).map_err(de::Error::custom))
+            .transpose()
+    }
 }
 
 impl GarbageRecognizer {
@@ -25,30 +163,695 @@ impl GarbageRecognizer {
     ) -> Self {
         Self {
             name: name.into(),
+            aliases: vec![],
             recognize: recognize.unwrap_or_default(),
             delete: delete.unwrap_or_default(),
+            command: None,
+            size_strategy: SizeStrategy::default(),
+            priority: 0,
+            cache_ttl: None,
+            delete_max_depth: 0,
+            requires: BTreeMap::new(),
+        }
+    }
+
+    /// The delete targets of the recognizer in the project directory and, down to
+    /// `delete_max_depth`, its subdirectories. Deletables are not searched for inside
+    /// other deletables.
+    pub fn deletable_paths_in(&self, directory: &Path) -> Vec<PathBuf> {
+        let directories: Vec<PathBuf> = match self.delete_max_depth {
+            0 => vec![directory.to_path_buf()],
+            max_depth => WalkDir::new(directory)
+                .max_depth(max_depth)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_entry(|entry| entry.depth() == 0 || !self.is_delete_target(entry))
+                .filter_map(Result::ok)
+                .filter(|entry| entry.file_type().is_dir())
+                .map(walkdir::DirEntry::into_path)
+                .collect(),
+        };
+
+        self.delete
+            .iter()
+            .flat_map(|target| {
+                directories
+                    .iter()
+                    .flat_map(move |directory| target.paths_in(directory))
+            })
+            .collect()
+    }
+
+    /// Whether every entry the deletable requires is inside it or next to it, deletables
+    /// without requirements always qualify.
+    pub fn requirements_met(&self, deletable: &Path, marker_fs: &dyn MarkerFs) -> bool {
+        let name = deletable
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let parent = deletable.parent().unwrap_or(deletable);
+        self.requires
+            .iter()
+            .filter(|(pattern, _)| glob_match(pattern, &name))
+            .flat_map(|(_, required)| required)
+            .all(|required| {
+                required.is_present_in(deletable, marker_fs)
+                    || required.is_present_in(parent, marker_fs)
+            })
+    }
+
+    fn is_delete_target(&self, entry: &walkdir::DirEntry) -> bool {
+        let names = HashSet::from([entry.file_name().to_string_lossy().into_owned()]);
+        self.delete
+            .iter()
+            .any(|target| target.is_listed_in(&names) == Some(true))
+    }
+
+    /// Returns the first delete target that could destroy source code or escape the
+    /// project directory, like `src`, `.` or an absolute path.
+    pub fn dangerous_delete_target(&self) -> Option<&str> {
+        const BLOCKLIST: [&str; 7] = [".", "..", "src", "lib", ".git", ".hg", ".svn"];
+
+        self.delete
+            .iter()
+            .map(FileType::name)
+            .zip(&self.delete)
+            .find(|(name, file_type)| {
+                let path = Path::new(name.trim());
+                // a glob needs a literal part, `*` or `.*` would match the sources as well
+                let matches_anything = matches!(file_type,
+                    FileType::Glob(pattern) | FileType::Symlink(pattern)
+                    if pattern.chars().all(|c| matches!(c, '*' | '?' | '.')));
+                matches_anything
+                    || name.trim().is_empty()
+                    || BLOCKLIST.contains(&name.trim())
+                    || path.has_root()
+                    || path.is_absolute()
+                    || path
+                        .components()
+                        .any(|component| component == std::path::Component::ParentDir)
+            })
+            .map(|(name, _)| name)
+    }
+
+    #[cfg(test)]
+    pub fn with_delete_max_depth(mut self, delete_max_depth: usize) -> Self {
+        self.delete_max_depth = delete_max_depth;
+        self
+    }
+
+    #[cfg(test)]
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = Some(cache_ttl);
+        self
+    }
+
+    pub fn with_requirement(mut self, deletable: &str, requires: Vec<FileType>) -> Self {
+        self.requires.insert(deletable.to_string(), requires);
+        self
+    }
+
+    pub fn with_aliases(mut self, aliases: &[&str]) -> Self {
+        self.aliases = aliases.iter().map(|alias| alias.to_string()).collect();
+        self
+    }
+
+    /// What identifies a project of the recognizer. Without `recognize` markers any
+    /// directory containing a delete target is one, e.g. every stray `node_modules`.
+    pub fn markers(&self) -> &[FileType] {
+        match self.recognize.is_empty() {
+            true => &self.delete,
+            false => &self.recognize,
+        }
+    }
+
+    /// The name followed by the aliases of the recognizer.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+
+    pub fn with_size_strategy(mut self, size_strategy: SizeStrategy) -> Self {
+        self.size_strategy = size_strategy;
+        self
+    }
+
+    /// Delegates sizing and cleaning to an external command instead of `delete` paths.
+    #[cfg(test)]
+    pub fn with_command(mut self, command: RecognizerCommand) -> Self {
+        self.command = Some(command);
+        self
+    }
+}
+
+/// An external program run inside the project directory. The size invocation prints the
+/// reclaimable size on stdout, the clean invocation performs the actual cleanup.
+#[derive(Serialize, Deserialize, JsonSchema, Eq, PartialEq, Hash, Clone, Debug)]
+pub struct RecognizerCommand {
+    pub program: String,
+    pub size_args: Vec<String>,
+    pub clean_args: Vec<String>,
+    /// Where the size is in the output, `{}` marks it, e.g. `{} total` for the
+    /// `Summary 12 files, 1.2GiB total` of `cargo clean --dry-run`. Without a pattern
+    /// the output has to start with the size in bytes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size_pattern: Option<String>,
+}
+
+impl RecognizerCommand {
+    #[cfg(test)]
+    pub fn new<S: Into<String>>(
+        program: S,
+        size_args: Vec<String>,
+        clean_args: Vec<String>,
+    ) -> Self {
+        Self {
+            program: program.into(),
+            size_args,
+            clean_args,
+            size_pattern: None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_size_pattern(mut self, size_pattern: &str) -> Self {
+        self.size_pattern = Some(size_pattern.to_string());
+        self
+    }
+
+    /// Runs the size invocation, which is killed and fails with
+    /// [`io::ErrorKind::TimedOut`] once the deadline has passed.
+    pub fn reclaimable_bytes(
+        &self,
+        directory: &Path,
+        deadline: Option<Instant>,
+    ) -> Result<u64, GarbageError> {
+        let mut child = Command::new(&self.program)
+            .args(&self.size_args)
+            .current_dir(directory)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        // read aside, a command with a lot of output would block on the full pipe
+        let mut stdout = child.stdout.take().expect("stdout of the command is piped");
+        let reader = thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = stdout.read_to_end(&mut output);
+            output
+        });
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+            }
+            thread::sleep(COMMAND_POLL_INTERVAL);
+        };
+        let output = reader.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(GarbageError::CommandFailed(self.program.clone()));
+        }
+        size_in_output(
+            &String::from_utf8_lossy(&output),
+            self.size_pattern.as_deref(),
+        )
+        .ok_or_else(|| GarbageError::CommandFailed(self.program.clone()))
+    }
+
+    fn clean(&self, directory: &Path) -> DeleteOperationResult {
+        let status = Command::new(&self.program)
+            .args(&self.clean_args)
+            .current_dir(directory)
+            .stdin(Stdio::null())
+            .status();
+
+        match status {
+            Ok(status) if status.success() => {
+                DeleteOperationResult::success(directory.to_path_buf(), 0)
+            }
+            Ok(status) => DeleteOperationResult::failure(
+                directory.to_path_buf(),
+                Some(format!("{} exited with {}", self.program, status)),
+            ),
+            Err(e) => DeleteOperationResult::failure(directory.to_path_buf(), Some(e.to_string())),
         }
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Debug)]
+/// How often a running size command is checked against the deadline.
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// The size at the `{}` of the pattern on the first line it matches, the last word before
+/// the text following `{}` or the first word after the text preceding it. Without a
+/// pattern the first word of the output, in bytes.
+fn size_in_output(output: &str, pattern: Option<&str>) -> Option<u64> {
+    let Some(pattern) = pattern else {
+        return output.split_whitespace().next()?.parse().ok();
+    };
+    let (before, after) = pattern.split_once("{}")?;
+    output.lines().find_map(|line| {
+        let rest = &line[line.find(before)? + before.len()..];
+        let size = match after.is_empty() {
+            true => rest.split_whitespace().next(),
+            false => rest[..rest.find(after)?].split_whitespace().last(),
+        };
+        parse_size(size?).ok()
+    })
+}
+
+#[derive(Serialize, Deserialize, Eq, PartialEq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
 pub enum FileType {
     File(String),
     Directory(String),
+    /// Any entry whose name matches the pattern with `*` and `?` wildcards, e.g. `*.tf`.
+    Glob(String),
+    /// Like [`FileType::Glob`], but only symbolic links, e.g. Bazel's `bazel-*` next to
+    /// sources of the same name.
+    Symlink(String),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl FileType {
+    pub fn name(&self) -> &str {
+        match self {
+            FileType::File(value) => value,
+            FileType::Directory(value) => value,
+            FileType::Glob(value) => value,
+            FileType::Symlink(value) => value,
+        }
+    }
+
+    /// Whether the directory directly contains an entry of this file type.
+    pub fn is_present_in(&self, directory: &Path, marker_fs: &dyn MarkerFs) -> bool {
+        match self {
+            FileType::Glob(pattern) => marker_fs
+                .entry_names(directory)
+                .iter()
+                .any(|name| glob_match(pattern, name)),
+            FileType::Symlink(pattern) => marker_fs
+                .entry_names(directory)
+                .iter()
+                .filter(|name| glob_match(pattern, name))
+                .any(|name| is_symlink(&directory.join(name))),
+            file_type => marker_fs.exists(&directory.join(file_type.name())),
+        }
+    }
+
+    /// Same as [`FileType::is_present_in`] against the listed entry names of the
+    /// directory, `None` if the file type lies deeper than the listing reaches or a name
+    /// matches that may not be a symbolic link.
+    pub fn is_listed_in(&self, names: &HashSet<String>) -> Option<bool> {
+        match self {
+            FileType::Glob(pattern) => Some(names.iter().any(|name| glob_match(pattern, name))),
+            FileType::Symlink(pattern) => {
+                (!names.iter().any(|name| glob_match(pattern, name))).then_some(false)
+            }
+            file_type if Path::new(file_type.name()).components().count() == 1 => {
+                Some(names.contains(file_type.name()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The paths this file type refers to inside the directory, for globs only the
+    /// matching entries that exist, sorted by name.
+    pub fn paths_in(&self, directory: &Path) -> Vec<PathBuf> {
+        let (pattern, symlinks_only) = match self {
+            FileType::Glob(pattern) => (pattern, false),
+            FileType::Symlink(pattern) => (pattern, true),
+            _ => return vec![directory.join(self.name())],
+        };
+
+        let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter(|entry| glob_match(pattern, &entry.file_name().to_string_lossy()))
+            // the entry type of the listing itself, not of what a link points to
+            .filter(|entry| {
+                !symlinks_only
+                    || entry
+                        .file_type()
+                        .is_ok_and(|file_type| file_type.is_symlink())
+            })
+            .map(|entry| entry.path())
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_symlink())
+}
+
+/// How the scan checks a directory for the markers of the recognizers.
+#[derive(clap::ValueEnum, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum RecognizeBy {
+    /// Probe every marker of every recognizer on its own.
+    #[default]
+    Exists,
+    /// Read the entries of each directory once and look the markers up in them.
+    Listing,
+}
+
+/// Order in which a clean processes the selected results.
+#[derive(clap::ValueEnum, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum DeleteOrder {
+    /// As listed.
+    #[default]
+    Discovery,
+    /// Reclaims something early even if a later deletion fails or is interrupted.
+    Smallest,
+    /// Frees the most space as quickly as possible.
+    Largest,
+}
+
+/// Order of the results in a listing.
+#[derive(clap::ValueEnum, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ListSort {
+    /// By id, which follows the project directory.
+    #[default]
+    Id,
+    /// Largest first.
+    Size,
+    /// By recognizer name, then by id.
+    Recognizer,
+}
+
+/// Sorts the results of a listing, which come in id order, ties keep that order.
+pub fn sort_garbage(results: &mut [GarbageRecognizerResult], sort: ListSort) {
+    match sort {
+        ListSort::Id => {}
+        ListSort::Size => results.sort_by_key(|result| Reverse(result.size)),
+        ListSort::Recognizer => results.sort_by(|a, b| a.recognizer_name.cmp(&b.recognizer_name)),
+    }
+}
+
+/// Shell of a script written by `--emit-script`.
+#[derive(clap::ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ScriptShell {
+    /// POSIX shell, `rm -rf`.
+    Sh,
+    /// PowerShell, `Remove-Item`.
+    Ps1,
+}
+
+impl ScriptShell {
+    /// The path as a single argument of the shell, quoted so that nothing in it is
+    /// expanded. PowerShell can't be given a path that isn't valid UTF-8.
+    pub fn quote(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self {
+            ScriptShell::Sh => {
+                // nothing is special inside single quotes, a quote ends them: 'it'\''s'
+                let mut quoted = vec![b'\''];
+                for &byte in path_bytes(path).as_ref() {
+                    match byte {
+                        b'\'' => quoted.extend_from_slice(b"'\\''"),
+                        byte => quoted.push(byte),
+                    }
+                }
+                quoted.push(b'\'');
+                Ok(quoted)
+            }
+            ScriptShell::Ps1 => {
+                let path = path.to_str().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} is not valid UTF-8", path.display()),
+                    )
+                })?;
+                // PowerShell also ends a single quoted string at the typographic quotes
+                let mut quoted = String::from("'");
+                for ch in path.chars() {
+                    if matches!(ch, '\'' | '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}') {
+                        quoted.push(ch);
+                    }
+                    quoted.push(ch);
+                }
+                quoted.push('\'');
+                Ok(quoted.into_bytes())
+            }
+        }
+    }
+
+    fn delete_command(&self, quoted: &[u8]) -> Vec<u8> {
+        let (before, after): (&[u8], &[u8]) = match self {
+            ScriptShell::Sh => (b"rm -rf -- ", b""),
+            ScriptShell::Ps1 => (b"Remove-Item -LiteralPath ", b" -Recurse -Force"),
+        };
+        [before, quoted, after].concat()
+    }
+}
+
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().into()
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> std::borrow::Cow<'_, [u8]> {
+    path.to_string_lossy().into_owned().into_bytes().into()
+}
+
+/// Writes a script deleting the deletables of the results, with a comment naming the
+/// project and size above the commands of each result. Results cleaned by a recognizer
+/// command are only named in a comment, the script doesn't clean them.
+pub fn write_deletion_script(
+    results: &[GarbageRecognizerResult],
+    shell: ScriptShell,
+    size_format: SizeFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    // a comment ends at the line break, whatever the path holds
+    let comment = |text: String| -> String {
+        text.chars()
+            .map(|ch| if ch.is_control() { '?' } else { ch })
+            .collect()
+    };
+
+    if shell == ScriptShell::Sh {
+        writeln!(out, "#!/bin/sh")?;
+    }
+    let (by_command, deleted): (Vec<_>, Vec<_>) =
+        results.iter().partition(|result| result.by_command);
+    let total: u64 = deleted.iter().map(|result| result.size).sum();
+    writeln!(
+        out,
+        "# Generated by wsg, deletes the garbage of {}, {}",
+        format_count(deleted.len() as u64, "project", "projects"),
+        format_size(total, size_format)
+    )?;
+    for result in by_command {
+        writeln!(
+            out,
+            "# [{}] {} {} is cleaned by its command, clean it with wsg",
+            result.index,
+            comment(result.recognizer_name.clone()),
+            comment(result.directory.display().to_string()),
+        )?;
+    }
+    for result in deleted {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "# [{}] {} {}, {}",
+            result.index,
+            comment(result.recognizer_name.clone()),
+            comment(result.directory.display().to_string()),
+            format_size(result.size, size_format)
+        )?;
+        for deletable in &result.deletable {
+            out.write_all(&shell.delete_command(&shell.quote(&deletable.path)?))?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// File system queries of the recognition step, replaceable to observe them.
+pub trait MarkerFs {
+    fn exists(&self, path: &Path) -> bool;
+    /// Names of the entries directly inside the directory.
+    fn entry_names(&self, directory: &Path) -> Vec<String>;
+}
+
+struct DiskMarkerFs;
+
+impl MarkerFs for DiskMarkerFs {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn entry_names(&self, directory: &Path) -> Vec<String> {
+        fs::read_dir(directory)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect()
+    }
+}
+
+/// How the size of a deletable is measured.
+#[derive(Serialize, Deserialize, Eq, PartialEq, Hash, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum SizeStrategy {
+    /// Sum of the lengths of all files.
+    #[default]
+    Walk,
+    /// Sum of the lengths of all entries including directories, like `du --apparent-size`.
+    Apparent,
+    /// Allocated blocks with hardlinked files counted once, like `du`. Falls back to
+    /// `Apparent` on platforms without block information.
+    Blocks,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
 pub struct GarbageRecognizerResult {
     pub index: GarbageIndex,
     pub recognizer_name: String,
+    /// How many of the recognizer's markers the directory contains.
+    #[serde(default)]
+    pub matched_markers: usize,
+    #[serde(with = "os_path")]
+    #[schemars(with = "os_path::PathRepr")]
     pub directory: PathBuf,
     pub size: u64,
-    pub deletable: Vec<PathBuf>,
+    /// Set when the scan ran with `--no-size`, `size` is 0 until measured with `--size`.
+    #[serde(default)]
+    pub size_unmeasured: bool,
+    pub deletable: Vec<DeletableEntry>,
+    #[serde(default)]
+    pub modified: Option<SystemTime>,
+    /// Set when the recognizer's command cleans the directory instead of deleting paths.
+    #[serde(default)]
+    pub by_command: bool,
+    /// The command of the registered recognizer, never read from a cache, which anyone
+    /// could write. See [`attach_recognizer_commands`].
+    #[serde(skip)]
+    #[schemars(skip)]
+    pub command: Option<RecognizerCommand>,
+    /// Sum of the lengths of the files and directories in the deletables, like
+    /// [`SizeStrategy::Apparent`], only computed with `--show-both-sizes`.
+    #[serde(default)]
+    pub apparent_size: Option<u64>,
+    /// Allocated blocks on disk, only computed with `--show-both-sizes` on Unix.
+    #[serde(default)]
+    pub disk_size: Option<u64>,
+    /// Set by `--flag-over` when a single deletable is larger than the cap.
+    #[serde(default)]
+    pub flagged: bool,
+}
+
+/// A result of `recognizer_name` in `directory` for tests, with a `target` deletable of
+/// each of `deletable_sizes`.
+#[cfg(test)]
+pub(crate) fn result_fixture(
+    id: u32,
+    recognizer_name: &str,
+    directory: impl Into<PathBuf>,
+    size: u64,
+    deletable_sizes: &[u64],
+) -> GarbageRecognizerResult {
+    let directory = directory.into();
+    GarbageRecognizerResult {
+        index: GarbageIndex::Id(id),
+        recognizer_name: recognizer_name.to_string(),
+        matched_markers: 1,
+        deletable: deletable_sizes
+            .iter()
+            .map(|&size| DeletableEntry {
+                path: directory.join("target"),
+                size,
+                kind: Some(DeletableKind::Directory),
+            })
+            .collect(),
+        directory,
+        size,
+        size_unmeasured: false,
+        modified: None,
+        by_command: false,
+        command: None,
+        apparent_size: None,
+        disk_size: None,
+        flagged: false,
+    }
+}
+
+/// Looks up the command of each result cleaned by one in the registered recognizers,
+/// which only hold allowed commands. A result whose recognizer is gone or no longer runs
+/// a command is left without one and refused by the clean.
+pub fn attach_recognizer_commands<'a>(
+    results: &mut [GarbageRecognizerResult],
+    recognizers: impl IntoIterator<Item = &'a GarbageRecognizer>,
+) {
+    let commands: HashMap<&str, &RecognizerCommand> = recognizers
+        .into_iter()
+        .filter_map(|recognizer| Some((recognizer.name.as_str(), recognizer.command.as_ref()?)))
+        .collect();
+    for result in results.iter_mut().filter(|result| result.by_command) {
+        result.command = commands
+            .get(result.recognizer_name.as_str())
+            .map(|&command| command.clone());
+    }
+}
+
+/// Marks the results with a deletable larger than `cap`, e.g. a `target` that grew far
+/// beyond what a build usually leaves behind.
+pub fn flag_results_over(results: &mut [GarbageRecognizerResult], cap: u64) {
+    for result in results {
+        result.flagged = result
+            .deletable
+            .iter()
+            .any(|deletable| deletable.size > cap);
+    }
+}
+
+/// A path of a result that gets deleted, with its size by the recognizer's strategy.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Clone, Debug)]
+pub struct DeletableEntry {
+    #[serde(with = "os_path")]
+    #[schemars(with = "os_path::PathRepr")]
+    pub path: PathBuf,
+    pub size: u64,
+    /// What the path was when scanned, `None` in caches written before it was recorded.
+    #[serde(default)]
+    pub kind: Option<DeletableKind>,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DeletableKind {
+    Directory,
+    File,
+    /// Only the link is deleted, whatever it points to stays.
+    Symlink,
+}
+
+impl DeletableKind {
+    /// The kind of a path from its metadata, as read by [`fs::symlink_metadata`].
+    pub fn of(metadata: &fs::Metadata) -> Self {
+        if metadata.is_symlink() {
+            DeletableKind::Symlink
+        } else if metadata.is_dir() {
+            DeletableKind::Directory
+        } else {
+            DeletableKind::File
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Eq, Hash, Clone, Debug)]
 pub enum GarbageIndex {
     Id(u32),
+    /// The nth deletable of the result with the id, written as `<id>.<n>`.
+    Deletable(u32, usize),
     All,
 }
 
@@ -56,9 +859,10 @@ impl FromStr for GarbageIndex {
     type Err = ParseIntError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let ident = match s {
-            "all" => GarbageIndex::All,
-            _ => GarbageIndex::Id(u32::from_str(s)?),
+        let ident = match s.split_once('.') {
+            _ if s == "all" => GarbageIndex::All,
+            Some((id, n)) => GarbageIndex::Deletable(u32::from_str(id)?, usize::from_str(n)?),
+            None => GarbageIndex::Id(u32::from_str(s)?),
         };
 
         Ok(ident)
@@ -69,171 +873,1159 @@ impl Display for GarbageIndex {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             GarbageIndex::Id(id) => write!(f, "{}", id),
+            GarbageIndex::Deletable(id, n) => write!(f, "{}.{}", id, n),
             GarbageIndex::All => write!(f, "all"),
         }
     }
 }
 
+/// Scan diagnostics per recognizer, used to tune the recognizer set.
+#[derive(Default, PartialEq, Debug)]
+pub struct RecognizerStats {
+    /// Directories recognized as a project with garbage.
+    pub matched: usize,
+    /// Directories not visited because a deletable of this recognizer contains them.
+    pub skipped: usize,
+}
+
+pub type ScanStats = BTreeMap<String, RecognizerStats>;
+
+#[cfg(test)]
 pub fn find_garbage_in_directory(
     path: &Path,
     state: &AppState,
 ) -> Result<Vec<GarbageRecognizerResult>, GarbageError> {
-    let mut ignored_subdirectories = HashSet::<PathBuf>::new();
-    let mut results = Vec::<GarbageRecognizerResult>::new();
-    let mut ident_counter = 0;
-
-    for entry in WalkDir::new(path).follow_links(false) {
-        let entry = entry?;
-        let metadata = entry.metadata()?;
-        let entry_path = entry.path();
+    find_garbage_in_directory_with_stats(path, state).map(|scan| scan.results)
+}
 
-        if metadata.is_file() {
-            continue;
-        }
+/// Results of a complete or, after the scan timeout, partial scan.
+pub struct GarbageScan {
+    pub results: Vec<GarbageRecognizerResult>,
+    pub stats: ScanStats,
+    pub timed_out: bool,
+    /// Paths below the root the walk could not read, e.g. without permission.
+    pub skipped: Vec<(PathBuf, io::ErrorKind)>,
+    /// Why the projects with an invalid or refused project config were skipped.
+    pub invalid_configs: Vec<String>,
+    /// Why the size commands that failed didn't yield a result.
+    pub failed_commands: Vec<String>,
+    /// The zip archives the walk came across, only looked for with `--inside-archives`.
+    pub archives: Vec<PathBuf>,
+}
 
-        if ignored_subdirectories
+/// The recognizer whose delete target has the name of `root`, e.g. for a scan started in
+/// a `node_modules` directory, along with that target.
+pub fn deletable_named_like<'a>(
+    root: &Path,
+    recognizers: impl IntoIterator<Item = &'a GarbageRecognizer>,
+) -> Option<(&'a GarbageRecognizer, &'a FileType)> {
+    let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+    let name = root.file_name()?.to_string_lossy().into_owned();
+    let names = HashSet::from([name]);
+    recognizers.into_iter().find_map(|recognizer| {
+        recognizer
+            .delete
             .iter()
-            .any(|ignored_subdirectory| entry_path.starts_with(ignored_subdirectory))
-        {
-            continue;
-        }
+            .find(|target| target.is_listed_in(&names) == Some(true))
+            .map(|target| (recognizer, target))
+    })
+}
 
-        for recognizer in state.garbage_recognizer.iter() {
-            let mut deletable_files = Vec::new();
-            let mut directory_size = 0;
+pub fn find_garbage_in_directory_with_stats(
+    path: &Path,
+    state: &AppState,
+) -> Result<GarbageScan, GarbageError> {
+    let scanner = match state.scan_checkpoints {
+        true => GarbageScanner::checkpointed(
+            path,
+            state,
+            scan_checkpoint_path(path),
+            SCAN_CHECKPOINT_INTERVAL,
+        )?,
+        false => GarbageScanner::new(path, state),
+    };
+    collect_garbage_scan(scanner)
+}
 
-            let contains_recognitions = recognizer.recognize.iter().any(|recognition| {
-                let file_type_path = match recognition {
-                    FileType::File(value) => value,
-                    FileType::Directory(value) => value,
-                };
-                let file_path = entry_path.join(file_type_path);
-                file_path.exists()
-            });
+/// How often a checkpointed scan saves its progress.
+pub const SCAN_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(10);
 
-            let contains_deletable_content = recognizer.delete.iter().any(|recognition| {
-                let file_type_path = match recognition {
-                    FileType::File(value) => value,
-                    FileType::Directory(value) => value,
-                };
-                let deletable_content_path = entry_path.join(file_type_path);
-                if deletable_content_path.exists() {
-                    directory_size = dir_size(&deletable_content_path).unwrap_or_default();
-                    ignored_subdirectories.insert(deletable_content_path.clone());
-                    deletable_files.push(deletable_content_path.clone());
-                    true
-                } else {
-                    false
-                }
-            });
+/// A path in a [`ScanCheckpoint`], kept as raw bytes when it isn't valid UTF-8.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CheckpointPath(#[serde(with = "os_path")] pub PathBuf);
 
-            if contains_recognitions && contains_deletable_content {
-                let garbage_result = GarbageRecognizerResult {
-                    index: GarbageIndex::Id(ident_counter),
-                    recognizer_name: recognizer.name.clone(),
-                    directory: entry_path.to_path_buf(),
-                    size: directory_size,
-                    deletable: deletable_files,
-                };
-                results.push(garbage_result);
-                ident_counter += 1;
-            }
-        }
-    }
+/// The progress of an interrupted scan, the direct children of the root it walked
+/// completely and the garbage found in them.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ScanCheckpoint {
+    #[serde(with = "os_path")]
+    pub source: PathBuf,
+    /// The [`scan_fingerprint`] of the scan that wrote it.
+    #[serde(default)]
+    pub fingerprint: u64,
+    pub completed: Vec<CheckpointPath>,
+    pub results: Vec<GarbageRecognizerResult>,
+}
 
-    Ok(results)
+/// Identifies what a scan looks for, the recognizers and the options that change which
+/// results it finds. A checkpoint is only resumed by a scan with the same fingerprint.
+pub fn scan_fingerprint(state: &AppState) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    ordered_recognizers(state).hash(&mut hasher);
+    (
+        state.root_markers_only,
+        state.include_empty,
+        state.no_size,
+        state.follow_links,
+        state.show_both_sizes,
+    )
+        .hash(&mut hasher);
+    hasher.finish()
 }
 
-pub fn compute_deletable_size_from_garbage_results(results: &Vec<GarbageRecognizerResult>) -> u64 {
-    results.iter().map(|entry| &entry.size).sum()
+/// The checkpoint of the last interrupted scan of `source`, `None` without one, when it
+/// belongs to another path or another [`scan_fingerprint`], or can't be read.
+pub fn read_scan_checkpoint(
+    file: &Path,
+    source: &Path,
+    fingerprint: u64,
+) -> Result<Option<ScanCheckpoint>, GarbageError> {
+    let content = match fs::read(file) {
+        Ok(content) => content,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let Ok(checkpoint) = serde_json::from_slice::<ScanCheckpoint>(&content) else {
+        return Ok(None);
+    };
+    Ok(
+        (checkpoint.source == source && checkpoint.fingerprint == fingerprint)
+            .then_some(checkpoint),
+    )
 }
 
-pub fn clean_garbage_in_directory(
-    path: &Path,
-) -> Result<Vec<DeleteOperationSelection>, GarbageError> {
-    let result_list: Vec<GarbageRecognizerResult> = read_garbage_result_vec_cache(path, None)?;
-    clean_garbage_from_vec(result_list)
+/// Checks the results of a checkpoint against the disk before they are trusted. A result
+/// outside the root discards the whole checkpoint, which is then not one written by wsg.
+/// A result whose recognizer or deletables are gone puts its subtree back to be walked.
+fn revalidate_checkpoint(
+    mut checkpoint: ScanCheckpoint,
+    root: &Path,
+    recognizers: &[&GarbageRecognizer],
+) -> ScanCheckpoint {
+    let subtree_of = |path: &Path| -> Option<OsString> {
+        match path.strip_prefix(root).ok()?.components().next()? {
+            std::path::Component::Normal(name) => Some(name.to_os_string()),
+            _ => None,
+        }
+    };
+    let is_current = |result: &GarbageRecognizerResult| {
+        let recognizer = recognizers
+            .iter()
+            .find(|recognizer| recognizer.name == result.recognizer_name);
+        let deletables_present = result.deletable.iter().all(|deletable| {
+            deletable.path.starts_with(&result.directory)
+                && fs::symlink_metadata(&deletable.path).is_ok()
+        });
+        recognizer.is_some_and(|recognizer| recognizer.command.is_some() == result.by_command)
+            && result.directory.is_dir()
+            && deletables_present
+            && (result.by_command || !result.deletable.is_empty())
+    };
+
+    let mut stale = HashSet::new();
+    for result in &checkpoint.results {
+        let Some(subtree) = subtree_of(&result.directory) else {
+            return ScanCheckpoint {
+                source: checkpoint.source,
+                fingerprint: checkpoint.fingerprint,
+                ..Default::default()
+            };
+        };
+        let completed = checkpoint
+            .completed
+            .iter()
+            .any(|completed| completed.0.as_os_str() == subtree);
+        if !completed || !is_current(result) {
+            stale.insert(subtree);
+        }
+    }
+    checkpoint
+        .completed
+        .retain(|completed| !stale.contains(completed.0.as_os_str()));
+    checkpoint
+        .results
+        .retain(|result| subtree_of(&result.directory).is_some_and(|s| !stale.contains(&s)));
+    checkpoint
 }
 
-pub fn clean_garbage_from_vec(
-    garbage: Vec<GarbageRecognizerResult>,
-) -> Result<Vec<DeleteOperationSelection>, GarbageError> {
-    let result: Vec<DeleteOperationSelection> = garbage
-        .iter()
-        .map(|result| delete_deletable_from_garbage_recognizer_result(&result))
-        .collect();
+pub fn write_scan_checkpoint(file: &Path, checkpoint: &ScanCheckpoint) -> Result<(), GarbageError> {
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(file, serde_json::to_vec(checkpoint)?)?;
+    Ok(())
+}
 
-    Ok(result)
+/// What a checkpointed scanner tracks besides the checkpoint itself.
+struct Checkpointing {
+    file: PathBuf,
+    interval: Duration,
+    written_at: Instant,
+    progress: ScanCheckpoint,
+    /// The direct child of the root being walked, its garbage is only saved once the
+    /// walk leaves it.
+    subtree: Option<OsString>,
+    subtree_results: Vec<GarbageRecognizerResult>,
 }
 
-fn delete_deletable_from_garbage_recognizer_result(
-    result: &GarbageRecognizerResult,
-) -> DeleteOperationSelection {
-    let results: Vec<DeleteOperationResult> = result
-        .deletable
-        .iter()
-        .map(|path| match path.metadata() {
-            Ok(metadata) => {
-                if metadata.is_dir() {
-                    delete_dir(path)
-                } else if metadata.is_dir() {
-                    delete_file(path)
-                } else {
-                    DeleteOperationResult::failure(path.to_path_buf(), None)
-                }
-            }
-            Err(e) => {
-                DeleteOperationResult::failure(path.to_path_buf(), Some(e.to_string()))
+impl Checkpointing {
+    fn enter(&mut self, subtree: Option<OsString>) {
+        if let Some(completed) = std::mem::replace(&mut self.subtree, subtree) {
+            self.progress
+                .completed
+                .push(CheckpointPath(PathBuf::from(completed)));
+            self.progress.results.append(&mut self.subtree_results);
+            if self.written_at.elapsed() >= self.interval {
+                self.save();
             }
-        })
-        .collect();
+        }
+        self.subtree_results.clear();
+    }
 
-    DeleteOperationSelection::new(result.recognizer_name.to_string(), results)
+    /// A checkpoint that can't be written only costs the next `--resume` some work.
+    fn save(&mut self) {
+        let _ = write_scan_checkpoint(&self.file, &self.progress);
+        self.written_at = Instant::now();
+    }
 }
 
-fn delete_dir(path: &Path) -> DeleteOperationResult {
-    result_of_deletion(path, fs::remove_dir_all(path))
-}
+fn collect_garbage_scan(mut scanner: GarbageScanner) -> Result<GarbageScan, GarbageError> {
+    let mut results = scanner.by_ref().collect::<Result<Vec<_>, _>>()?;
 
-fn delete_file(path: &Path) -> DeleteOperationResult {
-    result_of_deletion(path, fs::remove_file(path))
+    assign_garbage_indices(&mut results);
+
+    Ok(GarbageScan {
+        results,
+        timed_out: scanner.timed_out(),
+        skipped: std::mem::take(&mut scanner.skipped),
+        invalid_configs: std::mem::take(&mut scanner.invalid_configs),
+        failed_commands: std::mem::take(&mut scanner.failed_commands),
+        archives: scanner.archives.take().unwrap_or_default(),
+        stats: scanner.into_stats(),
+    })
 }
 
-fn result_of_deletion(path: &Path, result: io::Result<()>) -> DeleteOperationResult {
-    match result {
-        Ok(_) => DeleteOperationResult::success(path.to_path_buf()),
-        Err(e) => DeleteOperationResult::failure(path.to_path_buf(), Some(e.to_string())),
-    }
+/// Measures a deletable, see [`SizeBackend`].
+pub type Sizer<'a> = dyn Fn(&Path, SizeStrategy, Option<Instant>) -> io::Result<u64> + 'a;
+
+/// The default sizer, `du` with `--fast-size` where available, otherwise the walk. `du`
+/// can't be throttled or spread over threads, so a throttle or `--size-threads` always walks.
+fn size_backend<'a>(
+    fast_size: bool,
+    parallelism: Option<SizeParallelism>,
+    throttle: Option<Arc<IoThrottle>>,
+) -> Box<Sizer<'a>> {
+    let fast_size = fast_size && throttle.is_none() && parallelism.is_none();
+    let walk = WalkBackend {
+        parallelism,
+        throttle,
+    };
+    let backend: Box<dyn SizeBackend> = match fast_size.then(|| DuBackend::detect(walk.clone())) {
+        Some(Some(du)) => Box::new(du),
+        _ => Box::new(walk),
+    };
+    Box::new(move |path, strategy, deadline| backend.size(path, strategy, deadline))
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct DeleteOperationSelection {
-    name: String,
-    result: Vec<DeleteOperationResult>,
+/// Lazily walks a directory and yields garbage as it is discovered.
+///
+/// Indices are assigned in discovery order, collect the results through
+/// [`find_garbage_in_directory_with_stats`] to get the stable numbering used by `--clean`.
+pub struct GarbageScanner<'a> {
+    root: PathBuf,
+    canonical_root: Option<PathBuf>,
+    root_markers_only: bool,
+    show_both_sizes: bool,
+    walker: Box<dyn Iterator<Item = walkdir::Result<walkdir::DirEntry>> + 'a>,
+    measure_size: bool,
+    include_empty: bool,
+    sizer: Box<Sizer<'a>>,
+    throttle: Option<Arc<IoThrottle>>,
+    recognize_by: RecognizeBy,
+    marker_fs: Box<dyn MarkerFs + 'a>,
+    deadline: Option<Instant>,
+    timed_out: bool,
+    recognizers: Vec<&'a GarbageRecognizer>,
+    ignored_subdirectories: HashMap<PathBuf, String>,
+    recognized_projects: Vec<PathBuf>,
+    pending: VecDeque<GarbageRecognizerResult>,
+    stats: ScanStats,
+    skipped: Vec<(PathBuf, io::ErrorKind)>,
+    invalid_configs: Vec<String>,
+    failed_commands: Vec<String>,
+    archives: Option<Vec<PathBuf>>,
+    ident_counter: u32,
+    checkpointing: Option<Checkpointing>,
 }
 
-impl DeleteOperationSelection {
-    pub fn new<S: Into<String>>(name: S, result: Vec<DeleteOperationResult>) -> Self {
-        Self {
-            name: name.into(),
-            result,
-        }
+impl<'a> GarbageScanner<'a> {
+    /// Walks the canonical form of `path`, so the results hold absolute paths that stay
+    /// valid for `--clean` from another working directory.
+    pub fn new(path: &Path, state: &'a AppState) -> Self {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let walker = WalkDir::new(&path)
+            .follow_links(state.follow_links)
+            .into_iter();
+        Self::with_entries(&path, state, walker)
+    }
+
+    /// Like [`GarbageScanner::new`], saving the progress to `file` every `interval` and
+    /// deleting it once the walk is complete. With `state.resume` it starts from the
+    /// checkpoint in `file`, yielding the garbage saved there that is still on disk and
+    /// skipping the subtrees it completed. The walk is in file name order, so that
+    /// checkpoints line up.
+    pub fn checkpointed(
+        path: &Path,
+        state: &'a AppState,
+        file: PathBuf,
+        interval: Duration,
+    ) -> Result<Self, GarbageError> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let fingerprint = scan_fingerprint(state);
+        let progress = match state.resume {
+            true => read_scan_checkpoint(&file, &path, fingerprint)?,
+            false => None,
+        }
+        .map(|progress| revalidate_checkpoint(progress, &path, &ordered_recognizers(state)))
+        .unwrap_or_else(|| ScanCheckpoint {
+            source: path.clone(),
+            fingerprint,
+            ..Default::default()
+        });
+        let completed: HashSet<OsString> = progress
+            .completed
+            .iter()
+            .map(|subtree| subtree.0.clone().into_os_string())
+            .collect();
+        let walker = WalkDir::new(&path)
+            .follow_links(state.follow_links)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(move |entry| {
+                entry.depth() != 1 || !completed.contains(entry.file_name())
+            });
+
+        let mut scanner = Self::with_entries(&path, state, walker);
+        for result in &progress.results {
+            let mut result = result.clone();
+            result.index = GarbageIndex::Id(scanner.ident_counter);
+            scanner.ident_counter += 1;
+            scanner.pending.push_back(result);
+        }
+        scanner.checkpointing = Some(Checkpointing {
+            file,
+            interval,
+            written_at: Instant::now(),
+            progress,
+            subtree: None,
+            subtree_results: Vec::new(),
+        });
+        Ok(scanner)
+    }
+
+    /// Scans the given directory entries instead of walking `path` itself.
+    pub fn with_entries(
+        path: &Path,
+        state: &'a AppState,
+        entries: impl Iterator<Item = walkdir::Result<walkdir::DirEntry>> + 'a,
+    ) -> Self {
+        let parallelism = state.size_threads.map(|threads| SizeParallelism {
+            threads,
+            threshold: PARALLEL_SIZE_THRESHOLD,
+        });
+        let stats = state
+            .garbage_recognizer
+            .iter()
+            .map(|recognizer| (recognizer.name.clone(), RecognizerStats::default()))
+            .collect();
+        let throttle = state
+            .io_throttle
+            .map(|reads_per_second| Arc::new(IoThrottle::new(reads_per_second)));
+
+        Self {
+            root: path.to_path_buf(),
+            canonical_root: fs::canonicalize(path).ok(),
+            root_markers_only: state.root_markers_only,
+            show_both_sizes: state.show_both_sizes,
+            walker: Box::new(entries),
+            measure_size: !state.no_size,
+            include_empty: state.include_empty,
+            sizer: size_backend(state.fast_size, parallelism, throttle.clone()),
+            throttle,
+            recognize_by: state.recognize_by,
+            marker_fs: Box::new(DiskMarkerFs),
+            deadline: state.timeout.map(|timeout| Instant::now() + timeout),
+            timed_out: false,
+            recognizers: ordered_recognizers(state),
+            ignored_subdirectories: HashMap::new(),
+            recognized_projects: Vec::new(),
+            pending: VecDeque::new(),
+            stats,
+            skipped: Vec::new(),
+            invalid_configs: Vec::new(),
+            failed_commands: Vec::new(),
+            archives: state.inside_archives.then(Vec::new),
+            ident_counter: 0,
+            checkpointing: None,
+        }
+    }
+
+    /// Statistics of the directories visited so far.
+    #[cfg(test)]
+    pub fn stats(&self) -> &ScanStats {
+        &self.stats
+    }
+
+    pub fn into_stats(self) -> ScanStats {
+        self.stats
+    }
+
+    /// Replaces how deletables are measured.
+    #[cfg(test)]
+    pub fn with_sizer(
+        mut self,
+        sizer: impl Fn(&Path, SizeStrategy, Option<Instant>) -> io::Result<u64> + 'a,
+    ) -> Self {
+        self.sizer = Box::new(sizer);
+        self
+    }
+
+    /// Replaces the file system queries used to find the project markers.
+    #[cfg(test)]
+    pub fn with_marker_fs(mut self, marker_fs: impl MarkerFs + 'a) -> Self {
+        self.marker_fs = Box::new(marker_fs);
+        self
+    }
+
+    /// Whether the scan stopped early because the timeout elapsed.
+    pub fn timed_out(&self) -> bool {
+        self.timed_out
+    }
+
+    fn deadline_passed(&mut self) -> bool {
+        self.timed_out |= self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline);
+        self.timed_out
+    }
+
+    /// With `root_markers_only`, a directory below an already recognized project is not
+    /// a project of its own, unless it is a direct child of the scan root.
+    fn is_nested_project(&self, entry_path: &Path) -> bool {
+        self.root_markers_only
+            && entry_path.parent() != Some(self.root.as_path())
+            && self
+                .recognized_projects
+                .iter()
+                .any(|project| project != entry_path && entry_path.starts_with(project))
+    }
+
+    /// Whether deleting `path` would delete the scan root, also through `..` or links.
+    fn contains_scan_root(&self, path: &Path) -> bool {
+        if self.root.starts_with(path) {
+            return true;
+        }
+        match (&self.canonical_root, fs::canonicalize(path)) {
+            (Some(root), Ok(path)) => root.starts_with(path),
+            _ => false,
+        }
+    }
+
+    fn scan_entry(&mut self, entry: walkdir::DirEntry) -> Result<(), GarbageError> {
+        let metadata = entry.metadata()?;
+        let entry_path = entry.path();
+
+        if !metadata.is_dir() {
+            if let Some(archives) = &mut self.archives {
+                // an archive inside a deletable goes with it
+                if metadata.is_file()
+                    && is_zip_archive(entry_path)
+                    && !self
+                        .ignored_subdirectories
+                        .keys()
+                        .any(|ignored_subdirectory| entry_path.starts_with(ignored_subdirectory))
+                {
+                    archives.push(entry_path.to_path_buf());
+                }
+            }
+            return Ok(());
+        }
+        if let Some(throttle) = &self.throttle {
+            throttle.acquire();
+        }
+
+        if let Some(claimed_by) = self
+            .ignored_subdirectories
+            .iter()
+            .find(|(ignored_subdirectory, _)| entry_path.starts_with(ignored_subdirectory))
+            .map(|(_, recognizer_name)| recognizer_name)
+        {
+            if let Some(recognizer_stats) = self.stats.get_mut(claimed_by) {
+                recognizer_stats.skipped += 1;
+            }
+            return Ok(());
+        }
+
+        let listing: Option<HashSet<String>> = (self.recognize_by == RecognizeBy::Listing)
+            .then(|| self.marker_fs.entry_names(entry_path).into_iter().collect());
+
+        let mut matches: Vec<(&'a GarbageRecognizer, usize)> = self
+            .recognizers
+            .iter()
+            .map(|recognizer| {
+                let matched_markers = recognizer
+                    .markers()
+                    .iter()
+                    .filter(|recognition| {
+                        listing
+                            .as_ref()
+                            .and_then(|names| recognition.is_listed_in(names))
+                            .unwrap_or_else(|| {
+                                recognition.is_present_in(entry_path, self.marker_fs.as_ref())
+                            })
+                    })
+                    .count();
+                (*recognizer, matched_markers)
+            })
+            // only a present marker claims the deletables, an unrelated `build` is walked into
+            .filter(|(_, matched_markers)| *matched_markers > 0)
+            .collect();
+
+        if self.is_nested_project(entry_path) {
+            return Ok(());
+        }
+        // with equal priorities the recognizer matching more markers claims shared deletables
+        matches.sort_by_key(|(recognizer, matched_markers)| {
+            (Reverse(recognizer.priority), Reverse(*matched_markers))
+        });
+        // a broken config of one project costs that project, not the scan
+        let config = match matches.is_empty() {
+            true => None,
+            false => match ProjectConfig::read(entry_path) {
+                Ok(config) => config,
+                Err(error) => {
+                    self.invalid_configs.push(error.to_string());
+                    return Ok(());
+                }
+            },
+        };
+
+        for (recognizer, matched_markers) in matches {
+            let mut deletable_files = Vec::new();
+            let mut directory_size = 0;
+            let mut modified = None;
+            let mut apparent_size = None;
+            let mut disk_size = None;
+
+            self.recognized_projects.push(entry_path.to_path_buf());
+
+            if let Some(command) = &recognizer.command {
+                let size = match self.measure_size {
+                    true => command.reclaimable_bytes(entry_path, self.deadline),
+                    false => Ok(0),
+                };
+                let size = match size {
+                    Err(GarbageError::IOError(error))
+                        if error.kind() == io::ErrorKind::TimedOut =>
+                    {
+                        self.timed_out = true;
+                        return Ok(());
+                    }
+                    Err(error) => {
+                        self.failed_commands.push(format!(
+                            "{} skipped {}, {}",
+                            recognizer.name,
+                            entry_path.display(),
+                            error
+                        ));
+                        None
+                    }
+                    Ok(size) => Some(size),
+                };
+                if let Some(size) = size {
+                    self.pending.push_back(GarbageRecognizerResult {
+                        index: GarbageIndex::Id(self.ident_counter),
+                        recognizer_name: recognizer.name.clone(),
+                        matched_markers,
+                        directory: entry_path.to_path_buf(),
+                        size,
+                        size_unmeasured: !self.measure_size,
+                        deletable: vec![],
+                        modified: None,
+                        by_command: true,
+                        command: Some(command.clone()),
+                        apparent_size: None,
+                        disk_size: None,
+                        flagged: false,
+                    });
+                    self.ident_counter += 1;
+                    self.stats
+                        .entry(recognizer.name.clone())
+                        .or_default()
+                        .matched += 1;
+                }
+                continue;
+            }
+
+            let mut deletable_paths = recognizer.deletable_paths_in(entry_path);
+            if let Some(config) = &config {
+                deletable_paths = config.apply(entry_path, deletable_paths);
+            }
+
+            for deletable_content_path in deletable_paths {
+                if !deletable_content_path.exists()
+                    || self
+                        .ignored_subdirectories
+                        .contains_key(&deletable_content_path)
+                    || self.contains_scan_root(&deletable_content_path)
+                    || !recognizer
+                        .requirements_met(&deletable_content_path, self.marker_fs.as_ref())
+                {
+                    continue;
+                }
+
+                let metadata = fs::symlink_metadata(&deletable_content_path).ok();
+                let is_symlink = metadata.as_ref().is_some_and(fs::Metadata::is_symlink);
+                let size = match (self.measure_size, &metadata) {
+                    (false, _) => Ok(0),
+                    // deleting a link frees the link, not what it points to, e.g. a shared cache
+                    (true, Some(metadata)) if is_symlink => Ok(metadata.len()),
+                    (true, _) => (self.sizer)(
+                        &deletable_content_path,
+                        recognizer.size_strategy,
+                        self.deadline,
+                    ),
+                };
+                let size = match size {
+                    Err(error) if error.kind() == io::ErrorKind::TimedOut => {
+                        self.timed_out = true;
+                        return Ok(());
+                    }
+                    size => size.unwrap_or_default(),
+                };
+                directory_size += size;
+                if self.measure_size && self.show_both_sizes && !is_symlink {
+                    apparent_size = add_optional_size(
+                        apparent_size,
+                        dir_size_with_strategy(&deletable_content_path, SizeStrategy::Apparent)
+                            .ok(),
+                    );
+                    disk_size =
+                        add_optional_size(disk_size, disk_dir_size(&deletable_content_path));
+                }
+                modified = metadata
+                    .as_ref()
+                    .and_then(|metadata| metadata.modified().ok())
+                    .max(modified);
+                self.ignored_subdirectories
+                    .insert(deletable_content_path.clone(), recognizer.name.clone());
+                deletable_files.push(DeletableEntry {
+                    path: deletable_content_path,
+                    size,
+                    kind: metadata.as_ref().map(DeletableKind::of),
+                });
+            }
+            // deletables that are present but empty are leftovers of an earlier clean
+            let contains_deletable_content = !deletable_files.is_empty()
+                && (directory_size > 0 || !self.measure_size || self.include_empty);
+
+            if contains_deletable_content {
+                let garbage_result = GarbageRecognizerResult {
+                    index: GarbageIndex::Id(self.ident_counter),
+                    recognizer_name: recognizer.name.clone(),
+                    matched_markers,
+                    directory: entry_path.to_path_buf(),
+                    size: directory_size,
+                    size_unmeasured: !self.measure_size,
+                    deletable: deletable_files,
+                    modified,
+                    by_command: false,
+                    command: None,
+                    apparent_size,
+                    disk_size,
+                    flagged: false,
+                };
+                self.pending.push_back(garbage_result);
+                self.ident_counter += 1;
+                self.stats
+                    .entry(recognizer.name.clone())
+                    .or_default()
+                    .matched += 1;
+            }
+        }
+
+        Ok(())
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl Iterator for GarbageScanner<'_> {
+    type Item = Result<GarbageRecognizerResult, GarbageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.pending.pop_front() {
+                if let Some(checkpointing) = &mut self.checkpointing {
+                    if checkpointing.subtree.is_some() {
+                        checkpointing.subtree_results.push(result.clone());
+                    }
+                }
+                return Some(Ok(result));
+            }
+
+            if self.deadline_passed() {
+                if let Some(checkpointing) = &mut self.checkpointing {
+                    checkpointing.save();
+                }
+                return None;
+            }
+
+            let Some(entry) = self.walker.next() else {
+                if let Some(checkpointing) = self.checkpointing.take() {
+                    let _ = fs::remove_file(&checkpointing.file);
+                }
+                return None;
+            };
+            let entry = match entry {
+                Err(error) if error.loop_ancestor().is_some() => continue,
+                // an unreadable directory below the root costs its garbage, not the scan
+                Err(error) if error.depth() > 0 && error.io_error().is_some() => {
+                    if let (Some(path), Some(io_error)) = (error.path(), error.io_error()) {
+                        self.skipped.push((path.to_path_buf(), io_error.kind()));
+                    }
+                    continue;
+                }
+                Err(error) => return Some(Err(error.into())),
+                Ok(entry) => entry,
+            };
+
+            if let Some(checkpointing) = &mut self.checkpointing {
+                if entry.depth() == 1 && entry.file_type().is_dir() {
+                    checkpointing.enter(Some(entry.file_name().to_os_string()));
+                }
+            }
+
+            if let Err(error) = self.scan_entry(entry) {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+fn add_optional_size(total: Option<u64>, size: Option<u64>) -> Option<u64> {
+    match (total, size) {
+        (Some(total), Some(size)) => Some(total + size),
+        (None, size) => size,
+        (total, None) => total,
+    }
+}
+
+/// Numbers the results by `(directory, recognizer_name)` rather than by discovery order,
+/// so the ids shown by `--list` and accepted by `--clean` are stable as long as the
+/// scanned tree is unchanged.
+fn assign_garbage_indices(results: &mut [GarbageRecognizerResult]) {
+    results.sort_by(|a, b| {
+        a.directory
+            .cmp(&b.directory)
+            .then_with(|| a.recognizer_name.cmp(&b.recognizer_name))
+    });
+    for (ident, result) in results.iter_mut().enumerate() {
+        result.index = GarbageIndex::Id(ident as u32);
+    }
+}
+
+/// Recognizers in a deterministic order, highest priority first and by name otherwise.
+fn ordered_recognizers(state: &AppState) -> Vec<&GarbageRecognizer> {
+    let mut recognizers: Vec<&GarbageRecognizer> = state.garbage_recognizer.iter().collect();
+    recognizers.sort_by(|a, b| {
+        b.priority
+            .cmp(&a.priority)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    recognizers
+}
+
+/// The shortest cache TTL among the recognizers with results, those without a
+/// `cache_ttl` count with the default durability. `None` without any results.
+pub fn effective_cache_ttl<'r>(
+    results: &[GarbageRecognizerResult],
+    recognizers: impl IntoIterator<Item = &'r GarbageRecognizer>,
+) -> Option<Duration> {
+    recognizers
+        .into_iter()
+        .filter(|recognizer| {
+            results
+                .iter()
+                .any(|result| result.recognizer_name == recognizer.name)
+        })
+        .map(|recognizer| recognizer.cache_ttl.unwrap_or(DEFAULT_CACHE_DURABILITY))
+        .min()
+}
+
+/// Measures the deletables of a result scanned with `--no-size`.
+pub fn measure_garbage_result(
+    result: &mut GarbageRecognizerResult,
+    strategy: SizeStrategy,
+    throttle: Option<&IoThrottle>,
+) {
+    if result.by_command {
+        // the command knows the size, a result read back from the cache stays unmeasured
+        let size = result
+            .command
+            .as_ref()
+            .and_then(|command| command.reclaimable_bytes(&result.directory, None).ok());
+        if let Some(size) = size {
+            result.size = size;
+            result.size_unmeasured = false;
+        }
+        return;
+    }
+    for deletable in &mut result.deletable {
+        deletable.size = match fs::symlink_metadata(&deletable.path) {
+            Ok(metadata) if metadata.is_symlink() => metadata.len(),
+            _ => dir_size_with_parallelism(&deletable.path, strategy, None, None, throttle)
+                .unwrap_or_default(),
+        };
+    }
+    result.size = result
+        .deletable
+        .iter()
+        .map(|deletable| deletable.size)
+        .sum();
+    result.size_unmeasured = false;
+}
+
+pub fn compute_deletable_size_from_garbage_results(results: &[GarbageRecognizerResult]) -> u64 {
+    results.iter().map(|entry| &entry.size).sum()
+}
+
+#[allow(dead_code)]
+pub fn clean_garbage_in_directory(
+    path: &Path,
+) -> Result<Vec<DeleteOperationSelection>, GarbageError> {
+    let result_list: Vec<GarbageRecognizerResult> = read_garbage_result_vec_cache(path, None)?;
+    clean_garbage_from_vec(result_list, DeleteOrder::Discovery, None)
+}
+
+/// Deletes the deletables of every result. When `empty_parents_root` is set, parent
+/// directories left empty by a successful deletion are removed as well, up to but not
+/// including that root.
+pub fn clean_garbage_from_vec(
+    garbage: Vec<GarbageRecognizerResult>,
+    order: DeleteOrder,
+    empty_parents_root: Option<&Path>,
+) -> Result<Vec<DeleteOperationSelection>, GarbageError> {
+    clean_garbage_from_vec_with(garbage, order, empty_parents_root, delete_path)
+}
+
+/// Removes a deletable found on disk, see [`clean_garbage_from_vec_with`].
+pub type Deleter<'a> = dyn Fn(&Path, &fs::Metadata) -> DeleteOperationResult + 'a;
+
+/// Like [`clean_garbage_from_vec`] with the removal of each deletable replaced.
+pub fn clean_garbage_from_vec_with(
+    mut garbage: Vec<GarbageRecognizerResult>,
+    order: DeleteOrder,
+    empty_parents_root: Option<&Path>,
+    deleter: impl Fn(&Path, &fs::Metadata) -> DeleteOperationResult,
+) -> Result<Vec<DeleteOperationSelection>, GarbageError> {
+    match order {
+        DeleteOrder::Discovery => {}
+        DeleteOrder::Smallest => garbage.sort_by_key(|result| result.size),
+        DeleteOrder::Largest => garbage.sort_by_key(|result| Reverse(result.size)),
+    }
+
+    let result: Vec<DeleteOperationSelection> = garbage
+        .iter()
+        .map(|result| delete_deletable_from_garbage_recognizer_result(result, &deleter))
+        .collect();
+
+    if let Some(root) = empty_parents_root {
+        result
+            .iter()
+            .flat_map(|selection| &selection.result)
+            .filter(|operation| operation.success)
+            .for_each(|operation| delete_empty_parents(&operation.path, root));
+    }
+
+    Ok(result)
+}
+
+/// Sorts the results in the order [`clean_until_free`] cleans them, the listing order
+/// frees the target with the fewest deletions so it cleans the largest first.
+pub fn order_until_free(garbage: &mut [GarbageRecognizerResult], order: DeleteOrder) {
+    match order {
+        DeleteOrder::Discovery | DeleteOrder::Largest => {
+            garbage.sort_by_key(|result| Reverse(result.size))
+        }
+        DeleteOrder::Smallest => garbage.sort_by_key(|result| result.size),
+    }
+}
+
+/// Cleans the results in [`order_until_free`] until `free_space` reports at least `target`
+/// bytes, the free space is checked again before every result so nothing more than needed
+/// is deleted. Returns the cleaned results along with their outcome.
+pub fn clean_until_free(
+    mut garbage: Vec<GarbageRecognizerResult>,
+    target: u64,
+    order: DeleteOrder,
+    empty_parents_root: Option<&Path>,
+    mut free_space: impl FnMut() -> io::Result<u64>,
+    deleter: impl Fn(&Path, &fs::Metadata) -> DeleteOperationResult,
+) -> Result<(Vec<GarbageRecognizerResult>, Vec<DeleteOperationSelection>), GarbageError> {
+    order_until_free(&mut garbage, order);
+
+    let mut cleaned = Vec::new();
+    let mut selections = Vec::new();
+    for result in garbage {
+        if free_space()? >= target {
+            break;
+        }
+        selections.push(delete_deletable_from_garbage_recognizer_result(
+            &result, &deleter,
+        ));
+        cleaned.push(result);
+    }
+
+    if let Some(root) = empty_parents_root {
+        selections
+            .iter()
+            .flat_map(|selection| &selection.result)
+            .filter(|operation| operation.success)
+            .for_each(|operation| delete_empty_parents(&operation.path, root));
+    }
+
+    Ok((cleaned, selections))
+}
+
+fn delete_empty_parents(path: &Path, root: &Path) {
+    let mut current = path.parent();
+    while let Some(dir) = current {
+        if dir == root || !dir.starts_with(root) {
+            break;
+        }
+        match fs::symlink_metadata(dir) {
+            Ok(metadata) if metadata.is_dir() => {}
+            _ => break,
+        }
+        let is_empty = fs::read_dir(dir)
+            .map(|mut entries| entries.next().is_none())
+            .unwrap_or(false);
+        // remove_dir refuses non-empty directories, so a file appearing in between is safe
+        if !is_empty || fs::remove_dir(dir).is_err() {
+            break;
+        }
+        current = dir.parent();
+    }
+}
+
+fn delete_deletable_from_garbage_recognizer_result(
+    result: &GarbageRecognizerResult,
+    deleter: &Deleter,
+) -> DeleteOperationSelection {
+    if result.by_command {
+        let results = vec![match &result.command {
+            Some(command) => command.clean(&result.directory),
+            None => DeleteOperationResult::failure(
+                result.directory.to_path_buf(),
+                Some(format!(
+                    "No allowed command of the recognizer {}, not cleaned",
+                    result.recognizer_name
+                )),
+            ),
+        }];
+        return DeleteOperationSelection::new(
+            result.recognizer_name.to_string(),
+            result.size,
+            results,
+        );
+    }
+
+    let results: Vec<DeleteOperationResult> = result
+        .deletable
+        .iter()
+        .map(|entry| (&entry.path, entry.kind))
+        .map(|(path, kind)| match fs::symlink_metadata(path) {
+            // the cached listing no longer describes the path, don't guess what to delete
+            Ok(metadata) if kind.is_some_and(|kind| kind != DeletableKind::of(&metadata)) => {
+                DeleteOperationResult::failure(
+                    path.to_path_buf(),
+                    Some("Type changed since scan, not deleted".to_string()),
+                )
+            }
+            Ok(metadata) => deleter(path, &metadata),
+            Err(e) => DeleteOperationResult::failure(path.to_path_buf(), Some(e.to_string())),
+        })
+        .collect();
+
+    DeleteOperationSelection::new(result.recognizer_name.to_string(), result.size, results)
+}
+
+/// Wraps `deleter` so every success is checked on disk, a path that is still present
+/// afterwards turns the success into a failure.
+pub fn verify_deletions(
+    deleter: impl Fn(&Path, &fs::Metadata) -> DeleteOperationResult,
+) -> impl Fn(&Path, &fs::Metadata) -> DeleteOperationResult {
+    move |path, metadata| {
+        let mut result = deleter(path, metadata);
+        if !result.success {
+            return result;
+        }
+        let gone = matches!(
+            fs::symlink_metadata(long_path(path)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound
+        );
+        if !gone {
+            result = DeleteOperationResult::failure(
+                path.to_path_buf(),
+                Some("Still present after deletion".to_string()),
+            );
+        }
+        result.verified = Some(gone);
+        result
+    }
+}
+
+pub fn delete_path(path: &Path, metadata: &fs::Metadata) -> DeleteOperationResult {
+    delete_path_with(path, metadata, false)
+}
+
+/// Like [`delete_path`], but a deletion denied by read-only entries clears their read-only
+/// attribute and is retried once.
+pub fn delete_path_forcing_readonly(path: &Path, metadata: &fs::Metadata) -> DeleteOperationResult {
+    delete_path_with(path, metadata, true)
+}
+
+fn delete_path_with(
+    path: &Path,
+    metadata: &fs::Metadata,
+    force_readonly: bool,
+) -> DeleteOperationResult {
+    if metadata.is_symlink() {
+        delete_symlink(path)
+    } else if metadata.is_dir() {
+        delete_dir(path, force_readonly)
+    } else if metadata.is_file() {
+        delete_file(path, force_readonly)
+    } else {
+        DeleteOperationResult::failure(path.to_path_buf(), None)
+    }
+}
+
+fn delete_dir(path: &Path, force_readonly: bool) -> DeleteOperationResult {
+    let fs_path = long_path(path);
+    let files = dir_file_count(fs_path.as_ref()).unwrap_or_default();
+    result_of_deletion(path, files, force_readonly, |path| fs::remove_dir_all(path))
+}
+
+fn delete_symlink(path: &Path) -> DeleteOperationResult {
+    // Windows removes a link to a directory like a directory
+    result_of_deletion(path, 1, false, |path| {
+        fs::remove_file(path).or_else(|error| fs::remove_dir(path).map_err(|_| error))
+    })
+}
+
+fn delete_file(path: &Path, force_readonly: bool) -> DeleteOperationResult {
+    result_of_deletion(path, 1, force_readonly, |path| fs::remove_file(path))
+}
+
+fn result_of_deletion(
+    path: &Path,
+    files: u64,
+    force_readonly: bool,
+    remove: impl Fn(&Path) -> io::Result<()>,
+) -> DeleteOperationResult {
+    let fs_path = long_path(path);
+    let result = match remove(&fs_path) {
+        Err(e) if force_readonly && e.kind() == io::ErrorKind::PermissionDenied => {
+            clear_readonly(&fs_path);
+            remove(&fs_path)
+        }
+        result => result,
+    };
+    match result {
+        Ok(_) => DeleteOperationResult::success(path.to_path_buf(), files),
+        Err(e) => DeleteOperationResult::failure(path.to_path_buf(), Some(e.to_string())),
+    }
+}
+
+/// Makes `path` and everything below it writable for the owner, entries that can't be
+/// changed are left for the retried deletion to report.
+fn clear_readonly(path: &Path) {
+    for entry in WalkDir::new(path).into_iter().flatten() {
+        if entry.path_is_symlink() {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let mut permissions = metadata.permissions();
+        if permissions.readonly() {
+            make_writable(&mut permissions);
+            let _ = fs::set_permissions(entry.path(), permissions);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn make_writable(permissions: &mut fs::Permissions) {
+    use std::os::unix::fs::PermissionsExt;
+    permissions.set_mode(permissions.mode() | 0o200);
+}
+
+// on Windows the attribute only guards against deletion, there are no other users to open up to
+#[cfg(not(unix))]
+#[allow(clippy::permissions_set_readonly_false)]
+fn make_writable(permissions: &mut fs::Permissions) {
+    permissions.set_readonly(false);
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct DeleteOperationSelection {
+    pub name: String,
+    pub size: u64,
+    pub result: Vec<DeleteOperationResult>,
+}
+
+impl DeleteOperationSelection {
+    pub fn new<S: Into<String>>(name: S, size: u64, result: Vec<DeleteOperationResult>) -> Self {
+        Self {
+            name: name.into(),
+            size,
+            result,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
 pub struct DeleteOperationResult {
-    path: PathBuf,
-    success: bool,
-    error_message: Option<String>,
+    #[serde(with = "os_path")]
+    #[schemars(with = "os_path::PathRepr")]
+    pub path: PathBuf,
+    pub success: bool,
+    pub error_message: Option<String>,
+    pub files: u64,
+    /// Whether the path was checked to be gone after its deletion, unset when not checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
 }
 
 impl DeleteOperationResult {
-    pub fn success(path: PathBuf) -> Self {
+    pub fn success(path: PathBuf, files: u64) -> Self {
         Self {
             path,
             success: true,
             error_message: None,
+            files,
+            verified: None,
         }
     }
 
@@ -242,20 +2034,2567 @@ impl DeleteOperationResult {
             path,
             success: false,
             error_message,
+            files: 0,
+            verified: None,
         }
     }
+
+    /// Why the deletion failed, a deletable without an error is of a type wsg can't delete.
+    pub fn failure_reason(&self) -> &str {
+        self.error_message
+            .as_deref()
+            .unwrap_or("unsupported file type")
+    }
 }
 
-pub fn filter_garbage_from_ids(
-    garbage: Vec<GarbageRecognizerResult>,
-    ids: &Vec<GarbageIndex>,
-) -> Vec<GarbageRecognizerResult> {
-    if ids.contains(&GarbageIndex::All) {
-        return garbage;
+#[derive(PartialEq, Debug)]
+pub struct CleanSummary {
+    pub files: u64,
+    pub directories: usize,
+    pub freed: u64,
+}
+
+impl CleanSummary {
+    pub fn from_selections(selections: &[DeleteOperationSelection]) -> Self {
+        let deleted = selections
+            .iter()
+            .flat_map(|selection| &selection.result)
+            .filter(|result| result.success);
+
+        Self {
+            files: deleted.clone().map(|result| result.files).sum(),
+            directories: deleted.count(),
+            freed: selections
+                .iter()
+                .filter(|selection| selection.result.iter().all(|result| result.success))
+                .map(|selection| selection.size)
+                .sum(),
+        }
     }
+}
 
-    garbage
-        .into_iter()
-        .filter(|result| ids.contains(&result.index))
-        .collect()
+/// Machine readable outcome of a clean, emitted with `--json`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+pub struct CleanReport {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub freed: u64,
+    pub selections: Vec<DeleteOperationSelection>,
+}
+
+/// The documents printed with `--json`, the garbage listed by `--list` or a dry run or
+/// the report of a clean. Only used to describe the output contract.
+#[allow(dead_code)]
+#[derive(JsonSchema)]
+#[serde(untagged)]
+enum JsonOutput {
+    GarbageResults(Vec<GarbageRecognizerResult>),
+    CleanReport(CleanReport),
+    ScanSummary(ScanSummary),
+}
+
+/// JSON schema of everything emitted with `--json`.
+pub fn json_output_schema() -> schemars::Schema {
+    schemars::schema_for!(JsonOutput)
+}
+
+impl CleanReport {
+    pub fn new(selections: Vec<DeleteOperationSelection>) -> Self {
+        Self {
+            timestamp: unix_timestamp(),
+            freed: CleanSummary::from_selections(&selections).freed,
+            selections,
+        }
+    }
+}
+
+/// Aggregate metrics of a scan, printed by `--summary`.
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct ScanSummary {
+    pub by_recognizer: BTreeMap<String, RecognizerSummary>,
+    pub total_bytes: u64,
+    /// Distinct directories with garbage, a directory matched by two recognizers counts once.
+    pub project_count: usize,
+    pub scan_duration_ms: u64,
+    #[serde(skip)]
+    pub size_format: SizeFormat,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Default, PartialEq, Debug)]
+pub struct RecognizerSummary {
+    pub count: usize,
+    pub bytes: u64,
+}
+
+impl ScanSummary {
+    pub fn new(results: &[GarbageRecognizerResult], scan_duration: Duration) -> Self {
+        let mut by_recognizer: BTreeMap<String, RecognizerSummary> = BTreeMap::new();
+        for result in results {
+            let summary = by_recognizer
+                .entry(result.recognizer_name.clone())
+                .or_default();
+            summary.count += 1;
+            summary.bytes += result.size;
+        }
+
+        Self {
+            by_recognizer,
+            total_bytes: compute_deletable_size_from_garbage_results(results),
+            project_count: results
+                .iter()
+                .map(|result| &result.directory)
+                .collect::<HashSet<_>>()
+                .len(),
+            scan_duration_ms: scan_duration.as_millis() as u64,
+            size_format: SizeFormat::default(),
+        }
+    }
+
+    /// Prints the sizes in a fixed unit or with other decimals than the default ones.
+    pub fn with_size_format(mut self, size_format: SizeFormat) -> Self {
+        self.size_format = size_format;
+        self
+    }
+}
+
+impl Display for ScanSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (name, summary) in &self.by_recognizer {
+            writeln!(
+                f,
+                "{}: {}, {}",
+                name,
+                format_count(summary.count as u64, "result", "results"),
+                format_size(summary.bytes, self.size_format)
+            )?;
+        }
+        write!(
+            f,
+            "Total: {}, {}, scanned in {:.1}s",
+            format_count(self.project_count as u64, "project", "projects"),
+            format_size(self.total_bytes, self.size_format),
+            self.scan_duration_ms as f64 / 1000.0
+        )
+    }
+}
+
+/// One line of the `--audit` time series, the reclaimable space below a scan root.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AuditRecord {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub host: String,
+    pub root: PathBuf,
+    pub duration_ms: u64,
+    /// Set when the scan timed out and the totals are incomplete.
+    pub partial: bool,
+    /// Reclaimable bytes per registered recognizer, 0 for those without garbage.
+    pub recognizers: BTreeMap<String, u64>,
+    pub total: u64,
+}
+
+impl AuditRecord {
+    pub fn new<S: Into<String>>(
+        host: S,
+        root: &Path,
+        scan: &GarbageScan,
+        duration: Duration,
+    ) -> Self {
+        let mut recognizers: BTreeMap<String, u64> =
+            scan.stats.keys().map(|name| (name.clone(), 0)).collect();
+        for result in &scan.results {
+            *recognizers
+                .entry(result.recognizer_name.clone())
+                .or_default() += result.size;
+        }
+
+        Self {
+            timestamp: unix_timestamp(),
+            host: host.into(),
+            root: root.to_path_buf(),
+            duration_ms: duration.as_millis() as u64,
+            partial: scan.timed_out,
+            total: compute_deletable_size_from_garbage_results(&scan.results),
+            recognizers,
+        }
+    }
+}
+
+/// Appends the record as a single JSON line, creating the file if needed.
+pub fn append_audit_record(file: &Path, record: &AuditRecord) -> Result<(), GarbageError> {
+    append_json_line(file, record)
+}
+
+/// Lifetime totals of a recognizer across all recorded scans.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, PartialEq)]
+pub struct RecognizerTotals {
+    pub matched: u64,
+    pub bytes: u64,
+}
+
+pub type RecognizerReport = BTreeMap<String, RecognizerTotals>;
+
+/// One line of the recognizer stats store, what a single scan found per recognizer.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StatsRecord {
+    pub timestamp: u64,
+    pub recognizers: RecognizerReport,
+}
+
+impl StatsRecord {
+    pub fn new(scan: &GarbageScan) -> Self {
+        let mut recognizers: RecognizerReport = scan
+            .stats
+            .iter()
+            .map(|(name, stats)| {
+                let totals = RecognizerTotals {
+                    matched: stats.matched as u64,
+                    bytes: 0,
+                };
+                (name.clone(), totals)
+            })
+            .collect();
+        for result in &scan.results {
+            recognizers
+                .entry(result.recognizer_name.clone())
+                .or_default()
+                .bytes += result.size;
+        }
+
+        Self {
+            timestamp: unix_timestamp(),
+            recognizers,
+        }
+    }
+}
+
+pub fn append_stats_record(file: &Path, record: &StatsRecord) -> Result<(), GarbageError> {
+    append_json_line(file, record)
+}
+
+/// Sums every record of the stats store, see [`read_json_lines`].
+pub fn read_recognizer_report(file: &Path) -> Result<RecognizerReport, GarbageError> {
+    let mut report = RecognizerReport::new();
+    for record in read_json_lines::<StatsRecord>(file)? {
+        for (name, totals) in record.recognizers {
+            let lifetime = report.entry(name).or_default();
+            lifetime.matched += totals.matched;
+            lifetime.bytes += totals.bytes;
+        }
+    }
+    Ok(report)
+}
+
+/// One clean in the local history, appended by `--clean` and shown by `--history`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub timestamp: u64,
+    pub freed: u64,
+    /// Projects with at least one deletable removed.
+    pub projects: usize,
+}
+
+impl HistoryEntry {
+    pub fn new(selections: &[DeleteOperationSelection]) -> Self {
+        Self {
+            timestamp: unix_timestamp(),
+            freed: CleanSummary::from_selections(selections).freed,
+            projects: selections
+                .iter()
+                .filter(|selection| selection.result.iter().any(|result| result.success))
+                .count(),
+        }
+    }
+}
+
+pub fn append_history_entry(file: &Path, entry: &HistoryEntry) -> Result<(), GarbageError> {
+    append_json_line(file, entry)
+}
+
+/// The cleans of the history, oldest first, see [`read_json_lines`].
+pub fn read_history(file: &Path) -> Result<Vec<HistoryEntry>, GarbageError> {
+    read_json_lines(file)
+}
+
+pub fn total_freed(history: &[HistoryEntry]) -> u64 {
+    history.iter().map(|entry| entry.freed).sum()
+}
+
+/// Parses a file of JSON lines. A missing file has no lines, lines that don't parse,
+/// e.g. from an interrupted write, are skipped.
+fn read_json_lines<T: DeserializeOwned>(file: &Path) -> Result<Vec<T>, GarbageError> {
+    let content = match fs::read_to_string(file) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        content => content?,
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn append_json_line<T: Serialize>(file: &Path, value: &T) -> Result<(), GarbageError> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(file)?
+        .write_all(line.as_bytes())?;
+    Ok(())
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+impl CleanSummary {
+    /// The summary line with the freed space in `size_format`.
+    pub fn describe(&self, size_format: SizeFormat) -> String {
+        self.describe_freed(format_size(self.freed, size_format))
+    }
+
+    fn describe_freed(&self, freed: String) -> String {
+        format!(
+            "Removed {} across {}, freed {}",
+            format_count(self.files, "file", "files"),
+            format_count(self.directories as u64, "directory", "directories"),
+            freed
+        )
+    }
+}
+
+impl Display for CleanSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe_freed(format_bytes(self.freed)))
+    }
+}
+
+pub fn filter_garbage_from_ids(
+    garbage: Vec<GarbageRecognizerResult>,
+    ids: &[GarbageIndex],
+) -> Vec<GarbageRecognizerResult> {
+    if ids.contains(&GarbageIndex::All) {
+        return garbage;
+    }
+
+    garbage
+        .into_iter()
+        .filter_map(|mut result| {
+            if ids.contains(&result.index) {
+                return Some(result);
+            }
+            let selection = selected_deletables(ids, &result.index);
+            if selection.is_empty() {
+                return None;
+            }
+            retain_deletables(&mut result, |n| selection.contains(&n));
+            Some(result)
+        })
+        .filter(|result| !result.deletable.is_empty() || result.by_command)
+        .collect()
+}
+
+/// Positions of the deletables selected with `<id>.<n>` for the result with the index.
+fn selected_deletables(ids: &[GarbageIndex], index: &GarbageIndex) -> Vec<usize> {
+    ids.iter()
+        .filter_map(|id| match (id, index) {
+            (GarbageIndex::Deletable(id, n), GarbageIndex::Id(index)) if id == index => Some(*n),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Keeps the deletables whose position matches and recomputes the size from them.
+fn retain_deletables(result: &mut GarbageRecognizerResult, keep: impl Fn(usize) -> bool) {
+    let mut position = 0;
+    result.deletable.retain(|_| {
+        position += 1;
+        keep(position - 1)
+    });
+    result.size = result.deletable.iter().map(|entry| entry.size).sum();
+    result.apparent_size = None;
+    result.disk_size = None;
+}
+
+pub fn remove_garbage_from_ids(
+    garbage: Vec<GarbageRecognizerResult>,
+    ids: &[GarbageIndex],
+) -> Vec<GarbageRecognizerResult> {
+    if ids.contains(&GarbageIndex::All) {
+        return vec![];
+    }
+
+    garbage
+        .into_iter()
+        .filter(|result| !ids.contains(&result.index))
+        .filter_map(|mut result| {
+            let selection = selected_deletables(ids, &result.index);
+            if selection.is_empty() {
+                return Some(result);
+            }
+            retain_deletables(&mut result, |n| !selection.contains(&n));
+            (!result.deletable.is_empty()).then_some(result)
+        })
+        .collect()
+}
+
+/// The ids of `cleaned` within `garbage`, as `<id>.<n>` where only part of a result's
+/// deletables was selected.
+pub fn cleaned_garbage_ids(
+    garbage: &[GarbageRecognizerResult],
+    cleaned: &[GarbageRecognizerResult],
+) -> Vec<GarbageIndex> {
+    cleaned
+        .iter()
+        .flat_map(|result| {
+            let cached = garbage.iter().find(|cached| cached.index == result.index);
+            match (cached, &result.index) {
+                (Some(cached), GarbageIndex::Id(id))
+                    if cached.deletable.len() != result.deletable.len() =>
+                {
+                    cached
+                        .deletable
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, entry)| result.deletable.contains(entry))
+                        .map(|(n, _)| GarbageIndex::Deletable(*id, n))
+                        .collect()
+                }
+                _ => vec![result.index.clone()],
+            }
+        })
+        .collect()
+}
+
+/// A result of a fresh scan that is new or larger than in the previous scan.
+#[derive(Debug)]
+pub struct GarbageChange {
+    pub result: GarbageRecognizerResult,
+    /// Size in the previous scan, `None` for garbage that wasn't there.
+    pub previous_size: Option<u64>,
+}
+
+impl GarbageChange {
+    pub fn growth(&self) -> u64 {
+        self.result.size - self.previous_size.unwrap_or_default()
+    }
+}
+
+/// The results of `current` that appeared or grew since `previous`. Results are matched
+/// by project directory and recognizer, as a directory may hold garbage of several.
+pub fn garbage_changes(
+    previous: &[GarbageRecognizerResult],
+    current: Vec<GarbageRecognizerResult>,
+) -> Vec<GarbageChange> {
+    current
+        .into_iter()
+        .filter_map(|result| {
+            let previous_size = previous
+                .iter()
+                .find(|previous| {
+                    previous.directory == result.directory
+                        && previous.recognizer_name == result.recognizer_name
+                })
+                .map(|previous| previous.size);
+            match previous_size {
+                Some(size) if result.size <= size => None,
+                previous_size => Some(GarbageChange {
+                    result,
+                    previous_size,
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Leaves out the `keep` most recently built results of each recognizer, ranked by the
+/// newest time inside their deletables like `filter_garbage_untouched_since`.
+pub fn filter_garbage_keep_newest(
+    garbage: Vec<GarbageRecognizerResult>,
+    keep: usize,
+) -> Vec<GarbageRecognizerResult> {
+    let mut groups = HashMap::<&str, Vec<&GarbageRecognizerResult>>::new();
+    for result in &garbage {
+        groups
+            .entry(result.recognizer_name.as_str())
+            .or_default()
+            .push(result);
+    }
+
+    let kept_indexes: HashSet<GarbageIndex> = groups
+        .into_values()
+        .flat_map(|mut group| {
+            group.sort_by_cached_key(|result| {
+                Reverse(
+                    result
+                        .deletable
+                        .iter()
+                        .filter_map(|deletable| newest_modified_in(&deletable.path))
+                        .max()
+                        .or(result.modified),
+                )
+            });
+            group
+                .into_iter()
+                .take(keep)
+                .map(|result| result.index.clone())
+        })
+        .collect();
+
+    garbage
+        .into_iter()
+        .filter(|result| !kept_indexes.contains(&result.index))
+        .collect()
+}
+
+/// Keeps only the results with nothing inside their deletables modified since `cutoff`,
+/// a rebuild rarely touches the time of the deletable itself. A result whose deletables
+/// can't be read falls back to its listed time, without one it is left out.
+pub fn filter_garbage_untouched_since(
+    garbage: Vec<GarbageRecognizerResult>,
+    cutoff: SystemTime,
+) -> Vec<GarbageRecognizerResult> {
+    garbage
+        .into_iter()
+        .filter(|result| {
+            result
+                .deletable
+                .iter()
+                .filter_map(|deletable| newest_modified_in(&deletable.path))
+                .max()
+                .or(result.modified)
+                .is_some_and(|modified| modified < cutoff)
+        })
+        .collect()
+}
+
+/// Leaves out the project the working directory `cwd` is in, the innermost one when
+/// projects are nested, so the build being worked on survives the clean.
+pub fn filter_garbage_preserve_active(
+    garbage: Vec<GarbageRecognizerResult>,
+    cwd: &Path,
+) -> Vec<GarbageRecognizerResult> {
+    let active = garbage
+        .iter()
+        .map(|result| &result.directory)
+        .filter(|directory| cwd.starts_with(directory))
+        .max_by_key(|directory| directory.components().count())
+        .cloned();
+
+    garbage
+        .into_iter()
+        .filter(|result| Some(&result.directory) != active.as_ref())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::garbage::{
+        append_audit_record, append_history_entry, append_stats_record, attach_recognizer_commands,
+        clean_garbage_from_vec, clean_garbage_from_vec_with, clean_until_free, cleaned_garbage_ids,
+        collect_garbage_scan, deletable_named_like, delete_path, delete_path_forcing_readonly,
+        effective_cache_ttl, filter_garbage_from_ids, filter_garbage_keep_newest,
+        filter_garbage_preserve_active, find_garbage_in_directory,
+        find_garbage_in_directory_with_stats, flag_results_over, json_output_schema,
+        measure_garbage_result, read_history, read_recognizer_report, read_scan_checkpoint,
+        remove_garbage_from_ids, result_fixture, scan_fingerprint, size_in_output, total_freed,
+        verify_deletions, write_deletion_script, AuditRecord, CheckpointPath, CleanReport,
+        CleanSummary, DeletableKind, DeleteOperationResult, DeleteOperationSelection, DeleteOrder,
+        DiskMarkerFs, FileType, GarbageIndex, GarbageRecognizer, GarbageRecognizerResult,
+        GarbageScan, GarbageScanner, HistoryEntry, MarkerFs, RecognizeBy, RecognizerCommand,
+        RecognizerStats, RecognizerSummary, RecognizerTotals, ScanSummary, ScriptShell,
+        SizeStrategy, StatsRecord, PROJECT_CONFIG_FILE_NAME,
+    };
+    use crate::recognizer::available_recognizer;
+    use crate::utils::DEFAULT_CACHE_DURABILITY;
+    use crate::AppState;
+    use std::cell::Cell;
+    use std::env::temp_dir;
+    use std::fs;
+    use std::fs::File;
+    use std::io::Write;
+    use std::ops::Sub;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime};
+    use walkdir::WalkDir;
+
+    #[test]
+    fn test_filter_garbage_keep_newest() {
+        let temp_dir = temp_dir().join("wsg_dev_keep_newest");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+
+        for age in 0..5 {
+            let project = temp_dir.join(format!("project{}", age));
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+            File::open(project.join("target"))
+                .expect("Failed to open target directory")
+                .set_modified(SystemTime::now().sub(Duration::from_secs(3600 * age)))
+                .expect("Failed to set modification time");
+        }
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 5);
+
+        let mut selected: Vec<_> = filter_garbage_keep_newest(garbage, 2)
+            .into_iter()
+            .map(|result| result.directory.file_name().unwrap().to_owned())
+            .collect();
+        selected.sort();
+        assert_eq!(selected, vec!["project2", "project3", "project4"]);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_recognizer() {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = temp_dir().join("wsg_dev_command");
+        let project = temp_dir.join("project");
+        fs::create_dir_all(&project).expect("Failed to create temporary wsg_dev directory");
+        File::create(project.join("build.marker")).expect("Failed to create test file");
+        File::create(project.join("artifact")).expect("Failed to create test file");
+
+        let script = temp_dir.join("fake-clean");
+        File::create(&script)
+            .expect("Failed to create test script")
+            .write_all(b"#!/bin/sh\nif [ \"$1\" = size ]; then echo 4242; else rm artifact; fi\n")
+            .expect("Can't write test script");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+            .expect("Failed to make test script executable");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(
+            GarbageRecognizer::new(
+                "Fake",
+                Some(vec![FileType::File("build.marker".into())]),
+                None,
+            )
+            .with_command(RecognizerCommand::new(
+                script.to_string_lossy(),
+                vec!["size".into()],
+                vec!["clean".into()],
+            )),
+        );
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].size, 4242);
+        assert_eq!(garbage[0].directory, project);
+
+        // as read back from the cache, where a planted command is ignored
+        let mut cached = serde_json::to_value(&garbage).expect("Failed to serialize");
+        cached[0]["command"] = serde_json::json!({
+            "program": "/bin/rm",
+            "size_args": [],
+            "clean_args": ["-rf", "."],
+        });
+        let mut garbage: Vec<GarbageRecognizerResult> =
+            serde_json::from_value(cached).expect("Failed to deserialize");
+        assert!(garbage[0].command.is_none());
+        let refused = clean_garbage_from_vec(garbage.clone(), DeleteOrder::Discovery, None)
+            .expect("Failed to clean");
+        assert!(!refused[0].result[0].success);
+        assert!(project.join("artifact").exists());
+
+        attach_recognizer_commands(&mut garbage, &state.garbage_recognizer);
+        clean_garbage_from_vec(garbage, DeleteOrder::Discovery, None).expect("Failed to clean");
+        assert!(!project.join("artifact").exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_garbage_follow_links() {
+        let temp_dir = temp_dir().join("wsg_dev_follow_links");
+        let project = temp_dir.join("projects").join("linked");
+        let workspace = temp_dir.join("workspace");
+        fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+        fs::create_dir_all(&workspace).expect("Failed to create test workspace");
+        File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        std::os::unix::fs::symlink(&project, workspace.join("linked"))
+            .expect("Failed to create symbolic link");
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&workspace, &state).expect("Failed to scan");
+        assert!(garbage.is_empty());
+
+        state.follow_links = true;
+        let garbage = find_garbage_in_directory(&workspace, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].directory, workspace.join("linked"));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_summary_counts_removed_files() {
+        let temp_dir = temp_dir().join("wsg_dev_clean_summary");
+        let target = temp_dir.join("project").join("target");
+        fs::create_dir_all(target.join("debug").join("deps"))
+            .expect("Failed to create test project");
+        File::create(temp_dir.join("project").join("Cargo.toml"))
+            .expect("Failed to create test file");
+        for file in ["a", "debug/b", "debug/deps/c", "debug/deps/d"] {
+            fs::write(target.join(file), [0; 100]).expect("Failed to create test file");
+        }
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        let selections =
+            clean_garbage_from_vec(garbage, DeleteOrder::Discovery, None).expect("Failed to clean");
+        let summary = CleanSummary::from_selections(&selections);
+
+        assert_eq!(
+            summary,
+            CleanSummary {
+                files: 4,
+                directories: 1,
+                freed: 400,
+            }
+        );
+        assert_eq!(
+            summary.to_string(),
+            "Removed 4 files across 1 directory, freed 400.00 B"
+        );
+        assert!(!target.exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_report_json() {
+        let selections = vec![
+            DeleteOperationSelection::new(
+                "Rust",
+                1000,
+                vec![DeleteOperationResult::success(
+                    PathBuf::from("/Users/testuser/Projects/example/target"),
+                    10,
+                )],
+            ),
+            DeleteOperationSelection::new(
+                "NodeJS",
+                500,
+                vec![DeleteOperationResult::failure(
+                    PathBuf::from("/Users/testuser/Projects/web/node_modules"),
+                    Some("Permission denied".into()),
+                )],
+            ),
+        ];
+
+        let json = serde_json::to_value(CleanReport::new(selections)).expect("Failed to serialize");
+
+        assert!(json["timestamp"].as_u64().unwrap() > 0);
+        assert_eq!(json["freed"], 1000);
+        assert_eq!(json["selections"][0]["name"], "Rust");
+        assert_eq!(json["selections"][0]["size"], 1000);
+        assert_eq!(
+            json["selections"][0]["result"][0],
+            serde_json::json!({
+                "path": "/Users/testuser/Projects/example/target",
+                "success": true,
+                "error_message": null,
+                "files": 10,
+            })
+        );
+        assert_eq!(json["selections"][1]["result"][0]["success"], false);
+        assert_eq!(
+            json["selections"][1]["result"][0]["error_message"],
+            "Permission denied"
+        );
+    }
+
+    #[test]
+    fn test_dangerous_delete_target() {
+        let recognizer = |delete: &str| {
+            GarbageRecognizer::new(
+                "Custom",
+                Some(vec![FileType::File("Cargo.toml".into())]),
+                Some(vec![
+                    FileType::Directory("target".into()),
+                    FileType::Directory(delete.into()),
+                ]),
+            )
+        };
+
+        for dangerous in [
+            "src", "lib", ".git", ".hg", ".svn", ".", "..", "", " ", "/", "/home", "../other",
+            "a/../..",
+        ] {
+            assert_eq!(
+                recognizer(dangerous).dangerous_delete_target(),
+                Some(dangerous),
+                "{:?} must be rejected",
+                dangerous
+            );
+        }
+
+        for safe in ["build", ".cache", "out/debug", "node_modules"] {
+            assert_eq!(recognizer(safe).dangerous_delete_target(), None);
+        }
+
+        let glob = |delete: &str| {
+            GarbageRecognizer::new(
+                "Custom",
+                Some(vec![FileType::Glob("*.tf".into())]),
+                Some(vec![FileType::Glob(delete.into())]),
+            )
+        };
+        for dangerous in ["*", ".*", "?*", "../*"] {
+            assert_eq!(glob(dangerous).dangerous_delete_target(), Some(dangerous));
+        }
+        assert_eq!(glob("cmake-build-*").dangerous_delete_target(), None);
+    }
+
+    #[test]
+    fn test_find_garbage_in_directory_with_stats() {
+        let temp_dir = temp_dir().join("wsg_dev_recognizer_stats");
+        for project in ["rust01", "rust02"] {
+            let project = temp_dir.join(project);
+            fs::create_dir_all(project.join("target").join("debug"))
+                .expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        }
+        let node_project = temp_dir.join("node");
+        let dependency = node_project.join("node_modules").join("dependency");
+        fs::create_dir_all(&dependency).expect("Failed to create test project");
+        File::create(node_project.join("package.json")).expect("Failed to create test file");
+        File::create(dependency.join("package.json")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        for (name, recognize, delete) in [
+            ("Rust", "Cargo.toml", "target"),
+            ("NodeJS", "package.json", "node_modules"),
+            ("Flutter", "pubspec.yaml", "build"),
+        ] {
+            state.register_garbage_recognizer(GarbageRecognizer::new(
+                name,
+                Some(vec![FileType::File(recognize.into())]),
+                Some(vec![FileType::Directory(delete.into())]),
+            ));
+        }
+
+        let scan = find_garbage_in_directory_with_stats(&temp_dir, &state).expect("Failed to scan");
+        let (garbage, stats) = (scan.results, scan.stats);
+        assert_eq!(garbage.len(), 3);
+        assert_eq!(
+            stats["Rust"],
+            RecognizerStats {
+                matched: 2,
+                skipped: 4,
+            }
+        );
+        assert_eq!(
+            stats["NodeJS"],
+            RecognizerStats {
+                matched: 1,
+                skipped: 2,
+            }
+        );
+        assert_eq!(stats["Flutter"], RecognizerStats::default());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_garbage_delete_empty_parents() {
+        let temp_dir = temp_dir().join("wsg_dev_empty_parents");
+        let empty_project = temp_dir.join("empty").join("project");
+        let kept_project = temp_dir.join("kept").join("project");
+        for project in [&empty_project, &kept_project] {
+            fs::create_dir_all(project.join("build").join("cache"))
+                .expect("Failed to create test project");
+        }
+        File::create(kept_project.join("build").join("output.log"))
+            .expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Nested",
+            Some(vec![FileType::Directory("build".into())]),
+            Some(vec![FileType::Directory("build/cache".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 2);
+        clean_garbage_from_vec(garbage, DeleteOrder::Discovery, Some(&temp_dir))
+            .expect("Failed to clean");
+
+        assert!(!temp_dir.join("empty").exists());
+        assert!(temp_dir.exists());
+        assert!(!kept_project.join("build").join("cache").exists());
+        assert!(kept_project.join("build").join("output.log").exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_find_garbage_assigns_stable_ids() {
+        let temp_dir = temp_dir().join("wsg_dev_stable_ids");
+        for project in ["zeta", "alpha", "mid"] {
+            let project = temp_dir.join(project);
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            fs::create_dir_all(project.join("node_modules"))
+                .expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+            File::create(project.join("package.json")).expect("Failed to create test file");
+        }
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        for (name, recognize, delete) in [
+            ("Rust", "Cargo.toml", "target"),
+            ("NodeJS", "package.json", "node_modules"),
+        ] {
+            state.register_garbage_recognizer(GarbageRecognizer::new(
+                name,
+                Some(vec![FileType::File(recognize.into())]),
+                Some(vec![FileType::Directory(delete.into())]),
+            ));
+        }
+
+        let ids = |garbage: Vec<GarbageRecognizerResult>| -> Vec<(String, PathBuf, String)> {
+            garbage
+                .into_iter()
+                .map(|result| {
+                    (
+                        result.index.to_string(),
+                        result.directory,
+                        result.recognizer_name,
+                    )
+                })
+                .collect()
+        };
+        let first = ids(find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan"));
+        let second = ids(find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan"));
+
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 6);
+        assert_eq!(
+            first[0],
+            (
+                "0".to_string(),
+                temp_dir.join("alpha"),
+                "NodeJS".to_string()
+            )
+        );
+        assert_eq!(
+            first[5],
+            ("5".to_string(), temp_dir.join("zeta"), "Rust".to_string())
+        );
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_garbage_scanner_iterator() {
+        let temp_dir = temp_dir().join("wsg_dev_garbage_scanner");
+        for project in ["rust01", "rust02", "rust03"] {
+            let project = temp_dir.join(project);
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        }
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let first: Vec<_> = GarbageScanner::new(&temp_dir, &state)
+            .take(2)
+            .collect::<Result<_, _>>()
+            .expect("Failed to scan");
+        assert_eq!(first.len(), 2);
+
+        let filtered: Vec<_> = GarbageScanner::new(&temp_dir, &state)
+            .filter_map(Result::ok)
+            .filter(|result| result.directory.ends_with("rust02"))
+            .collect();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(
+            filtered[0].deletable[0].path,
+            temp_dir.join("rust02").join("target")
+        );
+
+        let mut missing = GarbageScanner::new(&temp_dir.join("missing"), &state);
+        assert!(matches!(missing.next(), Some(Err(_))));
+        assert!(missing.next().is_none());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_find_garbage_root_markers_only() {
+        let temp_dir = temp_dir().join("wsg_dev_root_markers_only");
+        let project = temp_dir.join("app");
+        let decoy = project.join("docs").join("example");
+        for directory in [&project, &decoy] {
+            fs::create_dir_all(directory.join("node_modules"))
+                .expect("Failed to create test project");
+            File::create(directory.join("package.json")).expect("Failed to create test file");
+        }
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "NodeJS",
+            Some(vec![FileType::File("package.json".into())]),
+            Some(vec![FileType::Directory("node_modules".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 2);
+
+        state.root_markers_only = true;
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].directory, project);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_garbage_show_both_sizes() {
+        let temp_dir = temp_dir().join("wsg_dev_show_both_sizes");
+        let project = temp_dir.join("project");
+        let intermediates = project.join("build").join("intermediates");
+        fs::create_dir_all(&intermediates).expect("Failed to create test project");
+        File::create(project.join("pubspec.yaml")).expect("Failed to create test file");
+        File::create(project.join("build").join("sparse"))
+            .expect("Failed to create test file")
+            .set_len(10_000_000)
+            .expect("Failed to extend sparse test file");
+        fs::write(intermediates.join("classes"), [0; 100]).expect("Failed to create test file");
+        let intermediates_entry = fs::metadata(&intermediates)
+            .expect("Failed to read test directory")
+            .len();
+
+        let mut state = AppState::new();
+        state.show_both_sizes = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Flutter",
+            Some(vec![FileType::File("pubspec.yaml".into())]),
+            Some(vec![FileType::Directory("build".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        let apparent_size = garbage[0]
+            .apparent_size
+            .expect("Apparent size not computed");
+        let disk_size = garbage[0].disk_size.expect("Disk size not computed");
+        assert_eq!(apparent_size, 10_000_000 + 100 + intermediates_entry);
+        assert!(disk_size < apparent_size);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_find_garbage_deletable_sizes() {
+        let temp_dir = temp_dir().join("wsg_dev_deletable_sizes");
+        let project = temp_dir.join("project");
+        for (deletable, size) in [("__pycache__", 1_000), (".venv", 20_000), ("build", 300)] {
+            fs::create_dir_all(project.join(deletable)).expect("Failed to create test project");
+            File::create(project.join(deletable).join("file"))
+                .expect("Failed to create test file")
+                .set_len(size)
+                .expect("Failed to resize test file");
+        }
+        File::create(project.join("pyproject.toml")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Python",
+            Some(vec![FileType::File("pyproject.toml".into())]),
+            Some(vec![
+                FileType::Directory("__pycache__".into()),
+                FileType::Directory(".venv".into()),
+                FileType::Directory("build".into()),
+            ]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        let sizes: Vec<u64> = garbage[0]
+            .deletable
+            .iter()
+            .map(|entry| entry.size)
+            .collect();
+        assert_eq!(sizes, vec![1_000, 20_000, 300]);
+        assert_eq!(sizes.iter().sum::<u64>(), garbage[0].size);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_single_deletable() {
+        let temp_dir = temp_dir().join("wsg_dev_clean_single_deletable");
+        let project = temp_dir.join("project");
+        for (deletable, size) in [(".venv", 20_000), ("build", 300)] {
+            fs::create_dir_all(project.join(deletable)).expect("Failed to create test project");
+            File::create(project.join(deletable).join("file"))
+                .expect("Failed to create test file")
+                .set_len(size)
+                .expect("Failed to resize test file");
+        }
+        File::create(project.join("pyproject.toml")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Python",
+            Some(vec![FileType::File("pyproject.toml".into())]),
+            Some(vec![
+                FileType::Directory(".venv".into()),
+                FileType::Directory("build".into()),
+            ]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        let ids = ["0.0".parse::<GarbageIndex>().expect("Failed to parse id")];
+        assert_eq!(ids[0].to_string(), "0.0");
+
+        let selected = filter_garbage_from_ids(garbage.clone(), &ids);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].size, 20_000);
+        assert_eq!(cleaned_garbage_ids(&garbage, &selected), ids);
+
+        let selections = clean_garbage_from_vec(selected, DeleteOrder::Discovery, None)
+            .expect("Failed to clean");
+        assert_eq!(CleanSummary::from_selections(&selections).freed, 20_000);
+        assert!(!project.join(".venv").exists());
+        assert!(project.join("build").exists());
+
+        let remaining = remove_garbage_from_ids(garbage, &ids);
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].deletable.len(), 1);
+        assert_eq!(remaining[0].size, 300);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_garbage_scanner_timeout() {
+        let temp_dir = temp_dir().join("wsg_dev_scan_timeout");
+        for index in 0..20 {
+            let project = temp_dir.join(format!("rust{:02}", index));
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        }
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.timeout = Some(Duration::from_millis(100));
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let slow_entries = WalkDir::new(&temp_dir)
+            .into_iter()
+            .inspect(|_| thread::sleep(Duration::from_millis(20)));
+        let scanner = GarbageScanner::with_entries(&temp_dir, &state, slow_entries);
+        let scan = collect_garbage_scan(scanner).expect("Failed to scan");
+
+        assert!(scan.timed_out);
+        assert!(scan.results.len() < 20);
+
+        state.timeout = None;
+        let scan = find_garbage_in_directory_with_stats(&temp_dir, &state).expect("Failed to scan");
+        assert!(!scan.timed_out);
+        assert_eq!(scan.results.len(), 20);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_garbage_scanner_no_size() {
+        let temp_dir = temp_dir().join("wsg_dev_no_size");
+        let project = temp_dir.join("project");
+        fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+        File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        File::create(project.join("target").join("artifact"))
+            .expect("Failed to create test file")
+            .set_len(4_000)
+            .expect("Failed to resize test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let sizer_calls = Cell::new(0);
+        let scan = |state: &AppState| -> Vec<GarbageRecognizerResult> {
+            GarbageScanner::new(&temp_dir, state)
+                .with_sizer(|_, _, _| {
+                    sizer_calls.set(sizer_calls.get() + 1);
+                    Ok(42)
+                })
+                .collect::<Result<_, _>>()
+                .expect("Failed to scan")
+        };
+
+        state.no_size = true;
+        let mut garbage = scan(&state);
+        assert_eq!(sizer_calls.get(), 0);
+        assert!(garbage[0].size_unmeasured);
+        assert_eq!(garbage[0].size, 0);
+
+        measure_garbage_result(&mut garbage[0], SizeStrategy::Walk, None);
+        assert!(!garbage[0].size_unmeasured);
+        assert_eq!(garbage[0].size, 4_000);
+
+        state.no_size = false;
+        let garbage = scan(&state);
+        assert_eq!(sizer_calls.get(), 1);
+        assert_eq!(garbage[0].size, 42);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    struct CountingMarkerFs<'c> {
+        calls: &'c Cell<usize>,
+    }
+
+    impl MarkerFs for CountingMarkerFs<'_> {
+        fn exists(&self, path: &std::path::Path) -> bool {
+            self.calls.set(self.calls.get() + 1);
+            DiskMarkerFs.exists(path)
+        }
+
+        fn entry_names(&self, directory: &std::path::Path) -> Vec<String> {
+            self.calls.set(self.calls.get() + 1);
+            DiskMarkerFs.entry_names(directory)
+        }
+    }
+
+    #[test]
+    fn test_recognize_by_listing() {
+        let temp_dir = temp_dir().join("wsg_dev_recognize_by_listing");
+        for (project, marker, deletable) in [
+            ("rust", "Cargo.toml", "target"),
+            ("node", "package.json", "node_modules"),
+            ("infra", "main.tf", ".terraform"),
+            ("docs", "README.md", "build"),
+        ] {
+            fs::create_dir_all(temp_dir.join(project).join(deletable))
+                .expect("Failed to create test project");
+            File::create(temp_dir.join(project).join(marker)).expect("Failed to create test file");
+        }
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let mut scan = |recognize_by| {
+            state.recognize_by = recognize_by;
+            let calls = Cell::new(0);
+            let scanner = GarbageScanner::new(&temp_dir, &state)
+                .with_marker_fs(CountingMarkerFs { calls: &calls });
+            let results = collect_garbage_scan(scanner)
+                .expect("Failed to scan")
+                .results;
+            (
+                serde_json::to_string(&results).expect("Failed to serialize results"),
+                calls.get(),
+            )
+        };
+
+        let (exists_results, exists_calls) = scan(RecognizeBy::Exists);
+        let (listing_results, listing_calls) = scan(RecognizeBy::Listing);
+        assert_eq!(listing_results, exists_results);
+        assert!(
+            listing_calls < exists_calls,
+            "{} listing calls, {} exists calls",
+            listing_calls,
+            exists_calls
+        );
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_json_output_schema_validates_output() {
+        let schema =
+            serde_json::to_value(json_output_schema()).expect("Failed to serialize schema");
+        let validator = jsonschema::validator_for(&schema).expect("Schema is not valid");
+
+        let results = vec![GarbageRecognizerResult {
+            index: GarbageIndex::Deletable(3, 1),
+            modified: Some(std::time::SystemTime::now()),
+            by_command: true,
+            disk_size: Some(4_096),
+            ..result_fixture(0, "Rust", "/projects/wsg", 1_500, &[1_500])
+        }];
+        let report = CleanReport::new(vec![DeleteOperationSelection::new(
+            "Rust",
+            1_500,
+            vec![DeleteOperationResult::success(
+                "/projects/wsg/target".into(),
+                3,
+            )],
+        )]);
+
+        for document in [
+            serde_json::to_value(&results).expect("Failed to serialize results"),
+            serde_json::to_value(&report).expect("Failed to serialize report"),
+        ] {
+            assert!(validator.is_valid(&document), "{}", document);
+        }
+        assert!(!validator.is_valid(&serde_json::json!({ "freed": "a lot" })));
+    }
+
+    #[test]
+    fn test_clean_garbage_delete_order() {
+        let temp_dir = temp_dir().join("wsg_dev_delete_order");
+        let garbage = || -> Vec<GarbageRecognizerResult> {
+            [("medium", 200), ("small", 100), ("large", 300)]
+                .into_iter()
+                .enumerate()
+                .map(|(id, (name, size))| {
+                    let path = temp_dir.join(name).join("target");
+                    fs::create_dir_all(&path).expect("Failed to create test project");
+                    result_fixture(id as u32, name, temp_dir.join(name), size, &[size])
+                })
+                .collect()
+        };
+        let processed = |order| -> Vec<String> {
+            clean_garbage_from_vec(garbage(), order, None)
+                .expect("Failed to clean")
+                .into_iter()
+                .map(|selection| selection.name)
+                .collect()
+        };
+
+        assert_eq!(
+            processed(DeleteOrder::Discovery),
+            ["medium", "small", "large"]
+        );
+        assert_eq!(
+            processed(DeleteOrder::Smallest),
+            ["small", "medium", "large"]
+        );
+        assert_eq!(
+            processed(DeleteOrder::Largest),
+            ["large", "medium", "small"]
+        );
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_find_garbage_prefers_more_matched_markers() {
+        let temp_dir = temp_dir().join("wsg_dev_matched_markers");
+        fs::create_dir_all(temp_dir.join("build")).expect("Failed to create test project");
+        File::create(temp_dir.join("CMakeLists.txt")).expect("Failed to create test file");
+        File::create(temp_dir.join("vcpkg.json")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "CMake",
+            Some(vec![FileType::File("CMakeLists.txt".into())]),
+            Some(vec![FileType::Directory("build".into())]),
+        ));
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Vcpkg",
+            Some(vec![
+                FileType::File("CMakeLists.txt".into()),
+                FileType::File("vcpkg.json".into()),
+            ]),
+            Some(vec![FileType::Directory("build".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].recognizer_name, "Vcpkg");
+        assert_eq!(garbage[0].matched_markers, 2);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_append_audit_record() {
+        let temp_dir = temp_dir().join("wsg_dev_audit");
+        fs::create_dir_all(temp_dir.join("project").join("target"))
+            .expect("Failed to create test project");
+        File::create(temp_dir.join("project").join("Cargo.toml"))
+            .expect("Failed to create test file");
+        File::create(temp_dir.join("project").join("target").join("binary"))
+            .expect("Failed to create test file")
+            .set_len(700)
+            .expect("Failed to resize test file");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+        let scan = find_garbage_in_directory_with_stats(&temp_dir.join("project"), &state)
+            .expect("Failed to scan");
+
+        let audit_file = temp_dir.join("audit.ndjson");
+        for _ in 0..2 {
+            let record = AuditRecord::new(
+                "build-host",
+                &temp_dir.join("project"),
+                &scan,
+                Duration::from_millis(1_250),
+            );
+            append_audit_record(&audit_file, &record).expect("Failed to append audit record");
+        }
+
+        let content = fs::read_to_string(&audit_file).expect("Failed to read audit file");
+        assert_eq!(content.lines().count(), 2);
+        for line in content.lines() {
+            let record: serde_json::Value =
+                serde_json::from_str(line).expect("Audit line is not JSON");
+            assert!(record["timestamp"].as_u64().is_some());
+            assert_eq!(record["host"], "build-host");
+            assert_eq!(
+                record["root"],
+                temp_dir.join("project").display().to_string()
+            );
+            assert_eq!(record["duration_ms"], 1_250);
+            assert_eq!(record["partial"], false);
+            assert_eq!(record["recognizers"]["Rust"], 700);
+            assert_eq!(record["recognizers"]["NodeJS"], 0);
+            assert_eq!(record["total"], 700);
+        }
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_effective_cache_ttl() {
+        let recognizer = |name: &str| {
+            GarbageRecognizer::new(
+                name,
+                Some(vec![FileType::File("marker".into())]),
+                Some(vec![FileType::Directory("build".into())]),
+            )
+        };
+        let recognizers = [
+            recognizer("Flutter").with_cache_ttl(Duration::from_secs(7 * 24 * 60 * 60)),
+            recognizer("NodeJS").with_cache_ttl(Duration::from_secs(10 * 60)),
+            recognizer("Rust"),
+        ];
+        let results = |names: &[&str]| -> Vec<GarbageRecognizerResult> {
+            names
+                .iter()
+                .map(|name| result_fixture(0, name, temp_dir(), 0, &[]))
+                .collect()
+        };
+
+        assert_eq!(
+            effective_cache_ttl(&results(&["Flutter", "NodeJS"]), &recognizers),
+            Some(Duration::from_secs(10 * 60))
+        );
+        assert_eq!(
+            effective_cache_ttl(&results(&["Flutter"]), &recognizers),
+            Some(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            effective_cache_ttl(&results(&["Flutter", "Rust"]), &recognizers),
+            Some(DEFAULT_CACHE_DURABILITY)
+        );
+        assert_eq!(effective_cache_ttl(&[], &recognizers), None);
+    }
+
+    #[test]
+    fn test_scan_summary() {
+        let result =
+            |name: &str, directory: &str, size: u64| result_fixture(0, name, directory, size, &[]);
+        let results = vec![
+            result("Rust", "/projects/api", 100),
+            result("Rust", "/projects/cli", 200),
+            result("NodeJS", "/projects/api", 50),
+        ];
+
+        let summary = ScanSummary::new(&results, Duration::from_millis(1_500));
+        assert_eq!(
+            summary.by_recognizer["Rust"],
+            RecognizerSummary {
+                count: 2,
+                bytes: 300
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&summary).expect("Failed to serialize summary"),
+            serde_json::json!({
+                "by_recognizer": {
+                    "NodeJS": { "count": 1, "bytes": 50 },
+                    "Rust": { "count": 2, "bytes": 300 },
+                },
+                "total_bytes": 350,
+                "project_count": 2,
+                "scan_duration_ms": 1_500,
+            })
+        );
+        assert_eq!(
+            summary.to_string(),
+            "NodeJS: 1 result, 50.00 B\nRust: 2 results, 300.00 B\nTotal: 2 projects, 350.00 B, scanned in 1.5s"
+        );
+    }
+
+    #[test]
+    fn test_clean_refuses_deletable_type_changed_since_scan() {
+        let temp_dir = temp_dir().join("wsg_dev_type_changed");
+        let project = temp_dir.join("project");
+        fs::create_dir_all(project.join("target").join("debug"))
+            .expect("Failed to create test project");
+        File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        File::create(project.join("heap.hprof")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![
+                FileType::Directory("target".into()),
+                FileType::Glob("*.hprof".into()),
+            ]),
+        ));
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        let kinds: Vec<_> = garbage[0].deletable.iter().map(|d| d.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![Some(DeletableKind::Directory), Some(DeletableKind::File)]
+        );
+
+        fs::remove_dir_all(project.join("target")).expect("Failed to remove test directory");
+        fs::write(project.join("target"), "not a build directory").expect("Failed to write file");
+
+        let selections =
+            clean_garbage_from_vec(garbage, DeleteOrder::Discovery, None).expect("Failed to clean");
+        let results = &selections[0].result;
+        assert!(!results[0].success);
+        assert_eq!(
+            results[0].error_message.as_deref(),
+            Some("Type changed since scan, not deleted")
+        );
+        assert!(project.join("target").is_file());
+        assert!(results[1].success);
+        assert!(!project.join("heap.hprof").exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_verify_deletions_catches_path_left_behind() {
+        let temp_dir = temp_dir().join("wsg_dev_verify_deletions");
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let scan = || {
+            for project in ["stuck", "gone"] {
+                let target = temp_dir.join(project).join("target");
+                fs::create_dir_all(&target).expect("Failed to create test project");
+                fs::write(target.join("artifact"), vec![0; 100])
+                    .expect("Failed to create test file");
+                File::create(temp_dir.join(project).join("Cargo.toml"))
+                    .expect("Failed to create test file");
+            }
+            let mut garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+            garbage.sort_by(|a, b| a.directory.cmp(&b.directory));
+            garbage
+        };
+        let stuck = temp_dir.join("stuck");
+        // reports success like a network mount that lost the removal
+        let flaky_delete = |path: &Path, metadata: &fs::Metadata| match path.starts_with(&stuck) {
+            true => DeleteOperationResult::success(path.to_path_buf(), 1),
+            false => delete_path(path, metadata),
+        };
+
+        let selections =
+            clean_garbage_from_vec_with(scan(), DeleteOrder::Discovery, None, flaky_delete)
+                .expect("Failed to clean");
+        assert!(selections
+            .iter()
+            .all(|selection| selection.result[0].success));
+        assert!(selections
+            .iter()
+            .all(|selection| selection.result[0].verified.is_none()));
+
+        let selections = clean_garbage_from_vec_with(
+            scan(),
+            DeleteOrder::Discovery,
+            None,
+            verify_deletions(flaky_delete),
+        )
+        .expect("Failed to clean");
+        let gone = &selections[0].result[0];
+        assert!(gone.success);
+        assert_eq!(gone.verified, Some(true));
+        let left = &selections[1].result[0];
+        assert_eq!(left.path, stuck.join("target"));
+        assert!(!left.success);
+        assert_eq!(left.verified, Some(false));
+        assert_eq!(
+            left.error_message.as_deref(),
+            Some("Still present after deletion")
+        );
+        assert_eq!(CleanSummary::from_selections(&selections).freed, 100);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_force_readonly_deletes_readonly_files() {
+        let temp_dir = temp_dir().join("wsg_dev_force_readonly");
+        let target = temp_dir.join("target");
+        let sealed = target.join("sealed");
+        fs::create_dir_all(&sealed).expect("Failed to create test project");
+        File::create(temp_dir.join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(sealed.join("artifact"), vec![0; 100]).expect("Failed to create test file");
+        let set_readonly = |path: &Path| {
+            let mut permissions = fs::metadata(path)
+                .expect("Failed to read test file")
+                .permissions();
+            permissions.set_readonly(true);
+            fs::set_permissions(path, permissions).expect("Failed to make test file read-only");
+        };
+        // Windows refuses to delete a read-only file, unix one in a read-only directory
+        set_readonly(&sealed.join("artifact"));
+        set_readonly(&sealed);
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+
+        let selections =
+            clean_garbage_from_vec_with(garbage.clone(), DeleteOrder::Discovery, None, delete_path)
+                .expect("Failed to clean");
+        // permissions aren't enforced for everyone, e.g. root
+        if !selections[0].result[0].success {
+            assert!(sealed.join("artifact").exists());
+
+            let selections = clean_garbage_from_vec_with(
+                garbage,
+                DeleteOrder::Discovery,
+                None,
+                delete_path_forcing_readonly,
+            )
+            .expect("Failed to clean");
+            assert!(selections[0].result[0].success);
+        }
+        assert!(!target.exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_find_garbage_skips_empty_deletables() {
+        let temp_dir = temp_dir().join("wsg_dev_include_empty");
+        let cleaned = temp_dir.join("cleaned");
+        let built = temp_dir.join("built");
+        for project in [&cleaned, &built] {
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        }
+        fs::write(built.join("target").join("binary"), [0; 100])
+            .expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].directory, built);
+
+        state.include_empty = true;
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        let mut directories: Vec<_> = garbage.into_iter().map(|result| result.directory).collect();
+        directories.sort();
+        assert_eq!(directories, vec![built, cleaned]);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_recognizer_stats_store() {
+        let temp_dir = temp_dir().join("wsg_dev_stats_store");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+        let file = temp_dir.join("recognizer-stats.ndjson");
+        assert!(read_recognizer_report(&file)
+            .expect("Failed to read missing store")
+            .is_empty());
+
+        let record = |rust: (usize, u64), node: (usize, u64)| {
+            let result = |name: &str, size: u64| result_fixture(0, name, "/projects", size, &[]);
+            let mut results = Vec::new();
+            results.extend((0..rust.0).map(|_| result("Rust", rust.1)));
+            results.extend((0..node.0).map(|_| result("NodeJS", node.1)));
+            let stats = [("Rust", rust.0), ("NodeJS", node.0)]
+                .into_iter()
+                .map(|(name, matched)| {
+                    let stats = RecognizerStats {
+                        matched,
+                        skipped: 0,
+                    };
+                    (name.to_string(), stats)
+                })
+                .collect();
+            StatsRecord::new(&GarbageScan {
+                results,
+                stats,
+                timed_out: false,
+                skipped: Vec::new(),
+                invalid_configs: Vec::new(),
+                failed_commands: Vec::new(),
+                archives: Vec::new(),
+            })
+        };
+        append_stats_record(&file, &record((2, 100), (0, 0))).expect("Failed to append record");
+        append_stats_record(&file, &record((1, 50), (3, 10))).expect("Failed to append record");
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&file)
+            .and_then(|mut file| file.write_all(b"{\"timestamp\":"))
+            .expect("Failed to write truncated record");
+
+        let report = read_recognizer_report(&file).expect("Failed to read store");
+        assert_eq!(
+            report["Rust"],
+            RecognizerTotals {
+                matched: 3,
+                bytes: 250,
+            }
+        );
+        assert_eq!(
+            report["NodeJS"],
+            RecognizerTotals {
+                matched: 3,
+                bytes: 30,
+            }
+        );
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_find_garbage_never_offers_the_scan_root() {
+        let temp_dir = temp_dir().join("wsg_dev_root_deletable");
+        fs::create_dir_all(temp_dir.join("target")).expect("Failed to create test project");
+        File::create(temp_dir.join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(temp_dir.join("target").join("binary"), [0; 100])
+            .expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![
+                FileType::Directory(temp_dir.to_string_lossy().into_owned()),
+                FileType::Directory("target".into()),
+            ]),
+        ));
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        let deletables: Vec<_> = garbage[0].deletable.iter().map(|d| &d.path).collect();
+        assert_eq!(deletables, vec![&temp_dir.join("target")]);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_find_garbage_never_offers_a_parent_of_the_scan_root() {
+        let temp_dir = temp_dir().join("wsg_dev_root_inside_deletable");
+        let package = temp_dir.join("node_modules").join("package");
+        fs::create_dir_all(&package).expect("Failed to create test project");
+        fs::write(package.join("package.json"), [0; 100]).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Parent",
+            Some(vec![FileType::File("package.json".into())]),
+            Some(vec![FileType::Directory("..".into())]),
+        ));
+        let garbage = find_garbage_in_directory(&package, &state).expect("Failed to scan");
+        assert!(garbage.is_empty());
+
+        let recognizers = available_recognizer();
+        let (recognizer, target) =
+            deletable_named_like(&package.join(".."), &recognizers).expect("No deletable found");
+        assert_eq!(recognizer.name, "NodeJS");
+        assert_eq!(target.name(), "node_modules");
+        assert!(deletable_named_like(&package, &recognizers).is_none());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_history() {
+        let temp_dir = temp_dir().join("wsg_dev_clean_history");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+        let file = temp_dir.join("clean-history.ndjson");
+        assert!(read_history(&file)
+            .expect("Failed to read history")
+            .is_empty());
+
+        let selection = |size: u64, success: bool| {
+            let result = match success {
+                true => DeleteOperationResult::success(PathBuf::from("/projects/target"), 3),
+                false => DeleteOperationResult::failure(
+                    PathBuf::from("/projects/target"),
+                    Some("busy".to_string()),
+                ),
+            };
+            DeleteOperationSelection::new("Rust", size, vec![result])
+        };
+        let entry = HistoryEntry::new(&[selection(1_000, true), selection(500, false)]);
+        assert_eq!((entry.freed, entry.projects), (1_000, 1));
+        append_history_entry(&file, &entry).expect("Failed to append history");
+
+        let line = fs::read_to_string(&file).expect("Failed to read history");
+        assert_eq!(
+            line,
+            format!(
+                "{{\"timestamp\":{},\"freed\":1000,\"projects\":1}}\n",
+                entry.timestamp
+            )
+        );
+
+        for (freed, projects) in [(2_500, 2), (42, 1)] {
+            let entry = HistoryEntry {
+                timestamp: 1_700_000_000,
+                freed,
+                projects,
+            };
+            append_history_entry(&file, &entry).expect("Failed to append history");
+        }
+        let history = read_history(&file).expect("Failed to read history");
+        assert_eq!(history.len(), 3);
+        assert_eq!(total_freed(&history), 3_542);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_find_garbage_delete_max_depth() {
+        let temp_dir = temp_dir().join("wsg_dev_delete_max_depth");
+        let build = temp_dir.join("app").join("build");
+        fs::create_dir_all(build.join("build")).expect("Failed to create test project");
+        fs::write(build.join("build").join("classes.jar"), [0; 100])
+            .expect("Failed to create test file");
+        File::create(temp_dir.join("settings.gradle")).expect("Failed to create test file");
+
+        let recognizer = || {
+            GarbageRecognizer::new(
+                "Gradle",
+                Some(vec![FileType::File("settings.gradle".into())]),
+                Some(vec![FileType::Directory("build".into())]),
+            )
+        };
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(recognizer());
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert!(garbage.is_empty());
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(recognizer().with_delete_max_depth(1));
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].directory, temp_dir);
+        let deletables: Vec<_> = garbage[0].deletable.iter().map(|d| &d.path).collect();
+        assert_eq!(deletables, vec![&build]);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_flag_results_over() {
+        let result = |deletable_sizes: &[u64]| {
+            let size = deletable_sizes.iter().sum();
+            result_fixture(0, "Rust", "/projects/wsg", size, deletable_sizes)
+        };
+        let cap = 20_000_000_000;
+        // together above the cap, but no single deletable is
+        let mut results = vec![
+            result(&[25_000_000_000]),
+            result(&[15_000_000_000, 15_000_000_000]),
+        ];
+
+        flag_results_over(&mut results, cap);
+
+        assert!(results[0].flagged);
+        assert!(!results[1].flagged);
+        let json = serde_json::to_value(&results[0]).expect("Failed to serialize");
+        assert_eq!(json["flagged"], serde_json::Value::Bool(true));
+    }
+
+    #[test]
+    fn test_clean_until_free_stops_at_target() {
+        let path = temp_dir().join("wsg_dev_clean_until_free");
+        let mut garbage = Vec::new();
+        for (n, size) in [3_000u64, 1_000, 5_000, 2_000].into_iter().enumerate() {
+            let target = path.join(format!("project{}", n)).join("target");
+            fs::create_dir_all(&target).expect("Failed to create test project");
+            let project = target.parent().expect("Project has a parent");
+            garbage.push(result_fixture(n as u32, "Rust", project, size, &[size]));
+        }
+
+        // the disk frees exactly what was cleaned
+        let starting_free = 1_000;
+        let free_space = || {
+            let freed: u64 = garbage
+                .iter()
+                .filter(|result| !result.deletable[0].path.exists())
+                .map(|result| result.size)
+                .sum();
+            Ok(starting_free + freed)
+        };
+        let (cleaned, selections) = clean_until_free(
+            garbage.clone(),
+            8_500,
+            DeleteOrder::Discovery,
+            None,
+            free_space,
+            delete_path,
+        )
+        .expect("Failed to clean");
+
+        let cleaned_sizes: Vec<u64> = cleaned.iter().map(|result| result.size).collect();
+        assert_eq!(cleaned_sizes, vec![5_000, 3_000]);
+        assert_eq!(selections.len(), 2);
+        assert!(garbage[1].deletable[0].path.exists());
+        assert!(garbage[3].deletable[0].path.exists());
+
+        let (cleaned, _) = clean_until_free(
+            garbage.clone(),
+            500,
+            DeleteOrder::Discovery,
+            None,
+            || Ok(1_000),
+            delete_path,
+        )
+        .expect("Failed to clean");
+        assert!(cleaned.is_empty());
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_garbage_in_relative_root_yields_absolute_paths() {
+        let path = temp_dir().join("wsg_dev_relative_root");
+        let target = path.join("project").join("target");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(path.join("project").join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+
+        let cwd = std::env::current_dir().expect("Failed to get the working directory");
+        let relative = PathBuf::from("../".repeat(cwd.components().count() - 1))
+            .join(path.strip_prefix("/").expect("Temp dir is absolute"))
+            .join(".");
+        assert!(relative.is_relative());
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&relative, &state).expect("Failed to scan");
+
+        let canonical = fs::canonicalize(&path).expect("Failed to canonicalize");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].directory, canonical.join("project"));
+        assert_eq!(
+            garbage[0].deletable[0].path,
+            canonical.join("project").join("target")
+        );
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_filter_garbage_preserve_active() {
+        let result = |id: u32, directory: &str| result_fixture(id, "Rust", directory, 100, &[100]);
+        let garbage = vec![
+            result(0, "/projects/wsg"),
+            result(1, "/projects/wsg/crates/ui"),
+            result(2, "/projects/other"),
+            result(3, "/projects/wsg-fork"),
+        ];
+        let remaining = |cwd: &str| {
+            filter_garbage_preserve_active(garbage.clone(), Path::new(cwd))
+                .into_iter()
+                .map(|result| result.index)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            remaining("/projects/wsg/src"),
+            vec![
+                GarbageIndex::Id(1),
+                GarbageIndex::Id(2),
+                GarbageIndex::Id(3)
+            ]
+        );
+        assert_eq!(
+            remaining("/projects/wsg/crates/ui/src"),
+            vec![
+                GarbageIndex::Id(0),
+                GarbageIndex::Id(2),
+                GarbageIndex::Id(3)
+            ]
+        );
+        assert_eq!(remaining("/home").len(), 4);
+    }
+
+    #[test]
+    fn test_find_garbage_with_project_config() {
+        let temp_dir = temp_dir().join("wsg_dev_project_config");
+        let (custom, plain) = (temp_dir.join("custom"), temp_dir.join("plain"));
+        for project in [&custom, &plain] {
+            for deletable in ["target", ".cache", "dist"] {
+                fs::create_dir_all(project.join(deletable)).expect("Failed to create test project");
+                fs::write(project.join(deletable).join("artifact"), [0; 100])
+                    .expect("Failed to create test file");
+            }
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        }
+        fs::write(
+            custom.join(PROJECT_CONFIG_FILE_NAME),
+            "delete = [{ directory = \".cache\" }]\nkeep = [\"dist\"]\n",
+        )
+        .expect("Failed to write project config");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![
+                FileType::Directory("target".into()),
+                FileType::Directory("dist".into()),
+            ]),
+        ));
+
+        let mut garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        garbage.sort_by(|a, b| a.directory.cmp(&b.directory));
+        let deletable = |result: &GarbageRecognizerResult| -> Vec<PathBuf> {
+            result.deletable.iter().map(|d| d.path.clone()).collect()
+        };
+        assert_eq!(
+            deletable(&garbage[0]),
+            vec![custom.join("target"), custom.join(".cache")]
+        );
+        assert_eq!(garbage[0].size, 200);
+        assert_eq!(
+            deletable(&garbage[1]),
+            vec![plain.join("target"), plain.join("dist")]
+        );
+
+        // a refused config skips its project, the rest of the scan goes on
+        for refused in ["src", ".git"] {
+            fs::write(
+                custom.join(PROJECT_CONFIG_FILE_NAME),
+                format!("delete = [{{ directory = \"{}\" }}]\n", refused),
+            )
+            .expect("Failed to write project config");
+            let scan =
+                find_garbage_in_directory_with_stats(&temp_dir, &state).expect("Failed to scan");
+            let directories: Vec<_> = scan.results.iter().map(|r| &r.directory).collect();
+            assert_eq!(directories, vec![&plain]);
+            assert_eq!(scan.invalid_configs.len(), 1);
+            assert!(
+                scan.invalid_configs[0].contains(refused),
+                "{:?}",
+                scan.invalid_configs
+            );
+        }
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_markerless_recognizer_finds_stray_deletable() {
+        let temp_dir = temp_dir().join("wsg_dev_markerless");
+        let project = temp_dir.join("project");
+        let stray = temp_dir.join("copied").join("node_modules");
+        for node_modules in [&project.join("node_modules"), &stray] {
+            fs::create_dir_all(node_modules.join("dependency").join("node_modules"))
+                .expect("Failed to create test project");
+            fs::write(node_modules.join("dependency").join("index.js"), [0; 100])
+                .expect("Failed to create test file");
+        }
+        File::create(project.join("package.json")).expect("Failed to create test file");
+
+        let scan = |recognize: Option<Vec<FileType>>| {
+            let mut state = AppState::new();
+            state.register_garbage_recognizer(GarbageRecognizer::new(
+                "NodeJS",
+                recognize,
+                Some(vec![FileType::Directory("node_modules".into())]),
+            ));
+            let mut garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+            garbage.sort_by(|a, b| a.directory.cmp(&b.directory));
+            garbage
+                .iter()
+                .flat_map(|result| &result.deletable)
+                .map(|deletable| deletable.path.clone())
+                .collect::<Vec<PathBuf>>()
+        };
+
+        assert_eq!(
+            scan(Some(vec![FileType::File("package.json".into())])),
+            vec![project.join("node_modules")]
+        );
+        assert_eq!(scan(None), vec![stray, project.join("node_modules")]);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_resumed_scan_skips_completed_subtrees() {
+        let temp_dir = temp_dir().join("wsg_dev_resume_scan");
+        for project in ["alpha", "beta", "gamma"] {
+            let project = temp_dir.join("projects").join(project);
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        }
+        let root = fs::canonicalize(temp_dir.join("projects")).expect("Failed to canonicalize");
+        let checkpoint = temp_dir.join("progress.scan");
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let directories = |results: &[GarbageRecognizerResult]| -> Vec<PathBuf> {
+            let mut directories: Vec<_> = results.iter().map(|r| r.directory.clone()).collect();
+            directories.sort();
+            directories
+        };
+        let full = find_garbage_in_directory(&root, &state).expect("Failed to scan");
+
+        // interrupted once the walk moved on to the second project
+        let interrupted: Vec<_> =
+            GarbageScanner::checkpointed(&root, &state, checkpoint.clone(), Duration::ZERO)
+                .expect("Failed to start the scan")
+                .take(2)
+                .collect::<Result<_, _>>()
+                .expect("Failed to scan");
+        assert_eq!(interrupted.len(), 2);
+        let progress = read_scan_checkpoint(&checkpoint, &root, scan_fingerprint(&state))
+            .expect("Failed to read checkpoint")
+            .expect("No checkpoint left behind");
+        assert_eq!(progress.completed, vec![CheckpointPath("alpha".into())]);
+        assert_eq!(directories(&progress.results), vec![root.join("alpha")]);
+
+        state.resume = true;
+        let mut resumed =
+            GarbageScanner::checkpointed(&root, &state, checkpoint.clone(), Duration::ZERO)
+                .expect("Failed to resume the scan");
+        let results: Vec<_> = resumed
+            .by_ref()
+            .collect::<Result<_, _>>()
+            .expect("Failed to scan");
+        assert_eq!(directories(&results), directories(&full));
+        // alpha came from the checkpoint, only beta and gamma were walked again
+        assert_eq!(resumed.stats()["Rust"].matched, 2);
+        assert!(!checkpoint.exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_resume_distrusts_stale_checkpoints() {
+        let temp_dir = temp_dir().join("wsg_dev_resume_stale");
+        for project in ["alpha", "beta", "gamma"] {
+            let project = temp_dir.join("projects").join(project);
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        }
+        let root = fs::canonicalize(temp_dir.join("projects")).expect("Failed to canonicalize");
+        let checkpoint = temp_dir.join("progress.scan");
+
+        let state = |delete: &[&str], resume: bool| {
+            let mut state = AppState::new();
+            state.include_empty = true;
+            state.resume = resume;
+            state.register_garbage_recognizer(GarbageRecognizer::new(
+                "Rust",
+                Some(vec![FileType::File("Cargo.toml".into())]),
+                Some(
+                    delete
+                        .iter()
+                        .map(|name| FileType::Directory(name.to_string()))
+                        .collect(),
+                ),
+            ));
+            state
+        };
+        // leaves a checkpoint with alpha completed
+        let interrupt = |state: &AppState| {
+            GarbageScanner::checkpointed(&root, state, checkpoint.clone(), Duration::ZERO)
+                .expect("Failed to start the scan")
+                .take(2)
+                .for_each(drop);
+            assert!(checkpoint.exists());
+        };
+        let resume = |state: &AppState| -> (Vec<PathBuf>, usize) {
+            let mut scanner =
+                GarbageScanner::checkpointed(&root, state, checkpoint.clone(), Duration::ZERO)
+                    .expect("Failed to resume the scan");
+            let mut directories: Vec<PathBuf> = scanner
+                .by_ref()
+                .map(|result| result.expect("Failed to scan").directory)
+                .collect();
+            directories.sort();
+            (directories, scanner.stats()["Rust"].matched)
+        };
+        let all = vec![root.join("alpha"), root.join("beta"), root.join("gamma")];
+
+        // written before the recognizer changed
+        interrupt(&state(&["target"], false));
+        assert_eq!(
+            resume(&state(&["target", ".cache"], true)),
+            (all.clone(), 3)
+        );
+
+        // the garbage of alpha is gone since, alpha is walked again
+        interrupt(&state(&["target"], false));
+        fs::remove_dir_all(root.join("alpha").join("target")).expect("Failed to clean up");
+        let (directories, _) = resume(&state(&["target"], true));
+        assert_eq!(directories, all[1..].to_vec());
+        fs::create_dir_all(root.join("alpha").join("target")).expect("Failed to recreate");
+
+        // a result pointing outside the root discards the checkpoint
+        interrupt(&state(&["target"], false));
+        let mut planted: serde_json::Value =
+            serde_json::from_slice(&fs::read(&checkpoint).expect("Failed to read checkpoint"))
+                .expect("Failed to parse checkpoint");
+        planted["results"][0]["directory"] = serde_json::json!(temp_dir);
+        planted["results"][0]["deletable"][0]["path"] = serde_json::json!(checkpoint);
+        fs::write(&checkpoint, planted.to_string()).expect("Failed to write checkpoint");
+        assert_eq!(resume(&state(&["target"], true)), (all, 3));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_script_shell_quotes_spaces_and_quotes() {
+        let quote = |shell: ScriptShell, path: &str| -> String {
+            let quoted = shell.quote(Path::new(path)).expect("Failed to quote");
+            String::from_utf8(quoted).expect("Quoted path is not UTF-8")
+        };
+
+        assert_eq!(
+            quote(ScriptShell::Sh, "/my projects/app"),
+            "'/my projects/app'"
+        );
+        assert_eq!(
+            quote(ScriptShell::Sh, "/it's \"$HOME\" `x`"),
+            "'/it'\\''s \"$HOME\" `x`'"
+        );
+        assert_eq!(
+            quote(ScriptShell::Ps1, "C:\\my projects\\app"),
+            "'C:\\my projects\\app'"
+        );
+        assert_eq!(
+            quote(ScriptShell::Ps1, "C:\\it's \"$env:HOME\" \u{2019}x\u{2019}"),
+            "'C:\\it''s \"$env:HOME\" \u{2019}\u{2019}x\u{2019}\u{2019}'"
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_emitted_sh_script_deletes_odd_paths() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = temp_dir().join("wsg_dev_emit_script");
+        let project = temp_dir.join("it's a \"project\" $HOME");
+        fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+        File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(project.join("target").join("app"), vec![0; 1_000])
+            .expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        let mut script = Vec::new();
+        write_deletion_script(&garbage, ScriptShell::Sh, Default::default(), &mut script)
+            .expect("Failed to write script");
+        let text = String::from_utf8_lossy(&script);
+        assert!(text.starts_with("#!/bin/sh\n"), "{}", text);
+        assert!(text.contains("# [0] Rust "), "{}", text);
+
+        let status = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(std::ffi::OsStr::from_bytes(&script))
+            .status()
+            .expect("Failed to run sh");
+        assert!(status.success());
+        assert!(!project.join("target").exists());
+        assert!(project.join("Cargo.toml").exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_unrecognized_deletable_is_walked_into() {
+        let temp_dir = temp_dir().join("wsg_dev_unrecognized_deletable");
+        let nested = temp_dir.join("build").join("tool");
+        fs::create_dir_all(nested.join("target")).expect("Failed to create test project");
+        File::create(nested.join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(nested.join("target").join("binary"), [0; 100])
+            .expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "CMake",
+            Some(vec![FileType::File("CMakeLists.txt".into())]),
+            Some(vec![FileType::Directory("build".into())]),
+        ));
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].recognizer_name, "Rust");
+        assert_eq!(garbage[0].directory, nested);
+
+        File::create(temp_dir.join("CMakeLists.txt")).expect("Failed to create test file");
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].recognizer_name, "CMake");
+        assert_eq!(garbage[0].directory, temp_dir);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_size_command_output_deadline_and_failures() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = temp_dir().join("wsg_dev_size_command");
+        let project = temp_dir.join("project");
+        fs::create_dir_all(&project).expect("Failed to create temporary wsg_dev directory");
+        File::create(project.join("build.marker")).expect("Failed to create test file");
+        let script = temp_dir.join("fake-size");
+        let write_script = |body: &str| {
+            fs::write(&script, format!("#!/bin/sh\ntouch ran\n{}\n", body))
+                .expect("Can't write test script");
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+                .expect("Failed to make test script executable");
+        };
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(
+            GarbageRecognizer::new(
+                "Fake",
+                Some(vec![FileType::File("build.marker".into())]),
+                None,
+            )
+            .with_command(
+                RecognizerCommand::new(script.to_string_lossy(), vec![], vec![])
+                    .with_size_pattern("{} total"),
+            ),
+        );
+        let scan = |state: &AppState| {
+            let _ = fs::remove_file(project.join("ran"));
+            find_garbage_in_directory_with_stats(&temp_dir, state).expect("Failed to scan")
+        };
+
+        write_script("echo '   Compiling app'\necho '     Summary 12 files, 1.5GiB total'");
+        let found = scan(&state);
+        assert_eq!(found.results.len(), 1);
+        assert_eq!(found.results[0].size, 1_610_612_736);
+
+        state.no_size = true;
+        let found = scan(&state);
+        assert!(!project.join("ran").exists());
+        assert!(found.results[0].size_unmeasured);
+        state.no_size = false;
+
+        write_script("echo 'nothing to clean'");
+        let found = scan(&state);
+        assert!(found.results.is_empty());
+        assert_eq!(found.failed_commands.len(), 1);
+        assert!(
+            found.failed_commands[0].contains("Fake"),
+            "{:?}",
+            found.failed_commands
+        );
+
+        write_script("sleep 5");
+        state.timeout = Some(Duration::from_millis(100));
+        let started = Instant::now();
+        let found = scan(&state);
+        assert!(found.timed_out);
+        assert!(started.elapsed() < Duration::from_secs(4));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_size_in_output() {
+        let cargo = "     Summary 12 files, 1.2GiB total\n";
+        assert_eq!(size_in_output(cargo, Some("{} total")), Some(1_288_490_189));
+        assert_eq!(
+            size_in_output(cargo, Some("files, {}")),
+            Some(1_288_490_189)
+        );
+        assert_eq!(
+            size_in_output("freed: 42 bytes\n", Some("freed: {}")),
+            Some(42)
+        );
+        assert_eq!(size_in_output("4242\n", None), Some(4242));
+        assert_eq!(size_in_output(cargo, None), None);
+        assert_eq!(size_in_output("", Some("{} total")), None);
+    }
+
+    #[test]
+    fn test_deletion_script_leaves_command_results_to_wsg() {
+        let mut by_command = result_fixture(1, "Cargo", "/projects/tool", 5_000, &[]);
+        by_command.by_command = true;
+        let garbage = vec![
+            result_fixture(0, "Rust", "/projects/app", 300, &[300]),
+            by_command,
+        ];
+
+        let mut script = Vec::new();
+        write_deletion_script(&garbage, ScriptShell::Sh, Default::default(), &mut script)
+            .expect("Failed to write script");
+        let script = String::from_utf8(script).expect("Script is not UTF-8");
+
+        assert_eq!(
+            script,
+            "#!/bin/sh\n\
+             # Generated by wsg, deletes the garbage of 1 project, 300.00 B\n\
+             # [1] Cargo /projects/tool is cleaned by its command, clean it with wsg\n\
+             \n\
+             # [0] Rust /projects/app, 300.00 B\n\
+             rm -rf -- '/projects/app/target'\n"
+        );
+    }
+
+    #[test]
+    fn test_filter_garbage_keep_newest_ranks_by_contents() {
+        let temp_dir = temp_dir().join("wsg_dev_keep_newest_contents");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+
+        let day = Duration::from_secs(24 * 3600);
+        for (name, built) in [("rebuilt", true), ("stale", false)] {
+            let project = temp_dir.join(name);
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+            let artifact = File::create(project.join("target").join("app"))
+                .expect("Failed to create test file");
+            if !built {
+                artifact
+                    .set_modified(SystemTime::now().sub(day))
+                    .expect("Failed to set modification time");
+            }
+        }
+        // The rebuilt target keeps the older time of its directory, only its contents are new
+        for (name, age) in [("rebuilt", 2), ("stale", 1)] {
+            File::open(temp_dir.join(name).join("target"))
+                .expect("Failed to open target directory")
+                .set_modified(SystemTime::now().sub(day * age))
+                .expect("Failed to set modification time");
+        }
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 2);
+
+        let selected: Vec<_> = filter_garbage_keep_newest(garbage, 1)
+            .into_iter()
+            .map(|result| result.directory.file_name().unwrap().to_owned())
+            .collect();
+        assert_eq!(selected, vec!["stale"]);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_scan_fingerprint_ignores_timeout() {
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let fingerprint = scan_fingerprint(&state);
+
+        // a scan resumed with a longer timeout finds the same results
+        state.timeout = Some(Duration::from_secs(30));
+        assert_eq!(scan_fingerprint(&state), fingerprint);
+
+        state.include_empty = true;
+        assert_ne!(scan_fingerprint(&state), fingerprint);
+    }
 }
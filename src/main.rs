@@ -1,18 +1,48 @@
+use crate::archive::{find_garbage_in_archives, ArchiveGarbage};
 use crate::error::{ApplicationError, GarbageError};
 use crate::garbage::{
-    clean_garbage_from_vec, compute_deletable_size_from_garbage_results, filter_garbage_from_ids,
-    find_garbage_in_directory, GarbageIndex, GarbageRecognizer, GarbageRecognizerResult,
+    append_audit_record, append_history_entry, append_stats_record, attach_recognizer_commands,
+    clean_garbage_from_vec_with, clean_until_free, cleaned_garbage_ids,
+    compute_deletable_size_from_garbage_results, deletable_named_like, delete_path,
+    delete_path_forcing_readonly, effective_cache_ttl, filter_garbage_from_ids,
+    filter_garbage_keep_newest, filter_garbage_preserve_active, filter_garbage_untouched_since,
+    find_garbage_in_directory_with_stats, flag_results_over, garbage_changes, json_output_schema,
+    measure_garbage_result, order_until_free, read_history, read_recognizer_report,
+    remove_garbage_from_ids, sort_garbage, total_freed, verify_deletions, write_deletion_script,
+    AuditRecord, CleanReport, CleanSummary, DeletableKind, DeleteOperationResult,
+    DeleteOperationSelection, DeleteOrder, FileType, GarbageChange, GarbageIndex,
+    GarbageRecognizer, GarbageRecognizerResult, GarbageScan, GarbageScanner, HistoryEntry,
+    ListSort, RecognizeBy, RecognizerReport, ScanStats, ScanSummary, ScriptShell, SizeStrategy,
+    StatsRecord,
 };
-use crate::recognizer::available_recognizer;
-use crate::ui::{BuildContext, Size, UIBox};
+use crate::recognizer::{
+    append_recognizer, available_recognizer, config_safe_mode, default_config_path,
+    docker_reclaimable_space, export_recognizers, import_recognizers, init_config,
+    prompt_recognizer, RecognizerFileWatcher,
+};
+use crate::ui::{BuildContext, ColorChoice, OutputEncoding, Size, UIBox};
 use crate::utils::{
-    delete_all_cache_files, delete_garbage_result_vec_cache, format_bytes,
-    read_garbage_result_vec_cache, write_garbage_result_vec_cache,
+    clean_history_path, delete_all_cache_files, dir_size_breakdown, format_bytes_exact,
+    format_count, format_date, format_number, format_size, glob_match,
+    lock_garbage_result_vec_cache, parse_duration, parse_size, prune_cache_files,
+    read_garbage_result_vec_cache_any_age, read_garbage_result_vec_cache_with, read_since_marker,
+    recognizer_stats_store_path, refresh_garbage_result_vec_cache,
+    replace_garbage_result_vec_cache, unique_disk_size, write_garbage_result_vec_cache,
+    write_since_marker, CacheLock, IoThrottle, SizeFormat, SizeUnit, TimedLineReader,
 };
 use clap::Parser;
-use std::collections::HashSet;
+use crossterm::queue;
+use crossterm::style::{Attribute, Color, ResetColor, SetAttribute, SetForegroundColor};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Display;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Once;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 
+mod archive;
 mod error;
 mod garbage;
 mod recognizer;
@@ -21,12 +51,109 @@ mod utils;
 
 pub struct AppState {
     garbage_recognizer: HashSet<GarbageRecognizer>,
+    wait_for_lock: bool,
+    keep_newest: Option<usize>,
+    allowed_commands: HashSet<String>,
+    follow_links: bool,
+    json: bool,
+    confirm_over: Option<u64>,
+    recognizer_stats: bool,
+    delete_empty_parents: bool,
+    output_encoding: OutputEncoding,
+    root_markers_only: bool,
+    show_both_sizes: bool,
+    verbose: bool,
+    timeout: Option<Duration>,
+    no_size: bool,
+    dry_run: bool,
+    emit_script: Option<ScriptShell>,
+    safe_mode: bool,
+    docker_df: bool,
+    recognize_by: RecognizeBy,
+    delete_order: DeleteOrder,
+    group_by_recognizer: bool,
+    include_empty: bool,
+    size_threads: Option<usize>,
+    fast_size: bool,
+    padding: usize,
+    unique_size: bool,
+    prompt_timeout: Option<Duration>,
+    color: bool,
+    warn_size: Option<u64>,
+    size_format: SizeFormat,
+    flag_over: Option<u64>,
+    preserve_newest_build: bool,
+    fail_on_partial: bool,
+    tree: bool,
+    strict: bool,
+    verify: bool,
+    force_readonly: bool,
+    sort: ListSort,
+    oneline: bool,
+    yes: bool,
+    inside_archives: bool,
+    since_file: Option<PathBuf>,
+    io_throttle: Option<u32>,
+    /// Whether scans save their progress for `--resume`.
+    scan_checkpoints: bool,
+    resume: bool,
+    /// Where scans add to the lifetime recognizer stats, nothing is recorded without one.
+    stats_file: Option<PathBuf>,
+    /// Where cleans are recorded, only set with `--record-history`.
+    history_file: Option<PathBuf>,
 }
 
 impl AppState {
     pub fn new() -> Self {
         AppState {
             garbage_recognizer: HashSet::new(),
+            wait_for_lock: false,
+            keep_newest: None,
+            allowed_commands: HashSet::new(),
+            follow_links: false,
+            json: false,
+            confirm_over: None,
+            recognizer_stats: false,
+            delete_empty_parents: false,
+            output_encoding: OutputEncoding::Utf8,
+            root_markers_only: false,
+            show_both_sizes: false,
+            verbose: false,
+            timeout: None,
+            no_size: false,
+            dry_run: false,
+            emit_script: None,
+            safe_mode: false,
+            docker_df: false,
+            recognize_by: RecognizeBy::Exists,
+            delete_order: DeleteOrder::Discovery,
+            group_by_recognizer: false,
+            include_empty: false,
+            size_threads: None,
+            fast_size: false,
+            padding: 0,
+            unique_size: false,
+            prompt_timeout: None,
+            color: false,
+            warn_size: None,
+            size_format: SizeFormat::default(),
+            flag_over: None,
+            preserve_newest_build: false,
+            fail_on_partial: false,
+            tree: false,
+            strict: false,
+            verify: false,
+            force_readonly: false,
+            sort: ListSort::Id,
+            oneline: false,
+            yes: false,
+            inside_archives: false,
+            since_file: None,
+            io_throttle: None,
+            scan_checkpoints: false,
+            resume: false,
+            stats_file: None,
+            history_file: None,
         }
     }
 
@@ -35,38 +162,549 @@ impl AppState {
     }
 }
 
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
+#[command(group(clap::ArgGroup::new("cleaning").args(["clean", "prune"]).multiple(true)))]
 struct Args {
+    #[arg(help = "Directory to scan, defaults to the current directory for --list and --clean")]
     path: Option<PathBuf>,
 
     #[arg(short, long, help = "List all the garbage in directory")]
     list: bool,
 
-    #[arg(short, long, value_delimiter = ',', num_args = 1.., value_name = "index", help = "Delete all the garbage in directory")]
+    #[arg(short, long, value_delimiter = ',', num_args = 1.., value_name = "index", help = "Delete the garbage with the given indices (stable while the tree is unchanged), <index>.<n> for a single deletable, or all")]
     clean: Option<Vec<GarbageIndex>>,
 
-    #[arg(long, value_name="RECOGNIZER", value_delimiter=',', num_args = 1.., help = "Start without any recognizer, only the selected ones are applied.")]
+    #[arg(long, value_name="RECOGNIZER", value_delimiter=',', num_args = 1.., help = "Start without any recognizer, only the selected ones are applied. Accepts * and ? wildcards.")]
     include_recognizer: Option<Vec<String>>,
 
-    #[arg(long, value_name="RECOGNIZER", value_delimiter=',', num_args = 1.., help = "Start with all available recognizers, only the elected are excluded.")]
+    #[arg(long, value_name="RECOGNIZER", value_delimiter=',', num_args = 1.., help = "Start with all available recognizers, only the elected are excluded. Accepts * and ? wildcards.")]
     exclude_recognizer: Option<Vec<String>>,
 
+    #[arg(long, value_name="NAME", value_delimiter=',', num_args = 1.., help = "Also delete the directories with these names in every recognized project, e.g. .cache,tmp")]
+    also_delete: Option<Vec<String>>,
+
     #[arg(long, help = "List all available recognizers")]
     list_recognizer: bool,
 
     #[arg(long, help = "Clean the application cache for all listings")]
     clean_cache: bool,
 
-    #[arg(long, help = "Force to renew the cache for specific path")]
+    #[arg(
+        long,
+        help = "Force to renew the cache for specific path, rescan before cleaning with --clean, or overwrite an existing config with --init-config"
+    )]
     force: bool,
+
+    #[arg(
+        long,
+        help = "Wait for another wsg process working on the same path instead of failing"
+    )]
+    wait: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Keep the N most recently built projects of each recognizer out of the clean"
+    )]
+    keep_newest: Option<usize>,
+
+    #[arg(long, value_name = "PROGRAM", value_delimiter = ',', num_args = 1.., help = "Allow command based recognizers to run the given programs")]
+    allow_command: Option<Vec<String>>,
+
+    #[arg(long, help = "Follow symbolic links while scanning the directory")]
+    follow_links: bool,
+
+    #[arg(long, value_name = "RECOGNIZER", value_delimiter = ',', num_args = 1.., help = "Apply the given recognizers before all others, they claim directories shared with other recognizers")]
+    prioritize: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        help = "Print the result of --list, --clean or --summary as JSON, prompts are written to stderr"
+    )]
+    json: bool,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the active recognizers to a .toml or .json file"
+    )]
+    export_recognizers: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Load additional recognizers from a .toml or .json file, recognizers with the same name replace the built-in ones. Defaults to the --init-config file when it exists"
+    )]
+    import_recognizers: Option<PathBuf>,
+
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, help = "Require typing the size in GB to confirm a clean larger than SIZE, e.g. 50GB")]
+    confirm_over: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Scan without cache and print how many directories each recognizer matched or skipped"
+    )]
+    recognizer_stats: bool,
+
+    #[arg(
+        long,
+        help = "Remove directories left empty after cleaning, up to the scan root"
+    )]
+    delete_empty_parents: bool,
+
+    #[arg(long, value_enum, default_value_t = OutputEncoding::Utf8, help = "Replace characters the console can't display")]
+    output_encoding: OutputEncoding,
+
+    #[arg(
+        long,
+        help = "Ignore project markers inside an already recognized project"
+    )]
+    root_markers_only: bool,
+
+    #[arg(long, value_name = "interval", value_parser = parse_duration, help = "Rescan and list the garbage repeatedly, e.g. every 30s")]
+    watch: Option<Duration>,
+
+    #[arg(
+        long,
+        requires = "watch",
+        help = "Reload the recognizer file, imported or the default config, when it changes while watching"
+    )]
+    recognizer_file_watch: bool,
+
+    #[arg(
+        long,
+        help = "Show the apparent and the on-disk size of the garbage, implies a fresh scan"
+    )]
+    show_both_sizes: bool,
+
+    #[arg(
+        long,
+        help = "Write a starter recognizer config to ~/.config/wsg/recognizers.toml, use --force to overwrite it"
+    )]
+    init_config: bool,
+
+    #[arg(
+        short,
+        long,
+        help = "Show the size of every deletable path in the listing"
+    )]
+    verbose: bool,
+
+    #[arg(long, value_name = "duration", value_parser = parse_duration, help = "Stop scanning after the duration, e.g. 2m, and show the partial results")]
+    timeout: Option<Duration>,
+
+    #[arg(long, value_name = "duration", value_parser = parse_duration, help = "Remove cache files older than the duration, e.g. 30d, before running")]
+    prune_cache_older_than: Option<Duration>,
+
+    #[arg(
+        long,
+        help = "List the garbage without measuring it, measure single results later with --size"
+    )]
+    no_size: bool,
+
+    #[arg(
+        long,
+        value_name = "index",
+        help = "Measure a result of a --no-size scan and update the cache"
+    )]
+    size: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "index",
+        help = "Show the largest files and directories in the deletables of a listed result"
+    )]
+    explain_size: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Show what --clean would delete without deleting anything"
+    )]
+    dry_run: bool,
+
+    #[arg(
+        long,
+        value_name = "SHELL",
+        requires = "cleaning",
+        conflicts_with = "enforce_under",
+        help = "Print a script deleting what --clean would delete instead of deleting it"
+    )]
+    emit_script: Option<ScriptShell>,
+
+    #[arg(
+        long,
+        requires = "cleaning",
+        help = "Delete even when safe mode (WSG_SAFE=1 or safe_mode in the config) turns --clean or --prune into a dry run"
+    )]
+    really_clean: bool,
+
+    #[arg(
+        long,
+        help = "Also report the space Docker could reclaim machine wide, runs docker system df"
+    )]
+    docker_df: bool,
+
+    #[arg(long, value_enum, default_value_t = RecognizeBy::Exists, help = "Probe each marker on its own, or read every directory once with listing")]
+    recognize_by: RecognizeBy,
+
+    #[arg(long, hide = true, help = "Print the JSON schema of the --json output")]
+    print_schema: bool,
+
+    #[arg(long, value_enum, default_value_t = DeleteOrder::Discovery, help = "Order in which --clean deletes the selected garbage")]
+    delete_order: DeleteOrder,
+
+    #[arg(
+        long,
+        value_name = "file",
+        conflicts_with = "cleaning",
+        help = "List the garbage of only the directories listed in the file, one per line"
+    )]
+    roots_from: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "file",
+        help = "Append the reclaimable space per recognizer as a JSON line to the file, never deletes anything"
+    )]
+    audit: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Interactively create a recognizer and add it to the --import-recognizers file or the config"
+    )]
+    new_recognizer: bool,
+
+    #[arg(
+        long,
+        help = "Scan and print totals per recognizer, as a JSON object with --json"
+    )]
+    summary: bool,
+
+    #[arg(
+        long,
+        help = "Scan without deleting and exit with code 6 if there is any garbage, for hooks"
+    )]
+    check: bool,
+
+    #[arg(
+        long,
+        requires = "check",
+        help = "Stop --check at the first garbage found, without measuring it"
+    )]
+    fail_fast: bool,
+
+    #[arg(
+        long = "group-by-recognizer",
+        help = "Group the listing under one header per recognizer, largest group first"
+    )]
+    group_by_recognizer: bool,
+
+    #[arg(
+        long = "include-empty",
+        help = "Also list projects whose deletables are present but empty"
+    )]
+    include_empty: bool,
+
+    #[arg(
+        long,
+        help = "Print how many projects and bytes each recognizer found across all past scans, as JSON with --json"
+    )]
+    recognizer_report: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Measure directories with more than 1000 entries on up to N threads"
+    )]
+    size_threads: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "READS",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Read at most READS directories per second while scanning and measuring, to spare slow disks"
+    )]
+    io_throttle: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Save the progress of the scan every 10 seconds, so that --resume can continue it"
+    )]
+    checkpoint: bool,
+
+    #[arg(
+        long,
+        help = "Continue an interrupted scan of the path, skipping the subtrees it completed"
+    )]
+    resume: bool,
+
+    #[arg(
+        long,
+        help = "Scan and list only the projects whose garbage appeared or grew since the last scan"
+    )]
+    new_only: bool,
+
+    #[arg(
+        long,
+        help = "Print the space freed by the past cleans run with --record-history"
+    )]
+    history: bool,
+
+    #[arg(
+        long,
+        help = "Record the space freed by the clean for --history, in the user's state directory"
+    )]
+    record_history: bool,
+
+    #[arg(
+        long,
+        help = "Measure sizes with GNU du where available, slightly larger than the walk as du counts directories too. Ignored with --io-throttle or --size-threads"
+    )]
+    fast_size: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        help = "Spaces before the content of each listed box, with a blank line above and below"
+    )]
+    padding: usize,
+
+    #[arg(
+        long,
+        help = "Also show the space on disk the listed garbage takes with hard linked files counted once"
+    )]
+    unique_size: bool,
+
+    #[arg(long, value_name = "duration", value_parser = parse_duration, help = "Give up on the --clean prompts, with exit code 7, when there is no response within the duration, e.g. 30s")]
+    prompt_timeout: Option<Duration>,
+
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto, help = "Color the listing, see --warn-size")]
+    color: ColorChoice,
+
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, help = "Emphasize listed projects with more garbage than SIZE, e.g. 1GB")]
+    warn_size: Option<u64>,
+
+    #[arg(
+        long,
+        value_enum,
+        ignore_case = true,
+        value_name = "UNIT",
+        help = "Print every size in this unit instead of scaling each one, e.g. GB"
+    )]
+    size_unit: Option<SizeUnit>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 2,
+        help = "Decimals of the printed sizes, e.g. 1 for compact or 3 for audits"
+    )]
+    precision: usize,
+
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, help = "Flag listed projects with a single deletable larger than SIZE, e.g. 20GB")]
+    flag_over: Option<u64>,
+
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, requires = "yes", help = "Clean the largest garbage first until the disk of the path has SIZE free, e.g. 20GB, needs --yes")]
+    enforce_under: Option<u64>,
+
+    #[arg(long, help = "Clean without asking, required by --enforce-under")]
+    yes: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "clean",
+        help = "Scan, list and clean all the garbage found after a single confirmation"
+    )]
+    prune: bool,
+
+    #[arg(long, help = "Don't clean the project the working directory is in")]
+    preserve_newest_build: bool,
+
+    #[arg(
+        long,
+        help = "Exit with an error when some of the paths could not be deleted"
+    )]
+    fail_on_partial: bool,
+
+    #[arg(
+        long,
+        help = "List the projects as a tree of their directories below the scan path"
+    )]
+    tree: bool,
+
+    #[arg(
+        long,
+        help = "Fail instead of warning about skipped directories, a corrupt cache or recognizer names that match nothing"
+    )]
+    strict: bool,
+
+    #[arg(
+        long,
+        help = "Check every deleted path is gone afterwards and count it as failed otherwise"
+    )]
+    verify: bool,
+
+    #[arg(
+        long,
+        help = "Clear the read-only attribute of paths whose deletion is denied and retry once"
+    )]
+    force_readonly: bool,
+
+    #[arg(long, value_enum, default_value_t = ListSort::Id, help = "Order of the projects in a listing")]
+    sort: ListSort,
+
+    #[arg(
+        long,
+        conflicts_with = "tree",
+        help = "List one project per line as aligned columns instead of boxes"
+    )]
+    oneline: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "json",
+        help = "Also report the garbage of projects inside .zip archives, it's not cleaned"
+    )]
+    inside_archives: bool,
+
+    #[arg(
+        long,
+        value_name = "path",
+        help = "Clean only what is untouched since the time in the marker file, everything without one, and set it to now after the clean"
+    )]
+    since_file: Option<PathBuf>,
 }
 
+/// Exit code of a scan that was cut short by `--timeout`.
+const SCAN_TIMED_OUT_EXIT_CODE: i32 = 3;
+
+/// Exit code of a `--clean` whose ids select no garbage.
+const NOTHING_TO_CLEAN_EXIT_CODE: i32 = 4;
+
+/// Exit code of a clean with `--fail-on-partial` that could not delete every path.
+const PARTIAL_CLEAN_EXIT_CODE: i32 = 5;
+
+/// Exit code of a `--check` that found garbage.
+const GARBAGE_FOUND_EXIT_CODE: i32 = 6;
+
+/// Exit code of a clean whose prompt got no answer within `--prompt-timeout`.
+const PROMPT_TIMED_OUT_EXIT_CODE: i32 = 7;
+
 fn main() -> Result<(), ApplicationError> {
+    let result = run();
+    if let Err(ApplicationError::GarbageError(GarbageError::ScanTimedOut)) = result {
+        eprintln!("\nWarning: {}\n", GarbageError::ScanTimedOut);
+        std::process::exit(SCAN_TIMED_OUT_EXIT_CODE);
+    }
+    if let Err(error @ ApplicationError::NothingToClean(_)) = &result {
+        println!("{}", error);
+        std::process::exit(NOTHING_TO_CLEAN_EXIT_CODE);
+    }
+    if let Err(ApplicationError::GarbageError(error @ GarbageError::PartialClean(_))) = &result {
+        eprintln!("\n{}\n", error);
+        std::process::exit(PARTIAL_CLEAN_EXIT_CODE);
+    }
+    if let Err(ApplicationError::GarbageError(error @ GarbageError::GarbageFound(_))) = &result {
+        eprintln!("\n{}\n", error);
+        std::process::exit(GARBAGE_FOUND_EXIT_CODE);
+    }
+    if let Err(ApplicationError::GarbageError(error @ GarbageError::PromptTimedOut)) = &result {
+        eprintln!("\n{}\n", error);
+        std::process::exit(PROMPT_TIMED_OUT_EXIT_CODE);
+    }
+    result
+}
+
+fn run() -> Result<(), ApplicationError> {
     let mut state = AppState::new();
     let args = Args::parse();
 
-    register_garbage_recognizer(&mut state, &args);
+    // runs before the recognizers are registered, the file may not exist yet
+    if args.new_recognizer {
+        let file = args
+            .import_recognizers
+            .clone()
+            .or_else(default_config_path)
+            .ok_or_else(|| {
+                GarbageError::RecognizerFileError("Can't locate the config directory".to_string())
+            })?;
+        arg_new_recognizer(&file)?;
+        return Ok(());
+    }
+
+    state.strict = args.strict;
+    register_garbage_recognizer(&mut state, &args)?;
+    state.wait_for_lock = args.wait;
+    state.keep_newest = args.keep_newest;
+    state.follow_links = args.follow_links;
+    state.json = args.json;
+    state.confirm_over = args.confirm_over;
+    state.recognizer_stats = args.recognizer_stats;
+    state.delete_empty_parents = args.delete_empty_parents;
+    state.output_encoding = args.output_encoding;
+    state.root_markers_only = args.root_markers_only;
+    state.show_both_sizes = args.show_both_sizes;
+    state.verbose = args.verbose;
+    state.timeout = args.timeout;
+    state.no_size = args.no_size;
+    state.docker_df = args.docker_df;
+    state.recognize_by = args.recognize_by;
+    state.delete_order = args.delete_order;
+    state.group_by_recognizer = args.group_by_recognizer;
+    state.include_empty = args.include_empty;
+    state.size_threads = args.size_threads;
+    state.io_throttle = args.io_throttle;
+    state.scan_checkpoints = args.checkpoint || args.resume;
+    state.resume = args.resume;
+    state.fast_size = args.fast_size;
+    state.padding = args.padding;
+    state.unique_size = args.unique_size;
+    state.prompt_timeout = args.prompt_timeout;
+    state.color = args.color.enabled(io::stdout().is_terminal());
+    state.warn_size = args.warn_size;
+    state.size_format = SizeFormat {
+        unit: args.size_unit,
+        decimals: args.precision,
+    };
+    state.flag_over = args.flag_over;
+    state.preserve_newest_build = args.preserve_newest_build;
+    state.fail_on_partial = args.fail_on_partial;
+    state.verify = args.verify;
+    state.yes = args.yes;
+    state.since_file = args.since_file.clone();
+    state.force_readonly = args.force_readonly;
+    state.tree = args.tree;
+    state.oneline = args.oneline;
+    state.inside_archives = args.inside_archives;
+    state.stats_file = recognizer_stats_store_path();
+    state.history_file = args.record_history.then(clean_history_path).flatten();
+    state.sort = args.sort;
+    state.safe_mode = is_safe_mode(default_config_path().as_deref())?;
+    state.dry_run = clean_is_dry_run(&args, state.safe_mode);
+    state.emit_script = args.emit_script;
+
+    if args.print_schema {
+        let schema = serde_json::to_string_pretty(&json_output_schema())
+            .map_err(GarbageError::SerializationError)?;
+        println!("{}", schema);
+        return Ok(());
+    }
+
+    if let Some(max_age) = args.prune_cache_older_than {
+        let pruned = prune_cache_files(max_age)?;
+        println!(
+            "\nPruned {}\n",
+            format_count(pruned as u64, "cache file", "cache files")
+        );
+    }
 
     if args.clean_cache {
         delete_all_cache_files()?;
@@ -74,163 +712,3491 @@ fn main() -> Result<(), ApplicationError> {
         return Ok(());
     }
 
+    if args.init_config {
+        let file = default_config_path().ok_or_else(|| {
+            GarbageError::RecognizerFileError("Can't locate the config directory".to_string())
+        })?;
+        init_config(&file, args.force)?;
+        println!(
+            "\nStarter recognizer config written to {}, it is loaded unless --import-recognizers is given\n",
+            file.display()
+        );
+        return Ok(());
+    }
+
+    if let Some(file) = &args.export_recognizers {
+        export_recognizers(file, &state.garbage_recognizer)?;
+        println!("\nRecognizers exported to {}\n", file.display());
+        return Ok(());
+    }
+
+    if let Some(id) = args.size {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        arg_size(&state, &_path, id)?;
+        return Ok(());
+    }
+
+    if let Some(id) = args.explain_size {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        arg_explain_size(&state, &_path, id, &mut io::stdout().lock())?;
+        return Ok(());
+    }
+
+    if let Some(file) = &args.audit {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        arg_audit(&state, &_path, file)?;
+        return Ok(());
+    }
+
+    if let Some(target) = args.enforce_under {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        arg_enforce_under(&state, &_path, target, || fs2::available_space(&_path))?;
+        return Ok(());
+    }
+
+    if args.check {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        state.no_size |= args.fail_fast;
+        arg_check(&state, &_path, args.fail_fast, &mut io::stdout().lock())?;
+        return Ok(());
+    }
+
+    if args.summary {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        arg_summary(&state, &_path)?;
+        return Ok(());
+    }
+
+    if args.new_only {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        arg_new_only(&state, &_path)?;
+        return Ok(());
+    }
+
+    if args.history {
+        arg_history(&state)?;
+        return Ok(());
+    }
+
+    if args.recognizer_report {
+        arg_recognizer_report(&state)?;
+        return Ok(());
+    }
+
     if args.list_recognizer {
         arg_list_recognizer(&state);
-        return Ok(())
+        return Ok(());
     }
 
-    if args.list {
-        let _path = match &args.path {
-            None => Err(ApplicationError::MissingArgumentPath),
-            Some(path) => Ok(path),
-        }?;
-        let _ = arg_list(&state, &_path, args.force);
+    if let Some(file) = &args.roots_from {
+        let roots = read_scan_roots(file, state.strict, &mut io::stderr())?;
+        for root in dedup_scan_roots(roots, &mut io::stderr())? {
+            arg_list(&state, &root, args.force)?;
+        }
         return Ok(());
     }
 
-    if let Some(ids) = args.clean {
-        let _path = match &args.path {
-            None => Err(ApplicationError::MissingArgumentPath),
-            Some(path) => Ok(path),
-        }?;
-        if let Err(_) = arg_clean(&_path, &ids) {
-            let _ = arg_list(&state, _path, true);
-            println!("\nYou should first get an overview before you delete anything!\nThe --clean command can now be used.\n");
+    if args.list {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        if let Some(interval) = args.watch {
+            return arg_watch(&mut state, &args, &_path, interval);
         }
+        arg_list(&state, &_path, args.force)?;
         return Ok(());
     }
 
-    if args.path.is_some() && args.clean.is_none() && args.list == false {
-        let _path = match args.path {
+    if args.prune {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        return match state.prompt_timeout {
+            Some(timeout) => {
+                let mut input = TimedLineReader::spawn(io::BufReader::new(io::stdin()), timeout);
+                arg_prune(&state, &_path, &mut input)
+            }
+            None => arg_prune(&state, &_path, &mut io::stdin().lock()),
+        };
+    }
+
+    if let Some(ids) = &args.clean {
+        let _path = resolve_scan_root(&args.path, is_path_required())?;
+        validate_scan_root(&_path)?;
+        return match state.prompt_timeout {
+            Some(timeout) => {
+                let mut input = TimedLineReader::spawn(io::BufReader::new(io::stdin()), timeout);
+                arg_clean(&state, &_path, ids, args.force, &mut input)
+            }
+            None => arg_clean(&state, &_path, ids, args.force, &mut io::stdin().lock()),
+        };
+    }
+
+    if args.path.is_some() && args.clean.is_none() && !args.list {
+        let _path = match args.path.clone() {
             None => Err(ApplicationError::InvalidArgumentPath),
             Some(path) => Ok(path),
         }?;
-        let _ = arg_list(&state, &_path, args.force);
+        validate_scan_root(&_path)?;
+        if let Some(interval) = args.watch {
+            return arg_watch(&mut state, &args, &_path, interval);
+        }
+        arg_list(&state, &_path, args.force)?;
         return Ok(());
     }
 
     Ok(())
 }
 
-fn arg_list(state: &AppState, path: &Path, force: bool) -> Result<(), GarbageError> {
-    let generate_garbage_result_without_cache =
-        || -> Result<Vec<GarbageRecognizerResult>, GarbageError> {
-            let garbage = find_garbage_in_directory(path, state)?;
-            let _ = write_garbage_result_vec_cache(path, &garbage, None)?;
-            Ok(garbage)
-        };
+fn arg_watch(
+    state: &mut AppState,
+    args: &Args,
+    path: &Path,
+    interval: Duration,
+) -> Result<(), ApplicationError> {
+    let mut watcher = recognizer_config_path(args, default_config_path())
+        .filter(|_| args.recognizer_file_watch)
+        .map(|file| RecognizerFileWatcher::new(&file));
 
-    let result = if force {
-        generate_garbage_result_without_cache()?
-    } else {
-        match read_garbage_result_vec_cache(path, None) {
-            Ok(vec) => vec,
-            Err(_) => generate_garbage_result_without_cache()?,
+    loop {
+        if let Some(watcher) = &mut watcher {
+            match reload_recognizers_if_changed(state, args, watcher) {
+                Ok(true) => println!("\nRecognizer file changed, recognizers reloaded\n"),
+                Ok(false) => {}
+                Err(error) => eprintln!(
+                    "\nKeeping the previous recognizers, the recognizer file could not be loaded: {}\n",
+                    error
+                ),
+            }
         }
-    };
+        arg_list(state, path, true)?;
+        thread::sleep(interval);
+    }
+}
 
-    display_garbage_results(&result)?;
+/// Rebuilds the recognizer set when the watched file changed. On error the previous
+/// recognizers stay active.
+fn reload_recognizers_if_changed(
+    state: &mut AppState,
+    args: &Args,
+    watcher: &mut RecognizerFileWatcher,
+) -> Result<bool, ApplicationError> {
+    if !watcher.has_changed() {
+        return Ok(false);
+    }
+    register_garbage_recognizer(state, args)?;
+    Ok(true)
+}
 
-    Ok(())
+/// Falls back to the current directory when no path was given, unless the user
+/// opted out with `WSG_REQUIRE_PATH=1`.
+fn resolve_scan_root(path: &Option<PathBuf>, required: bool) -> Result<PathBuf, ApplicationError> {
+    match path {
+        Some(path) => Ok(path.clone()),
+        None if required => Err(ApplicationError::MissingArgumentPath),
+        None => std::env::current_dir().map_err(|_| ApplicationError::MissingArgumentPath),
+    }
 }
 
-fn display_garbage_results(results: &Vec<GarbageRecognizerResult>) -> Result<(), GarbageError> {
-    let terminal_size = crossterm::terminal::size()?;
-    let context = BuildContext::new(Size::new(
-        terminal_size.0 as usize,
-        terminal_size.1 as usize,
-    ));
+fn is_path_required() -> bool {
+    std::env::var("WSG_REQUIRE_PATH").is_ok_and(|value| value == "1")
+}
 
-    results.iter().for_each(|entry| {
-        println!();
-        let entry_string = format!(
-            "Project folder: {:?}\nto clean: {}\nDeletable {:?}",
-            entry.directory,
-            format_bytes(entry.size),
-            entry.deletable
-        );
-        let entry_box = UIBox::new(
-            &context,
-            format!(" [{}] {} ", entry.index, entry.recognizer_name),
-            entry_string,
-        );
-        entry_box.render();
-        println!();
-    });
+/// Safe mode, enabled with `WSG_SAFE=1` or `safe_mode = true` in the config, turns every
+/// `--clean` into a dry run.
+fn is_safe_mode(config: Option<&Path>) -> Result<bool, GarbageError> {
+    if std::env::var("WSG_SAFE").is_ok_and(|value| value == "1") {
+        return Ok(true);
+    }
+    config.map_or(Ok(false), config_safe_mode)
+}
 
-    println!(
-        "Cleanable storage: {}\n",
-        format_bytes(compute_deletable_size_from_garbage_results(results))
-    );
+/// An explicit `--dry-run` always wins, safe mode only gives way to `--really-clean`.
+fn clean_is_dry_run(args: &Args, safe_mode: bool) -> bool {
+    args.dry_run || (safe_mode && !args.really_clean)
+}
+
+/// Reads the newline separated scan roots of a `--roots-from` file. Entries that are
+/// not an existing directory are reported to `warnings` and skipped.
+fn read_scan_roots(
+    file: &Path,
+    strict: bool,
+    warnings: &mut impl Write,
+) -> Result<Vec<PathBuf>, GarbageError> {
+    let mut roots = Vec::new();
+    for line in fs::read_to_string(file)?.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let root = PathBuf::from(line);
+        match validate_scan_root(&root) {
+            Ok(()) => roots.push(root),
+            Err(error) => warn(
+                strict,
+                warnings,
+                format!(
+                    "skipping {} from {}, {}",
+                    root.display(),
+                    file.display(),
+                    error
+                ),
+            )?,
+        }
+    }
+    Ok(roots)
+}
+
+/// Canonicalizes the roots and drops those inside another root, which its scan already
+/// covers, each skipped root is noted in `notes`.
+fn dedup_scan_roots(
+    roots: Vec<PathBuf>,
+    notes: &mut impl Write,
+) -> Result<Vec<PathBuf>, GarbageError> {
+    let canonical: Vec<(PathBuf, PathBuf)> = roots
+        .into_iter()
+        .map(|root| {
+            (
+                fs::canonicalize(&root).unwrap_or_else(|_| root.clone()),
+                root,
+            )
+        })
+        .collect();
 
-    println!("Use the --clean <ids...> argument to clear the garbage. <ids...> can be 'all' or integers separated by a comma eg. 1,2,7");
+    let mut kept = Vec::new();
+    for (n, (path, root)) in canonical.iter().enumerate() {
+        let covered_by = canonical.iter().enumerate().find(|(other_n, (other, _))| {
+            // of two equal roots the first one is kept
+            *other_n != n && path.starts_with(other) && (path != other || *other_n < n)
+        });
+        match covered_by {
+            Some((_, (_, other_root))) => writeln!(
+                notes,
+                "Note: skipping {}, it is already scanned as part of {}",
+                root.display(),
+                other_root.display()
+            )?,
+            None => kept.push(path.clone()),
+        }
+    }
+    Ok(kept)
+}
 
+fn validate_scan_root(path: &Path) -> Result<(), ApplicationError> {
+    if !path.exists() {
+        return Err(ApplicationError::InvalidArgumentPath);
+    }
+    if !path.is_dir() {
+        return Err(ApplicationError::NotADirectory(path.to_path_buf()));
+    }
     Ok(())
 }
 
-fn arg_clean(path: &Path, ids: &Vec<GarbageIndex>) -> Result<(), GarbageError> {
-    let garbage = read_garbage_result_vec_cache(path, None)?;
-    let filtered_garbage = filter_garbage_from_ids(garbage, &ids);
+fn arg_list(state: &AppState, path: &Path, force: bool) -> Result<(), GarbageError> {
+    warn_if_scan_root_is_deletable(state, path)?;
+    let generate_garbage_result_without_cache = || -> Result<GarbageScan, GarbageError> {
+        let _lock = lock_garbage_result_vec_cache(path, state.wait_for_lock)?;
+        scan_into_cache(state, path)
+    };
+
+    // the archives are found by the walk of a scan, the cache doesn't keep them
+    let (mut result, stats, timed_out, archives) =
+        if force || state.recognizer_stats || state.show_both_sizes || state.inside_archives {
+            let scan = generate_garbage_result_without_cache()?;
+            (
+                scan.results,
+                Some(scan.stats),
+                scan.timed_out,
+                scan.archives,
+            )
+        } else {
+            match read_cache(state, path) {
+                Ok(vec) => (vec, None, false, Vec::new()),
+                Err(error) => {
+                    if let GarbageError::SerializationError(error) = error {
+                        warn(
+                            state.strict,
+                            &mut io::stderr(),
+                            format!("the cached listing is corrupt, rescanning: {}", error),
+                        )?;
+                    }
+                    let scan = generate_garbage_result_without_cache()?;
+                    (
+                        scan.results,
+                        Some(scan.stats),
+                        scan.timed_out,
+                        scan.archives,
+                    )
+                }
+            }
+        };
+
+    if let Some(cap) = state.flag_over {
+        flag_results_over(&mut result, cap);
+    }
+    sort_garbage(&mut result, state.sort);
+
+    if state.json {
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        return match timed_out {
+            true => Err(GarbageError::ScanTimedOut),
+            false => Ok(()),
+        };
+    }
 
-    display_garbage_to_clean(&filtered_garbage);
+    let mut out = io::stdout().lock();
+    if state.tree {
+        let root = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        writeln!(out)?;
+        render_garbage_tree(&result, &root, state, &mut out)?;
+        writeln!(out)?;
+    } else if state.oneline {
+        let width = terminal_size_or_default(crossterm::terminal::size)
+            .width
+            .unwrap_or(DEFAULT_TERMINAL_SIZE.0);
+        render_garbage_oneline(&result, state, width, &mut out)?;
+    } else {
+        display_garbage_results(&result, state, &mut out)?;
+    }
 
-    println!("Are you sure you want to delete the files listed above? (y/N):");
+    if state.inside_archives {
+        let archive_garbage = find_garbage_in_archives(&archives, state);
+        display_archive_garbage(&archive_garbage, state, &mut out)?;
+    }
 
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input)?;
+    if let (true, Some(stats)) = (state.recognizer_stats, stats) {
+        display_recognizer_stats(&stats, state.output_encoding, &mut out)?;
+    }
 
-    let confirmation = input.trim().eq_ignore_ascii_case("y");
+    if state.docker_df {
+        display_docker_reclaimable(&mut out)?;
+    }
 
-    if confirmation {
-        clean_garbage_from_vec(filtered_garbage)?;
-        delete_garbage_result_vec_cache(path)?;
-        println!("The garbage has been deleted successfully!");
+    if timed_out {
+        return Err(GarbageError::ScanTimedOut);
     }
 
     Ok(())
 }
 
-fn display_garbage_to_clean(results: &Vec<GarbageRecognizerResult>) {
-    results.iter().for_each(|garbage| {
-        println!("[{}] - {}", garbage.index, garbage.directory.display());
+/// Scans the path and caches the results, the caller holds the cache lock.
+fn scan_into_cache(state: &AppState, path: &Path) -> Result<GarbageScan, GarbageError> {
+    let scan = scan_garbage(state, path)?;
+    // a partial scan must not be mistaken for the complete garbage of the path, the TTL
+    // applies when the cache is read
+    if !scan.timed_out {
+        let _ = replace_garbage_result_vec_cache(path, &scan.results)?;
+    }
+    Ok(scan)
+}
+
+fn display_docker_reclaimable(out: &mut impl Write) -> io::Result<()> {
+    match docker_reclaimable_space() {
+        Ok(reclaimable) => {
+            writeln!(
+                out,
+                "Docker reclaimable space, machine wide and not part of the total above"
+            )?;
+            for entry in reclaimable {
+                writeln!(out, "\t{}: {}", entry.resource, entry.reclaimable)?;
+            }
+            writeln!(out)
+        }
+        Err(error) => writeln!(out, "Docker reclaimable space unavailable: {}\n", error),
+    }
+}
+
+fn arg_summary(state: &AppState, path: &Path) -> Result<(), GarbageError> {
+    warn_if_scan_root_is_deletable(state, path)?;
+    let _lock = lock_garbage_result_vec_cache(path, state.wait_for_lock)?;
+    let started = Instant::now();
+    let scan = scan_garbage(state, path)?;
+    let summary =
+        ScanSummary::new(&scan.results, started.elapsed()).with_size_format(state.size_format);
+    if !scan.timed_out {
+        let cache_ttl = effective_cache_ttl(&scan.results, &state.garbage_recognizer);
+        let _ = write_garbage_result_vec_cache(path, &scan.results, cache_ttl)?;
+    }
+
+    if state.json {
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        println!("\n{}\n", summary);
+    }
+
+    if scan.timed_out {
+        return Err(GarbageError::ScanTimedOut);
+    }
+    Ok(())
+}
+
+/// Scans without ever deleting and fails with [`GarbageError::GarbageFound`] if there is
+/// any garbage. With `fail_fast` the scan stops at the first result.
+fn arg_check(
+    state: &AppState,
+    path: &Path,
+    fail_fast: bool,
+    out: &mut impl Write,
+) -> Result<(), GarbageError> {
+    warn_if_scan_root_is_deletable(state, path)?;
+    let _lock = lock_garbage_result_vec_cache(path, state.wait_for_lock)?;
+    let started = Instant::now();
+    let (results, timed_out) = match fail_fast {
+        true => {
+            notify_follow_links(state);
+            let mut scanner = GarbageScanner::new(path, state);
+            let first = scanner.next().transpose()?;
+            (first.into_iter().collect::<Vec<_>>(), scanner.timed_out())
+        }
+        false => {
+            let scan = scan_garbage(state, path)?;
+            (scan.results, scan.timed_out)
+        }
+    };
+
+    if results.is_empty() {
+        if timed_out {
+            return Err(GarbageError::ScanTimedOut);
+        }
+        writeln!(out, "No garbage found in {}", path.display())?;
+        return Ok(());
+    }
+    for result in &results {
+        writeln!(
+            out,
+            "{} - {}, {}",
+            state
+                .output_encoding
+                .sanitize(&result.directory.display().to_string()),
+            result.recognizer_name,
+            display_size(result, state.size_format)
+        )?;
+    }
+    if !fail_fast {
+        let summary =
+            ScanSummary::new(&results, started.elapsed()).with_size_format(state.size_format);
+        writeln!(out, "\n{}", summary)?;
+    }
+    Err(GarbageError::GarbageFound(results.len()))
+}
+
+/// Scans and cleans the largest garbage first until `free_space` reaches `target`, without
+/// scanning at all when there is enough free space already.
+fn arg_enforce_under(
+    state: &AppState,
+    path: &Path,
+    target: u64,
+    mut free_space: impl FnMut() -> io::Result<u64>,
+) -> Result<(), ApplicationError> {
+    let free = free_space().map_err(GarbageError::from)?;
+    if free >= target {
         println!(
-            "\t{}, to clean: {}",
-            garbage.recognizer_name,
-            format_bytes(garbage.size)
+            "\n{} free, already above {}, nothing cleaned\n",
+            format_size(free, state.size_format),
+            format_size(target, state.size_format)
         );
-        for deletable_path in &garbage.deletable {
-            println!("\tDelete: {}", deletable_path.display())
+        return Ok(());
+    }
+
+    warn_if_scan_root_is_deletable(state, path)?;
+    let _lock = lock_garbage_result_vec_cache(path, state.wait_for_lock)?;
+    let scan = scan_into_cache(state, path)?;
+    if scan.timed_out {
+        return Err(GarbageError::ScanTimedOut.into());
+    }
+    let garbage = scan.results;
+    // the options that keep results apply, running out of them ends like running out of garbage
+    let selected_garbage = match select_garbage(state, path, &garbage, &[GarbageIndex::All]) {
+        Err(ApplicationError::NothingToClean(_)) => Vec::new(),
+        selected_garbage => selected_garbage?,
+    };
+
+    if state.dry_run {
+        // assumes every result frees its size, the free space can't be asked without deleting
+        let mut ordered = selected_garbage;
+        order_until_free(&mut ordered, state.delete_order);
+        let mut expected_free = free;
+        let would_clean: Vec<_> = ordered
+            .into_iter()
+            .take_while(|result| {
+                let needed = expected_free < target;
+                expected_free += result.size;
+                needed
+            })
+            .collect();
+        display_garbage_to_clean(
+            &would_clean,
+            state.output_encoding,
+            state.size_format,
+            &mut io::stdout().lock(),
+        )
+        .map_err(GarbageError::from)?;
+        println!("\nDry run, nothing was deleted.");
+        return Ok(());
+    }
+
+    let (cleaned, selections) = clean_until_free(
+        selected_garbage,
+        target,
+        state.delete_order,
+        state.delete_empty_parents.then_some(path),
+        &mut free_space,
+        deleter(state.verify, state.force_readonly),
+    )?;
+    let cleaned_ids = cleaned_garbage_ids(&garbage, &cleaned);
+    update_cache_after_clean(path, garbage, &cleaned_ids)?;
+    record_clean_history(state, &selections);
+
+    let outcome = clean_outcome(&selections, state.fail_on_partial, &mut io::stderr().lock());
+    if outcome.is_ok() && !cleaned.is_empty() {
+        if let Some(marker) = &state.since_file {
+            write_since_marker(marker, SystemTime::now()).map_err(GarbageError::from)?;
         }
-        println!();
-    });
-    println!();
-}
+    }
+    if state.json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&CleanReport::new(selections))
+                .map_err(GarbageError::from)?
+        );
+        return Ok(outcome?);
+    }
 
-fn arg_list_recognizer(state: &AppState) {
-    println!("All available garbage recognizers\n");
-    for recognizer in &state.garbage_recognizer {
-        println!("• {}", recognizer.name);
+    let mut out = io::stdout().lock();
+    display_garbage_to_clean(&cleaned, state.output_encoding, state.size_format, &mut out)
+        .map_err(GarbageError::from)?;
+    display_clean_results(&selections, state, &mut out).map_err(GarbageError::from)?;
+    println!(
+        "{}",
+        CleanSummary::from_selections(&selections).describe(state.size_format)
+    );
+    let free = free_space().map_err(GarbageError::from)?;
+    match free >= target {
+        true => println!(
+            "{} free, above the target of {}",
+            format_size(free, state.size_format),
+            format_size(target, state.size_format)
+        ),
+        false => println!(
+            "Nothing left to clean, {} free is still below the target of {}",
+            format_size(free, state.size_format),
+            format_size(target, state.size_format)
+        ),
     }
-    println!("\nBy default all are selected. Use --include-recognizer or --exclude-recognizer to adjust the selection");
+    Ok(outcome?)
 }
 
-fn register_garbage_recognizer(state: &mut AppState, args: &Args) {
-    let mut recognizer = available_recognizer();
+fn arg_new_only(state: &AppState, path: &Path) -> Result<(), GarbageError> {
+    warn_if_scan_root_is_deletable(state, path)?;
+    let (changes, timed_out) = new_garbage_since_cache(state, path)?;
+    let mut out = io::stdout().lock();
+    display_garbage_changes(&changes, state.output_encoding, state.size_format, &mut out)?;
 
-    include_recognizer(&mut recognizer, args);
-    exclude_recognizer(&mut recognizer, args);
+    if timed_out {
+        return Err(GarbageError::ScanTimedOut);
+    }
+    Ok(())
+}
 
-    state.garbage_recognizer.extend(recognizer);
+/// Scans fresh and compares with the cached listing, which then becomes the listing the
+/// next comparison and `--clean` work from.
+fn new_garbage_since_cache(
+    state: &AppState,
+    path: &Path,
+) -> Result<(Vec<GarbageChange>, bool), GarbageError> {
+    let _lock = lock_garbage_result_vec_cache(path, state.wait_for_lock)?;
+    let previous = match read_garbage_result_vec_cache_any_age(path) {
+        Ok((previous, _)) => previous,
+        Err(_) => Vec::new(),
+    };
+    let scan = scan_garbage(state, path)?;
+    if !scan.timed_out {
+        replace_garbage_result_vec_cache(path, &scan.results)?;
+    }
+
+    Ok((garbage_changes(&previous, scan.results), scan.timed_out))
 }
 
-fn include_recognizer(recognizer_vec: &mut Vec<GarbageRecognizer>, args: &Args) {
-    if let Some(include_recognizer) = &args.include_recognizer {
-        recognizer_vec.retain(|r| include_recognizer.contains(&r.name.to_lowercase()));
+fn display_garbage_changes(
+    changes: &[GarbageChange],
+    encoding: OutputEncoding,
+    size_format: SizeFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if changes.is_empty() {
+        return writeln!(out, "\nNo new garbage since the last scan\n");
+    }
+
+    writeln!(out, "\nNew or grown garbage since the last scan\n")?;
+    for change in changes {
+        let previous = match change.previous_size {
+            Some(size) => format!("was {}", format_size(size, size_format)),
+            None => "new".to_string(),
+        };
+        writeln!(
+            out,
+            "{}",
+            encoding.sanitize(&format!(
+                "[{}] {} {}: +{} ({})",
+                change.result.index,
+                change.result.recognizer_name,
+                change.result.directory.display(),
+                format_size(change.growth(), size_format),
+                previous
+            ))
+        )?;
+    }
+    writeln!(
+        out,
+        "\nUse the --clean <ids...> argument to clear the garbage.\n"
+    )
+}
+
+/// Adds a clean to the history with `--record-history`. The history is advisory, failing
+/// to record it does not fail the clean.
+fn record_clean_history(state: &AppState, selections: &[DeleteOperationSelection]) {
+    let Some(file) = &state.history_file else {
+        return;
+    };
+    let entry = HistoryEntry::new(selections);
+    if entry.projects > 0 {
+        if let Err(error) = append_history_entry(file, &entry) {
+            eprintln!("Warning: clean not recorded in the history: {}", error);
+        }
+    }
+}
+
+fn arg_history(state: &AppState) -> Result<(), GarbageError> {
+    let history = match clean_history_path() {
+        Some(file) => read_history(&file)?,
+        None => Vec::new(),
+    };
+    if state.json {
+        println!("{}", serde_json::to_string_pretty(&history)?);
+        return Ok(());
+    }
+
+    display_history(&history, state.size_format, &mut io::stdout().lock())?;
+    Ok(())
+}
+
+fn display_history(
+    history: &[HistoryEntry],
+    size_format: SizeFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if history.is_empty() {
+        return writeln!(out, "\nNothing cleaned yet\n");
+    }
+
+    writeln!(out, "\n{:<12}{:>12}{:>10}", "Date", "Freed", "Projects")?;
+    for entry in history {
+        writeln!(
+            out,
+            "{:<12}{:>12}{:>10}",
+            format_date(entry.timestamp),
+            format_size(entry.freed, size_format),
+            entry.projects
+        )?;
+    }
+    writeln!(
+        out,
+        "\nYou've freed {} in total\n",
+        display_total(total_freed(history), size_format)
+    )
+}
+
+/// Scanning inside build output finds the projects vendored there, which is rarely what
+/// was meant.
+fn warn_if_scan_root_is_deletable(state: &AppState, path: &Path) -> Result<(), GarbageError> {
+    match deletable_named_like(path, &state.garbage_recognizer) {
+        Some((recognizer, target)) => warn(
+            state.strict,
+            &mut io::stderr(),
+            format!(
+                "{} looks like {} garbage ({}), the projects found in it are likely build output",
+                path.display(),
+                recognizer.name,
+                target.name()
+            ),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Tells once per run, before the first walk, that `--follow-links` can make it long.
+/// It is a notice of the option rather than a scan anomaly, `--strict` doesn't fail on it.
+fn notify_follow_links(state: &AppState) {
+    static NOTIFIED: Once = Once::new();
+    if state.follow_links {
+        NOTIFIED.call_once(|| {
+            eprintln!("\nWarning: following symbolic links, linked trees are scanned as well which can take a long time. Link cycles are skipped.\n");
+        });
+    }
+}
+
+/// Scans the path and records the stats, the directories the scan had to skip are warned about.
+fn scan_garbage(state: &AppState, path: &Path) -> Result<GarbageScan, GarbageError> {
+    notify_follow_links(state);
+    let scan = find_garbage_in_directory_with_stats(path, state)?;
+    for (skipped, kind) in &scan.skipped {
+        warn(
+            state.strict,
+            &mut io::stderr(),
+            format!("skipped {}, {}", skipped.display(), kind),
+        )?;
+    }
+    for invalid_config in &scan.invalid_configs {
+        warn(
+            state.strict,
+            &mut io::stderr(),
+            format!("project skipped, {}", invalid_config),
+        )?;
+    }
+    for failed_command in &scan.failed_commands {
+        warn(state.strict, &mut io::stderr(), failed_command)?;
+    }
+    record_scan_stats(state, &scan);
+    Ok(scan)
+}
+
+/// Reports an anomaly the run can live with, with `--strict` it fails the run instead.
+fn warn(strict: bool, out: &mut impl Write, message: impl Display) -> Result<(), GarbageError> {
+    if strict {
+        return Err(GarbageError::Strict(message.to_string()));
+    }
+    writeln!(out, "Warning: {}", message)?;
+    Ok(())
+}
+
+/// Adds a complete scan to the lifetime recognizer stats. The stats are advisory, failing
+/// to record them does not fail the scan.
+fn record_scan_stats(state: &AppState, scan: &GarbageScan) {
+    let Some(file) = &state.stats_file else {
+        return;
+    };
+    if scan.timed_out {
+        return;
+    }
+    let record = StatsRecord::new(scan);
+    if let Err(error) = append_stats_record(file, &record) {
+        eprintln!("Warning: recognizer stats not recorded: {}", error);
+    }
+}
+
+fn arg_recognizer_report(state: &AppState) -> Result<(), GarbageError> {
+    let report = match &state.stats_file {
+        Some(file) => read_recognizer_report(file)?,
+        None => RecognizerReport::new(),
+    };
+    if state.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let mut out = io::stdout().lock();
+    display_recognizer_report(&report, state.output_encoding, state.size_format, &mut out)?;
+    Ok(())
+}
+
+fn display_recognizer_report(
+    report: &RecognizerReport,
+    encoding: OutputEncoding,
+    size_format: SizeFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if report.is_empty() {
+        return writeln!(out, "\nNo scans recorded yet\n");
+    }
+    writeln!(out, "\nRecognizer totals across all scans\n")?;
+    for (name, totals) in report {
+        writeln!(
+            out,
+            "{}",
+            encoding.sanitize(&format!(
+                "• {}: {} matched, {} found",
+                name,
+                totals.matched,
+                format_size(totals.bytes, size_format)
+            ))
+        )?;
+    }
+    writeln!(out)
+}
+
+fn arg_audit(state: &AppState, path: &Path, file: &Path) -> Result<(), GarbageError> {
+    let started = Instant::now();
+    let scan = scan_garbage(state, path)?;
+    let host = hostname::get()
+        .map(|host| host.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let record = AuditRecord::new(host, path, &scan, started.elapsed());
+    append_audit_record(file, &record)?;
+    println!(
+        "\nAudit record of {} appended to {}, {} reclaimable\n",
+        path.display(),
+        file.display(),
+        format_size(record.total, state.size_format)
+    );
+
+    if scan.timed_out {
+        return Err(GarbageError::ScanTimedOut);
+    }
+    Ok(())
+}
+
+/// Reads the cached listing of the path, valid for the TTL of its recognizers.
+fn read_cache(state: &AppState, path: &Path) -> Result<Vec<GarbageRecognizerResult>, GarbageError> {
+    read_garbage_result_vec_cache_with(path, |results| {
+        effective_cache_ttl(results, &state.garbage_recognizer)
+    })
+}
+
+fn arg_new_recognizer(file: &Path) -> Result<(), ApplicationError> {
+    if !io::stdin().is_terminal() {
+        return Err(ApplicationError::NotInteractive(
+            "--new-recognizer".to_string(),
+        ));
+    }
+
+    let recognizer = prompt_recognizer(&mut io::stdin().lock(), &mut io::stdout())?;
+    validate_recognizer_delete_targets(std::slice::from_ref(&recognizer))?;
+    append_recognizer(file, &recognizer)?;
+    println!(
+        "\nRecognizer {} added to {}\n",
+        recognizer.name,
+        file.display()
+    );
+    Ok(())
+}
+
+fn arg_size(state: &AppState, path: &Path, id: u32) -> Result<(), ApplicationError> {
+    let _lock = lock_garbage_result_vec_cache(path, state.wait_for_lock)?;
+    let mut garbage = read_cache(state, path)?;
+    attach_recognizer_commands(&mut garbage, &state.garbage_recognizer);
+    let result = garbage
+        .iter_mut()
+        .find(|result| result.index == GarbageIndex::Id(id))
+        .ok_or_else(|| ApplicationError::IdNotExists(id.to_string()))?;
+
+    let strategy = size_strategy_of(state, result);
+    let throttle = state.io_throttle.map(IoThrottle::new);
+    measure_garbage_result(result, strategy, throttle.as_ref());
+    println!(
+        "\n[{}] {}: {}\n",
+        id,
+        result.directory.display(),
+        format_size(result.size, state.size_format)
+    );
+
+    replace_garbage_result_vec_cache(path, &garbage)?;
+    Ok(())
+}
+
+/// How the recognizer of the result measures, the default for unknown recognizers.
+fn size_strategy_of(state: &AppState, result: &GarbageRecognizerResult) -> SizeStrategy {
+    state
+        .garbage_recognizer
+        .iter()
+        .find(|recognizer| recognizer.name == result.recognizer_name)
+        .map(|recognizer| recognizer.size_strategy)
+        .unwrap_or_default()
+}
+
+/// Entries `--explain-size` shows, the largest first.
+const EXPLAIN_SIZE_ENTRIES: usize = 10;
+
+/// Measures what is directly inside the deletables of a cached result and prints the
+/// largest entries, deletables that aren't directories are listed as a whole.
+fn arg_explain_size(
+    state: &AppState,
+    path: &Path,
+    id: u32,
+    out: &mut impl Write,
+) -> Result<(), ApplicationError> {
+    let garbage = read_cache(state, path)?;
+    let result = garbage
+        .iter()
+        .find(|result| result.index == GarbageIndex::Id(id))
+        .ok_or_else(|| ApplicationError::IdNotExists(id.to_string()))?;
+    let strategy = size_strategy_of(state, result);
+    let throttle = state.io_throttle.map(IoThrottle::new);
+
+    let mut entries = Vec::new();
+    for deletable in &result.deletable {
+        let is_directory = fs::symlink_metadata(&deletable.path).is_ok_and(|m| m.is_dir());
+        match is_directory {
+            true => entries.extend(
+                dir_size_breakdown(&deletable.path, strategy, throttle.as_ref())
+                    .map_err(GarbageError::from)?,
+            ),
+            false => entries.push((deletable.path.clone(), deletable.size)),
+        }
+    }
+    entries.sort_by(|(a_path, a_size), (b_path, b_size)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+    display_size_breakdown(result, &entries, state, out).map_err(GarbageError::from)?;
+    Ok(())
+}
+
+fn display_size_breakdown(
+    result: &GarbageRecognizerResult,
+    entries: &[(PathBuf, u64)],
+    state: &AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let encoding = state.output_encoding;
+    let total: u64 = entries.iter().map(|(_, size)| size).sum();
+    writeln!(
+        out,
+        "\n[{}] {}: {}\n",
+        result.index,
+        encoding.sanitize(&result.directory.display().to_string()),
+        format_size(total, state.size_format)
+    )?;
+    let shown = entries.len().min(EXPLAIN_SIZE_ENTRIES);
+    let sizes: Vec<String> = entries[..shown]
+        .iter()
+        .map(|(_, size)| format_size(*size, state.size_format))
+        .collect();
+    let width = sizes.iter().map(String::len).max().unwrap_or(0);
+    for ((entry, _), size) in entries.iter().zip(&sizes) {
+        let entry = entry.strip_prefix(&result.directory).unwrap_or(entry);
+        writeln!(
+            out,
+            "  {:>width$}  {}",
+            size,
+            encoding.sanitize(&entry.display().to_string())
+        )?;
+    }
+    if entries.len() > shown {
+        let rest: u64 = entries[shown..].iter().map(|(_, size)| size).sum();
+        writeln!(
+            out,
+            "  {} more entries, {}",
+            format_number((entries.len() - shown) as u64),
+            format_size(rest, state.size_format)
+        )?;
+    }
+    writeln!(out)
+}
+
+fn display_recognizer_stats(
+    stats: &ScanStats,
+    encoding: OutputEncoding,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out, "\nRecognizer statistics\n")?;
+    for (name, recognizer_stats) in stats {
+        writeln!(
+            out,
+            "{}",
+            encoding.sanitize(&format!(
+                "• {}: {} matched, {} skipped",
+                name,
+                recognizer_stats.matched,
+                format_count(recognizer_stats.skipped as u64, "directory", "directories")
+            ))
+        )?;
+    }
+    writeln!(out)
+}
+
+fn display_garbage_results(
+    results: &[GarbageRecognizerResult],
+    state: &AppState,
+    out: &mut impl Write,
+) -> Result<(), GarbageError> {
+    let mut context = BuildContext::new(terminal_size_or_default(crossterm::terminal::size));
+    context.encoding(state.output_encoding);
+    context.padding(state.padding);
+
+    render_garbage_results(results, state, &context, out)?;
+    Ok(())
+}
+
+/// Size used when the terminal can't be queried, e.g. in CI or inside some containers.
+const DEFAULT_TERMINAL_SIZE: (usize, usize) = (80, 24);
+
+fn terminal_size_or_default(query: impl FnOnce() -> io::Result<(u16, u16)>) -> Size {
+    match query() {
+        Ok((width, height)) => Size::new(width as usize, height as usize),
+        Err(_) => Size::new(DEFAULT_TERMINAL_SIZE.0, DEFAULT_TERMINAL_SIZE.1),
+    }
+}
+
+fn render_garbage_results(
+    results: &[GarbageRecognizerResult],
+    state: &AppState,
+    context: &BuildContext,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if state.group_by_recognizer {
+        let mut groups: Vec<(&str, Vec<&GarbageRecognizerResult>)> = Vec::new();
+        for entry in results {
+            match groups
+                .iter_mut()
+                .find(|(name, _)| *name == entry.recognizer_name)
+            {
+                Some((_, group)) => group.push(entry),
+                None => groups.push((&entry.recognizer_name, vec![entry])),
+            }
+        }
+        let group_size =
+            |group: &[&GarbageRecognizerResult]| group.iter().map(|entry| entry.size).sum::<u64>();
+        groups.sort_by(|(a_name, a), (b_name, b)| {
+            group_size(b)
+                .cmp(&group_size(a))
+                .then_with(|| a_name.cmp(b_name))
+        });
+
+        for (name, group) in groups {
+            writeln!(
+                out,
+                "\n{} ({}, {})",
+                context.encoding.sanitize(name),
+                format_count(group.len() as u64, "project", "projects"),
+                format_size(group_size(&group), state.size_format)
+            )?;
+            for entry in group {
+                render_garbage_box(entry, state, context, out)?;
+            }
+        }
+    } else {
+        for entry in results {
+            render_garbage_box(entry, state, context, out)?;
+        }
+    }
+
+    writeln!(
+        out,
+        "Cleanable storage: {}\n",
+        display_total(
+            compute_deletable_size_from_garbage_results(results),
+            state.size_format
+        )
+    )?;
+
+    if state.unique_size {
+        let deletables = results
+            .iter()
+            .flat_map(|result| &result.deletable)
+            .map(|deletable| deletable.path.as_path());
+        match unique_disk_size(deletables) {
+            Some(size) => writeln!(
+                out,
+                "Unique on disk: {}, hard linked files counted once\n",
+                display_total(size, state.size_format)
+            )?,
+            None => writeln!(out, "Unique on disk: unavailable on this platform\n")?,
+        }
+    }
+
+    writeln!(out, "Use the --clean <ids...> argument to clear the garbage. <ids...> can be 'all' or integers separated by a comma eg. 1,2,7")?;
+
+    Ok(())
+}
+
+/// Lists the garbage found inside archives, it can only be reclaimed by repacking them.
+fn display_archive_garbage(
+    garbage: &[ArchiveGarbage],
+    state: &AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    if garbage.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "\nInside archives, reported only and not cleaned:")?;
+    for entry in garbage {
+        let location = format!(
+            "{}:{}/",
+            entry.archive.display(),
+            entry.directory.trim_end_matches('/')
+        );
+        writeln!(
+            out,
+            "  {} {} ({} compressed) {}",
+            state.output_encoding.sanitize(&location),
+            state.output_encoding.sanitize(&entry.recognizer_name),
+            format_size(entry.size, state.size_format),
+            state.output_encoding.sanitize(&entry.deletable.join(", "))
+        )?;
+    }
+    writeln!(
+        out,
+        "Inside archives: {}\n",
+        display_total(
+            garbage.iter().map(|entry| entry.size).sum(),
+            state.size_format
+        )
+    )
+}
+
+/// Shortest room left for the directory column of `--oneline`, narrower terminals wrap.
+const MIN_ONELINE_PATH_WIDTH: usize = 16;
+
+/// Prints one aligned `[id] size  recognizer  directory` row per project, fitted to
+/// `width`. Directories too long for the rest of the line keep their tail.
+fn render_garbage_oneline(
+    results: &[GarbageRecognizerResult],
+    state: &AppState,
+    width: usize,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let rows: Vec<(String, String, String, String)> = results
+        .iter()
+        .map(|result| {
+            (
+                format!("[{}]", result.index),
+                display_size(result, state.size_format),
+                state.output_encoding.sanitize(&result.recognizer_name),
+                state
+                    .output_encoding
+                    .sanitize(&result.directory.display().to_string()),
+            )
+        })
+        .collect();
+    let column_width = |column: fn(&(String, String, String, String)) -> &String| {
+        rows.iter()
+            .map(|row| column(row).chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let id_width = column_width(|row| &row.0);
+    let size_width = column_width(|row| &row.1);
+    let recognizer_width = column_width(|row| &row.2);
+    let path_width = width
+        .saturating_sub(id_width + 1 + size_width + 2 + recognizer_width + 2)
+        .max(MIN_ONELINE_PATH_WIDTH);
+
+    for (id, size, recognizer, directory) in &rows {
+        writeln!(
+            out,
+            "{:<id_width$} {:>size_width$}  {:<recognizer_width$}  {}",
+            id,
+            size,
+            recognizer,
+            truncate_left(directory, path_width)
+        )?;
+    }
+
+    writeln!(
+        out,
+        "\nCleanable storage: {}",
+        display_total(
+            compute_deletable_size_from_garbage_results(results),
+            state.size_format
+        )
+    )
+}
+
+/// Shortens `text` to `width` characters by replacing its start with "...".
+fn truncate_left(text: &str, width: usize) -> String {
+    let length = text.chars().count();
+    if length <= width {
+        return text.to_string();
+    }
+    let tail: String = text
+        .chars()
+        .skip(length - width.saturating_sub(3))
+        .collect();
+    format!("...{}", tail)
+}
+
+/// A directory of the `--tree` listing with the projects found in it.
+#[derive(Default)]
+struct GarbageTreeNode<'a> {
+    results: Vec<&'a GarbageRecognizerResult>,
+    children: BTreeMap<String, GarbageTreeNode<'a>>,
+}
+
+impl GarbageTreeNode<'_> {
+    fn size(&self) -> u64 {
+        self.results.iter().map(|result| result.size).sum::<u64>()
+            + self
+                .children
+                .values()
+                .map(GarbageTreeNode::size)
+                .sum::<u64>()
+    }
+}
+
+/// Prints the projects as a tree of their directories below `root`, every directory
+/// with the garbage it holds. Directories whose only content is one subdirectory are
+/// joined with it into a single line.
+fn render_garbage_tree(
+    results: &[GarbageRecognizerResult],
+    root: &Path,
+    state: &AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let mut tree = GarbageTreeNode::default();
+    for result in results {
+        let relative = result
+            .directory
+            .strip_prefix(root)
+            .unwrap_or(&result.directory);
+        let mut node = &mut tree;
+        for component in relative.components() {
+            let name = component.as_os_str().to_string_lossy().into_owned();
+            node = node.children.entry(name).or_default();
+        }
+        node.results.push(result);
+    }
+
+    render_garbage_tree_node(&root.display().to_string(), &tree, 0, state, out)
+}
+
+fn render_garbage_tree_node(
+    name: &str,
+    node: &GarbageTreeNode,
+    depth: usize,
+    state: &AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let projects: String = node
+        .results
+        .iter()
+        .map(|result| format!(" [{}] {}", result.index, result.recognizer_name))
+        .collect();
+    writeln!(
+        out,
+        "{:indent$}{} ({}){}",
+        "",
+        state.output_encoding.sanitize(name),
+        format_size(node.size(), state.size_format),
+        state.output_encoding.sanitize(&projects),
+        indent = depth * 2
+    )?;
+
+    for (child_name, mut child) in &node.children {
+        let mut name = child_name.clone();
+        while let (true, Some((only_name, only_child))) = (
+            child.results.is_empty() && child.children.len() == 1,
+            child.children.iter().next(),
+        ) {
+            name = format!("{}/{}", name, only_name);
+            child = only_child;
+        }
+        render_garbage_tree_node(&name, child, depth + 1, state, out)?;
+    }
+    Ok(())
+}
+
+fn render_garbage_box(
+    entry: &GarbageRecognizerResult,
+    state: &AppState,
+    context: &BuildContext,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(out)?;
+    let mut entry_string = format!(
+        "Project folder: {}\nto clean: {}\nDeletable: {}",
+        entry.directory.display(),
+        display_size(entry, state.size_format),
+        entry
+            .deletable
+            .iter()
+            .map(|deletable| deletable.path.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    if state.verbose {
+        for (n, deletable) in entry.deletable.iter().enumerate() {
+            entry_string.push_str(&format!(
+                "\n  [{}.{}] {}: {}",
+                entry.index,
+                n,
+                deletable.path.display(),
+                format_size(deletable.size, state.size_format)
+            ));
+        }
+    }
+    if let Some(apparent_size) = entry.apparent_size {
+        let disk_size = entry
+            .disk_size
+            .map(|size| format_size(size, state.size_format))
+            .unwrap_or_else(|| "unavailable".to_string());
+        entry_string.push_str(&format!(
+            "\napparent size: {}, on disk: {}",
+            format_size(apparent_size, state.size_format),
+            disk_size
+        ));
+    }
+    for deletable in &entry.deletable {
+        if deletable.kind != Some(DeletableKind::Symlink) {
+            continue;
+        }
+        if let Ok(target) = fs::read_link(&deletable.path) {
+            entry_string.push_str(&format!(
+                "\n{} links to {}, which is kept",
+                deletable.path.display(),
+                target.display()
+            ));
+        }
+    }
+    let mut title = format!(" [{}] {} ", entry.index, entry.recognizer_name);
+    if let (true, Some(cap)) = (entry.flagged, state.flag_over) {
+        title.push_str("(!) ");
+        entry_string.push_str(&format!(
+            "\n(!) a deletable is larger than {}",
+            format_size(cap, state.size_format)
+        ));
+    }
+    let entry_box = UIBox::new(context, title, entry_string);
+    let emphasized = state.color && state.warn_size.is_some_and(|size| entry.size > size);
+    if emphasized {
+        queue!(
+            out,
+            SetForegroundColor(Color::Yellow),
+            SetAttribute(Attribute::Bold)
+        )?;
+    }
+    entry_box.render_to(out)?;
+    if emphasized {
+        queue!(out, SetAttribute(Attribute::Reset))?;
+    }
+    writeln!(out)?;
+    Ok(())
+}
+
+/// Cleans what the last listing showed, which must not be older than its cache TTL. With
+/// `force` the path is rescanned instead and the lock is held from the scan to the clean,
+/// so nothing else can change the listing in between.
+fn arg_clean(
+    state: &AppState,
+    path: &Path,
+    ids: &[GarbageIndex],
+    force: bool,
+    input: &mut impl BufRead,
+) -> Result<(), ApplicationError> {
+    let (garbage, lock) = if force {
+        let lock = lock_garbage_result_vec_cache(path, state.wait_for_lock)?;
+        let scan = scan_into_cache(state, path)?;
+        if scan.timed_out {
+            return Err(GarbageError::ScanTimedOut.into());
+        }
+        (scan.results, Some(lock))
+    } else {
+        match read_cache(state, path) {
+            Ok(garbage) => (garbage, None),
+            Err(GarbageError::InvalidCache | GarbageError::SerializationError(_)) => {
+                return Err(ApplicationError::NoFreshListing(path.to_path_buf()))
+            }
+            Err(GarbageError::IOError(error)) if error.kind() == io::ErrorKind::NotFound => {
+                return Err(ApplicationError::NoFreshListing(path.to_path_buf()))
+            }
+            Err(error) => return Err(error.into()),
+        }
+    };
+
+    let filtered_garbage = select_garbage(state, path, &garbage, ids)?;
+    Ok(clean_listed_garbage(
+        state,
+        path,
+        garbage,
+        filtered_garbage,
+        lock,
+        input,
+    )?)
+}
+
+/// Scans the path and cleans all the garbage found after a single confirmation. The
+/// confirmed listing is exactly what gets deleted, it doesn't go through the cache.
+fn arg_prune(
+    state: &AppState,
+    path: &Path,
+    input: &mut impl BufRead,
+) -> Result<(), ApplicationError> {
+    warn_if_scan_root_is_deletable(state, path)?;
+    let lock = lock_garbage_result_vec_cache(path, state.wait_for_lock)?;
+    let scan = scan_garbage(state, path)?;
+    if scan.timed_out {
+        return Err(GarbageError::ScanTimedOut.into());
+    }
+
+    let filtered_garbage = select_garbage(state, path, &scan.results, &[GarbageIndex::All])?;
+    Ok(clean_listed_garbage(
+        state,
+        path,
+        scan.results,
+        filtered_garbage,
+        Some(lock),
+        input,
+    )?)
+}
+
+/// The results of a listing that a clean of `ids` deletes, after the options that keep
+/// some of them.
+fn select_garbage(
+    state: &AppState,
+    path: &Path,
+    garbage: &[GarbageRecognizerResult],
+    ids: &[GarbageIndex],
+) -> Result<Vec<GarbageRecognizerResult>, ApplicationError> {
+    let mut filtered_garbage = filter_garbage_from_ids(garbage.to_vec(), ids);
+    if let Some(marker) = &state.since_file {
+        if let Some(cutoff) = read_since_marker(marker).map_err(GarbageError::from)? {
+            filtered_garbage = filter_garbage_untouched_since(filtered_garbage, cutoff);
+        }
+    }
+    if let Some(keep) = state.keep_newest {
+        // the newest of the projects are kept, not the newest of the selection
+        let deletable: Vec<GarbageIndex> = filter_garbage_keep_newest(garbage.to_vec(), keep)
+            .into_iter()
+            .map(|result| result.index)
+            .collect();
+        filtered_garbage.retain(|result| deletable.contains(&result.index));
+    }
+    if state.preserve_newest_build {
+        // the results hold canonical paths
+        if let Ok(cwd) = std::env::current_dir().and_then(fs::canonicalize) {
+            filtered_garbage = filter_garbage_preserve_active(filtered_garbage, &cwd);
+        }
+    }
+    if filtered_garbage.is_empty() {
+        return Err(ApplicationError::NothingToClean(path.to_path_buf()));
+    }
+    ensure_within_scan_root(&filtered_garbage, path)?;
+    attach_recognizer_commands(&mut filtered_garbage, &state.garbage_recognizer);
+    Ok(filtered_garbage)
+}
+
+/// Refuses a listing that would delete anything outside the scanned path, e.g. because
+/// the cache file of another path was read.
+fn ensure_within_scan_root(
+    garbage: &[GarbageRecognizerResult],
+    path: &Path,
+) -> Result<(), ApplicationError> {
+    // the results hold canonical paths
+    let root = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let outside = garbage
+        .iter()
+        .flat_map(|result| {
+            std::iter::once(&result.directory).chain(result.deletable.iter().map(|d| &d.path))
+        })
+        .find(|deletable| !deletable.starts_with(&root));
+    match outside {
+        Some(deletable) => Err(ApplicationError::OutsideScanRoot(deletable.clone(), root)),
+        None => Ok(()),
+    }
+}
+
+fn clean_listed_garbage(
+    state: &AppState,
+    path: &Path,
+    garbage: Vec<GarbageRecognizerResult>,
+    mut filtered_garbage: Vec<GarbageRecognizerResult>,
+    lock: Option<CacheLock>,
+    input: &mut impl BufRead,
+) -> Result<(), GarbageError> {
+    if let Some(shell) = state.emit_script {
+        write_deletion_script(
+            &filtered_garbage,
+            shell,
+            state.size_format,
+            &mut io::stdout().lock(),
+        )?;
+        return Ok(());
+    }
+
+    if state.dry_run {
+        if state.json {
+            println!("{}", serde_json::to_string_pretty(&filtered_garbage)?);
+        } else {
+            display_garbage_to_clean(
+                &filtered_garbage,
+                state.output_encoding,
+                state.size_format,
+                &mut io::stdout().lock(),
+            )?;
+            println!("\nDry run, nothing was deleted.");
+        }
+        if state.safe_mode {
+            eprintln!("Safe mode is on, add --really-clean to delete the files listed above.");
+        }
+        return Ok(());
+    }
+
+    if state.confirm_over.is_some() {
+        // the threshold compares with what is deleted, an unmeasured result is not 0 bytes
+        let throttle = state.io_throttle.map(IoThrottle::new);
+        for result in filtered_garbage
+            .iter_mut()
+            .filter(|result| result.size_unmeasured)
+        {
+            let strategy = size_strategy_of(state, result);
+            measure_garbage_result(result, strategy, throttle.as_ref());
+        }
+    }
+
+    // with --json stdout carries the report, the listing to confirm goes to stderr
+    let question = "Are you sure you want to delete the files listed above? (y/N):";
+    match state.json {
+        true => {
+            display_garbage_to_clean(
+                &filtered_garbage,
+                state.output_encoding,
+                state.size_format,
+                &mut io::stderr().lock(),
+            )?;
+        }
+        false => {
+            display_garbage_to_clean(
+                &filtered_garbage,
+                state.output_encoding,
+                state.size_format,
+                &mut io::stdout().lock(),
+            )?;
+        }
+    }
+
+    let confirmation = match state.yes {
+        true => true,
+        false => {
+            match state.json {
+                true => eprintln!("{}", question),
+                false => println!("{}", question),
+            }
+            match confirm_clean(
+                input,
+                &filtered_garbage,
+                state.confirm_over,
+                state.size_format,
+            ) {
+                Err(GarbageError::IOError(error)) if error.kind() == io::ErrorKind::TimedOut => {
+                    return Err(GarbageError::PromptTimedOut);
+                }
+                confirmation => confirmation?,
+            }
+        }
+    };
+
+    if confirmation {
+        let _lock = match lock {
+            Some(lock) => lock,
+            None => lock_garbage_result_vec_cache(path, state.wait_for_lock)?,
+        };
+        let cleaned_ids = cleaned_garbage_ids(&garbage, &filtered_garbage);
+        let selections = clean_garbage_from_vec_with(
+            filtered_garbage,
+            state.delete_order,
+            state.delete_empty_parents.then_some(path),
+            deleter(state.verify, state.force_readonly),
+        )?;
+        update_cache_after_clean(path, garbage, &cleaned_ids)?;
+        record_clean_history(state, &selections);
+        let outcome = clean_outcome(&selections, state.fail_on_partial, &mut io::stderr().lock());
+        if state.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&CleanReport::new(selections))?
+            );
+        } else {
+            display_clean_results(&selections, state, &mut io::stdout().lock())?;
+            println!(
+                "{}",
+                CleanSummary::from_selections(&selections).describe(state.size_format)
+            );
+        }
+        outcome?;
+        if let Some(marker) = &state.since_file {
+            write_since_marker(marker, SystemTime::now())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints every path the clean deleted after a green check mark.
+fn display_clean_results(
+    selections: &[DeleteOperationSelection],
+    state: &AppState,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let deleted = match state.output_encoding {
+        OutputEncoding::Utf8 => '✓',
+        _ => '+',
+    };
+    // failures are reported once, on stderr by the clean outcome
+    for result in selections
+        .iter()
+        .flat_map(|selection| &selection.result)
+        .filter(|result| result.success)
+    {
+        if state.color {
+            queue!(out, SetForegroundColor(Color::Green))?;
+        }
+        write!(out, "{}", deleted)?;
+        if state.color {
+            queue!(out, ResetColor)?;
+        }
+        let path = state
+            .output_encoding
+            .sanitize(&result.path.display().to_string());
+        writeln!(out, " {}", path)?;
+    }
+    Ok(())
+}
+
+/// Deletes a deletable from disk, with `force_readonly` a deletion denied by read-only
+/// entries is retried once they are writable, with `verify` the path is checked to be gone
+/// afterwards.
+fn deleter(
+    verify: bool,
+    force_readonly: bool,
+) -> impl Fn(&Path, &fs::Metadata) -> DeleteOperationResult {
+    let delete_path = move |path: &Path, metadata: &fs::Metadata| match force_readonly {
+        true => delete_path_forcing_readonly(path, metadata),
+        false => delete_path(path, metadata),
+    };
+    let verified_delete = verify_deletions(delete_path);
+    move |path, metadata| match verify {
+        true => verified_delete(path, metadata),
+        false => delete_path(path, metadata),
+    }
+}
+
+/// Writes every deletion that failed to `out`, with `fail_on_partial` a single failure
+/// fails the whole clean.
+fn clean_outcome(
+    selections: &[DeleteOperationSelection],
+    fail_on_partial: bool,
+    out: &mut impl Write,
+) -> Result<(), GarbageError> {
+    let failed: Vec<_> = selections
+        .iter()
+        .flat_map(|selection| &selection.result)
+        .filter(|result| !result.success)
+        .collect();
+    for result in &failed {
+        writeln!(
+            out,
+            "Failed to delete {}: {}",
+            result.path.display(),
+            result.failure_reason()
+        )?;
+    }
+    match fail_on_partial && !failed.is_empty() {
+        true => Err(GarbageError::PartialClean(failed.len())),
+        false => Ok(()),
+    }
+}
+
+fn confirm_clean(
+    input: &mut impl BufRead,
+    garbage: &[GarbageRecognizerResult],
+    confirm_over: Option<u64>,
+    size_format: SizeFormat,
+) -> Result<bool, GarbageError> {
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    Ok(answer.trim().eq_ignore_ascii_case("y")
+        && confirm_large_deletion(
+            input,
+            compute_deletable_size_from_garbage_results(garbage),
+            confirm_over,
+            size_format,
+        )?)
+}
+
+/// Extra safety gate for deletions above the threshold, the user has to type the
+/// size in GB as displayed to proceed.
+fn confirm_large_deletion(
+    input: &mut impl BufRead,
+    size: u64,
+    threshold: Option<u64>,
+    size_format: SizeFormat,
+) -> Result<bool, GarbageError> {
+    match threshold {
+        Some(threshold) if size > threshold => {}
+        _ => return Ok(true),
+    }
+
+    let expected = format!("{:.2}", size as f64 / 1e9);
+    eprintln!(
+        "This will delete {}, type {} to confirm:",
+        format_size(size, size_format),
+        expected
+    );
+
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    let confirmed = answer.trim() == expected;
+    if !confirmed {
+        eprintln!("The size does not match, nothing was deleted.");
+    }
+    Ok(confirmed)
+}
+
+fn update_cache_after_clean(
+    path: &Path,
+    garbage: Vec<GarbageRecognizerResult>,
+    cleaned_ids: &[GarbageIndex],
+) -> Result<(), GarbageError> {
+    let remaining_garbage = remove_garbage_from_ids(garbage, cleaned_ids);
+    refresh_garbage_result_vec_cache(path, &remaining_garbage)?;
+    Ok(())
+}
+
+fn display_size(result: &GarbageRecognizerResult, size_format: SizeFormat) -> String {
+    if result.size_unmeasured {
+        "(not measured)".to_string()
+    } else {
+        format_size(result.size, size_format)
+    }
+}
+
+/// A total with its exact byte count, in the fixed unit if one was chosen.
+fn display_total(bytes: u64, size_format: SizeFormat) -> String {
+    match size_format.unit {
+        Some(_) => format!(
+            "{} ({} bytes)",
+            format_size(bytes, size_format),
+            format_number(bytes)
+        ),
+        None => format_bytes_exact(bytes, size_format.decimals),
+    }
+}
+
+fn display_garbage_to_clean(
+    results: &[GarbageRecognizerResult],
+    encoding: OutputEncoding,
+    size_format: SizeFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for garbage in results {
+        writeln!(
+            out,
+            "[{}] - {}",
+            garbage.index,
+            encoding.sanitize(&garbage.directory.display().to_string())
+        )?;
+        writeln!(
+            out,
+            "\t{}, to clean: {}",
+            encoding.sanitize(&garbage.recognizer_name),
+            display_size(garbage, size_format)
+        )?;
+        for deletable in &garbage.deletable {
+            writeln!(
+                out,
+                "\tDelete: {} ({})",
+                encoding.sanitize(&deletable.path.display().to_string()),
+                format_size(deletable.size, size_format)
+            )?;
+        }
+        writeln!(out)?;
+    }
+    writeln!(out)
+}
+
+fn arg_list_recognizer(state: &AppState) {
+    println!("All available garbage recognizers\n");
+    for recognizer in &state.garbage_recognizer {
+        let mut line = format!("• {}", recognizer.name);
+        if !recognizer.aliases.is_empty() {
+            line.push_str(&format!(" (also {})", recognizer.aliases.join(", ")));
+        }
+        println!("{}", state.output_encoding.sanitize(&line));
+    }
+    println!("\nBy default all are selected. Use --include-recognizer or --exclude-recognizer to adjust the selection");
+}
+
+/// The recognizer file to load, the one of `--import-recognizers` or else the default
+/// config if it was created.
+fn recognizer_config_path(args: &Args, default_config: Option<PathBuf>) -> Option<PathBuf> {
+    args.import_recognizers
+        .clone()
+        .or_else(|| default_config.filter(|file| file.exists()))
+}
+
+fn register_garbage_recognizer(state: &mut AppState, args: &Args) -> Result<(), ApplicationError> {
+    let mut recognizer = available_recognizer();
+
+    if let Some(file) = recognizer_config_path(args, default_config_path()) {
+        let imported = import_recognizers(&file)?;
+        recognizer.retain(|r| {
+            !imported
+                .iter()
+                .any(|i| i.name.eq_ignore_ascii_case(&r.name))
+        });
+        recognizer.extend(imported);
+    }
+
+    for pattern in unmatched_recognizer_patterns(&recognizer, args) {
+        warn(
+            args.strict,
+            &mut io::stderr(),
+            format!("{} matches none of the recognizers", pattern),
+        )?;
+    }
+    include_recognizer(&mut recognizer, args);
+    exclude_recognizer(&mut recognizer, args);
+    if let Some(names) = &args.also_delete {
+        also_delete(&mut recognizer, names);
+    }
+
+    if let Some(prioritize) = &args.prioritize {
+        prioritize_recognizer(&mut recognizer, prioritize)?;
+    }
+
+    // rebuilt on every reload, a program is only allowed while the arguments allow it
+    state.allowed_commands = args.allow_command.iter().flatten().cloned().collect();
+    retain_allowed_commands(&mut recognizer, &state.allowed_commands);
+    validate_recognizer_delete_targets(&recognizer)?;
+
+    state.garbage_recognizer = recognizer.into_iter().collect();
+    Ok(())
+}
+
+/// The `--include-recognizer` and `--exclude-recognizer` patterns no recognizer matches,
+/// most likely misspelled names.
+fn unmatched_recognizer_patterns(recognizer_vec: &[GarbageRecognizer], args: &Args) -> Vec<String> {
+    args.include_recognizer
+        .iter()
+        .chain(&args.exclude_recognizer)
+        .flatten()
+        .map(|pattern| pattern.to_lowercase())
+        .filter(|pattern| {
+            !recognizer_vec
+                .iter()
+                .any(|recognizer| matches_any_pattern(recognizer, std::slice::from_ref(pattern)))
+        })
+        .collect()
+}
+
+fn include_recognizer(recognizer_vec: &mut Vec<GarbageRecognizer>, args: &Args) {
+    if let Some(include_recognizer) = &args.include_recognizer {
+        let patterns = lowercase_patterns(include_recognizer);
+        recognizer_vec.retain(|r| matches_any_pattern(r, &patterns));
     }
 }
 
 fn exclude_recognizer(recognizer_vec: &mut Vec<GarbageRecognizer>, args: &Args) {
     if let Some(exclude_recognizer) = &args.exclude_recognizer {
-        recognizer_vec.retain(|r| !exclude_recognizer.contains(&r.name.to_lowercase()));
+        let patterns = lowercase_patterns(exclude_recognizer);
+        recognizer_vec.retain(|r| !matches_any_pattern(r, &patterns));
+    }
+}
+
+/// Adds the directory names to the deletables of every recognizer with project markers,
+/// a markerless one would match any directory with such a name. They go through the
+/// same delete target validation as the recognizers.
+fn also_delete(recognizer_vec: &mut [GarbageRecognizer], names: &[String]) {
+    for recognizer in recognizer_vec
+        .iter_mut()
+        .filter(|r| !r.recognize.is_empty())
+    {
+        for name in names {
+            let target = FileType::Directory(name.clone());
+            if !recognizer.delete.contains(&target) {
+                recognizer.delete.push(target);
+            }
+        }
+    }
+}
+
+fn lowercase_patterns(patterns: &[String]) -> Vec<String> {
+    patterns
+        .iter()
+        .map(|pattern| pattern.to_lowercase())
+        .collect()
+}
+
+/// `*` and `?` match like in globs, a pattern without them has to equal the lowercased
+/// name or one of the aliases of the recognizer.
+fn matches_any_pattern(recognizer: &GarbageRecognizer, patterns: &[String]) -> bool {
+    recognizer.names().any(|name| {
+        let name = name.to_lowercase();
+        patterns.iter().any(|pattern| glob_match(pattern, &name))
+    })
+}
+
+/// Moves the named recognizers above all others, the first name gets the highest priority.
+/// A recognizer is named by its name or one of its aliases.
+fn prioritize_recognizer(
+    recognizer_vec: &mut [GarbageRecognizer],
+    names: &[String],
+) -> Result<(), ApplicationError> {
+    let base_priority = recognizer_vec
+        .iter()
+        .map(|r| r.priority)
+        .max()
+        .unwrap_or_default();
+
+    for (position, name) in names.iter().enumerate() {
+        let recognizer = recognizer_vec
+            .iter_mut()
+            .find(|r| r.names().any(|known| known.eq_ignore_ascii_case(name)))
+            .ok_or_else(|| ApplicationError::UnknownRecognizer(name.clone()))?;
+        recognizer.priority = base_priority + (names.len() - position) as i32;
+    }
+
+    Ok(())
+}
+
+fn validate_recognizer_delete_targets(
+    recognizer_vec: &[GarbageRecognizer],
+) -> Result<(), ApplicationError> {
+    for recognizer in recognizer_vec {
+        if let Some(target) = recognizer.dangerous_delete_target() {
+            return Err(ApplicationError::DangerousRecognizer(
+                recognizer.name.clone(),
+                target.to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn retain_allowed_commands(recognizer_vec: &mut Vec<GarbageRecognizer>, allowed: &HashSet<String>) {
+    recognizer_vec.retain(|r| match &r.command {
+        Some(command) => allowed.contains(&command.program),
+        None => true,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{ApplicationError, GarbageError};
+    use crate::garbage::{
+        clean_garbage_from_vec_with, find_garbage_in_directory, read_history, result_fixture,
+        sort_garbage, DeleteOperationResult, DeleteOperationSelection, DeleteOrder, FileType,
+        GarbageIndex, GarbageRecognizer, GarbageRecognizerResult, ListSort, RecognizeBy,
+        RecognizerCommand,
+    };
+    use crate::recognizer::available_recognizer;
+    use crate::recognizer::RecognizerFileWatcher;
+    use crate::ui::{BuildContext, ColorChoice, OutputEncoding, Size};
+    use crate::utils::{
+        delete_garbage_result_vec_cache, read_garbage_result_vec_cache, read_since_marker,
+        replace_garbage_result_vec_cache, SizeFormat, TimedLineReader,
+    };
+    use crate::{
+        arg_check, arg_clean, arg_enforce_under, arg_explain_size, arg_prune, clean_is_dry_run,
+        clean_outcome, confirm_large_deletion, dedup_scan_roots, display_clean_results,
+        display_garbage_changes, display_garbage_to_clean, exclude_recognizer, include_recognizer,
+        new_garbage_since_cache, prioritize_recognizer, read_scan_roots, recognizer_config_path,
+        register_garbage_recognizer, reload_recognizers_if_changed, render_garbage_oneline,
+        render_garbage_results, render_garbage_tree, resolve_scan_root, scan_garbage,
+        select_garbage, terminal_size_or_default, truncate_left, unmatched_recognizer_patterns,
+        validate_recognizer_delete_targets, validate_scan_root, AppState, Args,
+    };
+    use clap::Parser;
+    use std::env::temp_dir;
+    use std::fs;
+    use std::fs::File;
+    use std::io::{self, Cursor, Read};
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime};
+
+    #[test]
+    fn test_validate_scan_root() {
+        let temp_dir = temp_dir().join("wsg_dev_scan_root");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+        let file = temp_dir.join("Cargo.toml");
+        File::create(&file).expect("Failed to create test file");
+
+        assert!(validate_scan_root(&temp_dir).is_ok());
+        assert!(matches!(
+            validate_scan_root(&file),
+            Err(ApplicationError::NotADirectory(path)) if path == file
+        ));
+        assert!(matches!(
+            validate_scan_root(&temp_dir.join("missing")),
+            Err(ApplicationError::InvalidArgumentPath)
+        ));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_resolve_scan_root() {
+        let current_dir = std::env::current_dir().expect("Failed to get current directory");
+        let path = PathBuf::from("/Users/testuser/Projects");
+
+        assert_eq!(resolve_scan_root(&None, false).unwrap(), current_dir);
+        assert_eq!(resolve_scan_root(&Some(path.clone()), false).unwrap(), path);
+        assert_eq!(resolve_scan_root(&Some(path.clone()), true).unwrap(), path);
+        assert!(matches!(
+            resolve_scan_root(&None, true),
+            Err(ApplicationError::MissingArgumentPath)
+        ));
+    }
+
+    #[test]
+    fn test_prioritize_recognizer() {
+        let temp_dir = temp_dir().join("wsg_dev_prioritize");
+        fs::create_dir_all(temp_dir.join("build")).expect("Failed to create test project");
+        File::create(temp_dir.join("pubspec.yaml")).expect("Failed to create test file");
+        File::create(temp_dir.join("CMakeLists.txt")).expect("Failed to create test file");
+
+        let recognizers = || {
+            vec![
+                GarbageRecognizer::new(
+                    "CMake",
+                    Some(vec![FileType::File("CMakeLists.txt".into())]),
+                    Some(vec![FileType::Directory("build".into())]),
+                ),
+                GarbageRecognizer::new(
+                    "Flutter",
+                    Some(vec![FileType::File("pubspec.yaml".into())]),
+                    Some(vec![FileType::Directory("build".into())]),
+                )
+                .with_aliases(&["dart"]),
+            ]
+        };
+        let scan = |recognizer_vec: Vec<GarbageRecognizer>| {
+            let mut state = AppState::new();
+            state.include_empty = true;
+            state.garbage_recognizer.extend(recognizer_vec);
+            find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan")
+        };
+
+        let garbage = scan(recognizers());
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].recognizer_name, "CMake");
+
+        let mut prioritized = recognizers();
+        prioritize_recognizer(&mut prioritized, &["flutter".to_string()])
+            .expect("Failed to prioritize");
+        let garbage = scan(prioritized);
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].recognizer_name, "Flutter");
+
+        let mut prioritized = recognizers();
+        prioritize_recognizer(&mut prioritized, &["Dart".to_string()])
+            .expect("Failed to prioritize by alias");
+        let garbage = scan(prioritized);
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].recognizer_name, "Flutter");
+
+        assert!(matches!(
+            prioritize_recognizer(&mut recognizers(), &["gradle".to_string()]),
+            Err(ApplicationError::UnknownRecognizer(name)) if name == "gradle"
+        ));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_validate_recognizer_delete_targets() {
+        let src_recognizer = GarbageRecognizer::new(
+            "Custom",
+            Some(vec![FileType::File("Makefile".into())]),
+            Some(vec![FileType::Directory("src".into())]),
+        );
+        assert!(matches!(
+            validate_recognizer_delete_targets(&[src_recognizer]),
+            Err(ApplicationError::DangerousRecognizer(name, target)) if name == "Custom" && target == "src"
+        ));
+
+        assert!(validate_recognizer_delete_targets(&available_recognizer()).is_ok());
+    }
+
+    #[test]
+    fn test_confirm_large_deletion() {
+        let size = 63_200_000_000;
+        let format = SizeFormat::default();
+
+        assert!(confirm_large_deletion(&mut Cursor::new(""), size, None, format).unwrap());
+        assert!(confirm_large_deletion(&mut Cursor::new(""), size, Some(size), format).unwrap());
+
+        let threshold = Some(50_000_000_000);
+        assert!(
+            !confirm_large_deletion(&mut Cursor::new("63\n"), size, threshold, format).unwrap()
+        );
+        assert!(!confirm_large_deletion(&mut Cursor::new("y\n"), size, threshold, format).unwrap());
+        assert!(
+            confirm_large_deletion(&mut Cursor::new("63.20\n"), size, threshold, format).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reload_recognizers_if_changed() {
+        let temp_dir = temp_dir().join("wsg_dev_reload_recognizers");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+        let file = temp_dir.join("recognizers.toml");
+        let write_config = |content: &str, modified: SystemTime| {
+            fs::write(&file, content).expect("Failed to write recognizer file");
+            File::options()
+                .write(true)
+                .open(&file)
+                .and_then(|f| f.set_modified(modified))
+                .expect("Failed to set modification time");
+        };
+        let recognizer = |name: &str| {
+            format!(
+                "[[recognizer]]\nname = \"{}\"\nrecognize = [{{ file = \"{}.marker\" }}]\ndelete = [{{ directory = \"out\" }}]\n",
+                name, name
+            )
+        };
+        let has_recognizer =
+            |state: &AppState, name: &str| state.garbage_recognizer.iter().any(|r| r.name == name);
+
+        let now = SystemTime::now();
+        write_config(&recognizer("Gradle"), now - Duration::from_secs(60));
+        let args = Args::parse_from([
+            "wsg",
+            "--watch",
+            "1s",
+            "--recognizer-file-watch",
+            "--import-recognizers",
+            file.to_str().expect("Temporary path is not UTF-8"),
+        ]);
+        let mut state = AppState::new();
+        register_garbage_recognizer(&mut state, &args).expect("Failed to register recognizers");
+        let mut watcher = RecognizerFileWatcher::new(&file);
+
+        assert!(has_recognizer(&state, "Gradle"));
+        assert!(
+            !reload_recognizers_if_changed(&mut state, &args, &mut watcher)
+                .expect("Failed to reload recognizers")
+        );
+
+        write_config(&recognizer("Maven"), now - Duration::from_secs(30));
+        assert!(
+            reload_recognizers_if_changed(&mut state, &args, &mut watcher)
+                .expect("Failed to reload recognizers")
+        );
+        assert!(has_recognizer(&state, "Maven"));
+        assert!(!has_recognizer(&state, "Gradle"));
+        assert!(has_recognizer(&state, "Rust"));
+
+        write_config("[[recognizer]\nname = ", now);
+        assert!(reload_recognizers_if_changed(&mut state, &args, &mut watcher).is_err());
+        assert!(has_recognizer(&state, "Maven"));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_display_garbage_to_clean_into_buffer() {
+        let garbage = vec![result_fixture(
+            4,
+            "Rust",
+            "/projects/wsg",
+            1_500_000,
+            &[1_500_000],
+        )];
+        let mut buffer = Vec::new();
+
+        display_garbage_to_clean(
+            &garbage,
+            OutputEncoding::Utf8,
+            SizeFormat::default(),
+            &mut buffer,
+        )
+        .expect("Failed to render into buffer");
+
+        assert_eq!(
+            buffer,
+            b"[4] - /projects/wsg\n\tRust, to clean: 1.50 MB\n\tDelete: /projects/wsg/target (1.50 MB)\n\n\n"
+        );
+    }
+
+    #[test]
+    fn test_safe_mode_clean_requires_really_clean() {
+        let path = temp_dir().join("wsg_dev_safe_clean");
+        let target = path.join("project").join("target");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(path.join("project").join("Cargo.toml")).expect("Failed to create test file");
+        File::create(target.join("binary")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        available_recognizer()
+            .into_iter()
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+        let garbage = find_garbage_in_directory(&path, &state).expect("Failed to scan");
+        replace_garbage_result_vec_cache(&path, &garbage).expect("Failed to write cache");
+        state.safe_mode = true;
+
+        let args = Args::parse_from(["wsg", "--clean", "all"]);
+        state.dry_run = clean_is_dry_run(&args, state.safe_mode);
+        arg_clean(
+            &state,
+            &path,
+            &[GarbageIndex::All],
+            false,
+            &mut Cursor::new("y\n"),
+        )
+        .expect("Failed to clean");
+        assert!(target.exists());
+
+        let args = Args::parse_from(["wsg", "--clean", "all", "--really-clean"]);
+        state.dry_run = clean_is_dry_run(&args, state.safe_mode);
+        arg_clean(
+            &state,
+            &path,
+            &[GarbageIndex::All],
+            false,
+            &mut Cursor::new("y\n"),
+        )
+        .expect("Failed to clean");
+        assert!(!target.exists());
+
+        let args = Args::parse_from(["wsg", "--clean", "all", "--really-clean", "--dry-run"]);
+        assert!(clean_is_dry_run(&args, true));
+        assert!(!clean_is_dry_run(
+            &Args::parse_from(["wsg", "--clean", "all"]),
+            false
+        ));
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_include_exclude_recognizer_patterns() {
+        let recognizers = || {
+            ["Java Gradle", "Java Maven", "Rust"]
+                .into_iter()
+                .map(|name| {
+                    GarbageRecognizer::new(
+                        name,
+                        Some(vec![FileType::File("marker".into())]),
+                        Some(vec![FileType::Directory("build".into())]),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let names = |recognizer_vec: Vec<GarbageRecognizer>| -> Vec<String> {
+            recognizer_vec.into_iter().map(|r| r.name).collect()
+        };
+
+        let mut excluded = recognizers();
+        exclude_recognizer(
+            &mut excluded,
+            &Args::parse_from(["wsg", "--exclude-recognizer", "java*"]),
+        );
+        assert_eq!(names(excluded), vec!["Rust"]);
+
+        let mut included = recognizers();
+        include_recognizer(
+            &mut included,
+            &Args::parse_from(["wsg", "--include-recognizer", "Java?Maven,rust"]),
+        );
+        assert_eq!(names(included), vec!["Java Maven", "Rust"]);
+
+        let mut exact = recognizers();
+        include_recognizer(
+            &mut exact,
+            &Args::parse_from(["wsg", "--include-recognizer", "java maven,rus"]),
+        );
+        assert_eq!(names(exact), vec!["Java Maven"]);
+    }
+
+    #[test]
+    fn test_include_exclude_recognizer_aliases() {
+        let names = |recognizer_vec: Vec<GarbageRecognizer>| -> Vec<String> {
+            let mut names: Vec<String> = recognizer_vec.into_iter().map(|r| r.name).collect();
+            names.sort();
+            names
+        };
+
+        let mut included = available_recognizer();
+        let args = Args::parse_from(["wsg", "--include-recognizer", "npm,Cargo"]);
+        include_recognizer(&mut included, &args);
+        assert_eq!(names(included), vec!["NodeJS", "Rust"]);
+        assert!(unmatched_recognizer_patterns(&available_recognizer(), &args).is_empty());
+
+        let mut excluded = available_recognizer();
+        exclude_recognizer(
+            &mut excluded,
+            &Args::parse_from(["wsg", "--exclude-recognizer", "js,rs"]),
+        );
+        let excluded = names(excluded);
+        assert_eq!(excluded.len(), available_recognizer().len() - 2);
+        assert!(!excluded.contains(&"NodeJS".to_string()));
+        assert!(!excluded.contains(&"Rust".to_string()));
+    }
+
+    #[test]
+    fn test_read_scan_roots() {
+        let temp_dir = temp_dir().join("wsg_dev_roots_from");
+        for project in ["api", "web"] {
+            fs::create_dir_all(temp_dir.join(project)).expect("Failed to create test project");
+        }
+        let roots_file = temp_dir.join("roots.txt");
+        fs::write(
+            &roots_file,
+            format!(
+                "{}\n\n{}\n  {}\n",
+                temp_dir.join("api").display(),
+                temp_dir.join("missing").display(),
+                temp_dir.join("web").display()
+            ),
+        )
+        .expect("Failed to write roots file");
+
+        let mut warnings = Vec::new();
+        let roots =
+            read_scan_roots(&roots_file, false, &mut warnings).expect("Failed to read roots");
+        assert_eq!(roots, vec![temp_dir.join("api"), temp_dir.join("web")]);
+
+        let warnings = String::from_utf8(warnings).expect("Warnings are not UTF-8");
+        assert_eq!(warnings.lines().count(), 1);
+        assert!(warnings.contains("missing"));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_dedup_scan_roots_scans_each_project_once() {
+        let temp_dir = temp_dir().join("wsg_dev_dedup_roots");
+        let code = temp_dir.join("code");
+        for project in ["api", "web"] {
+            let target = code.join(project).join("target");
+            fs::create_dir_all(&target).expect("Failed to create test project");
+            File::create(code.join(project).join("Cargo.toml"))
+                .expect("Failed to create test file");
+            fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+        }
+        let other = temp_dir.join("other");
+        fs::create_dir_all(&other).expect("Failed to create test directory");
+
+        let mut notes = Vec::new();
+        let roots = dedup_scan_roots(
+            vec![
+                code.join("api"),
+                code.clone(),
+                other.clone(),
+                code.join("."),
+            ],
+            &mut notes,
+        )
+        .expect("Failed to write notes");
+        assert_eq!(roots, vec![code.clone(), other]);
+        let notes = String::from_utf8(notes).expect("Notes are not UTF-8");
+        assert_eq!(notes.lines().count(), 2);
+        assert!(notes.contains(&format!("skipping {}", code.join("api").display())));
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let mut projects: Vec<PathBuf> = roots
+            .iter()
+            .flat_map(|root| find_garbage_in_directory(root, &state).expect("Failed to scan"))
+            .map(|result| result.directory)
+            .collect();
+        projects.sort();
+        assert_eq!(projects, vec![code.join("api"), code.join("web")]);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_listing_falls_back_to_default_width() {
+        let terminal_size = terminal_size_or_default(|| Err(std::io::Error::other("no tty")));
+        assert_eq!(terminal_size.width, Some(80));
+
+        let results = vec![result_fixture(
+            0,
+            "Rust",
+            "/projects/wsg",
+            1_500_000,
+            &[1_500_000],
+        )];
+        let mut out = Vec::new();
+        render_garbage_results(
+            &results,
+            &AppState::new(),
+            &BuildContext::new(terminal_size),
+            &mut out,
+        )
+        .expect("Failed to render listing");
+
+        let out = String::from_utf8(out).expect("Listing is not UTF-8");
+        let header = out
+            .lines()
+            .find(|line| line.starts_with('┌'))
+            .expect("Listing has no box");
+        assert_eq!(header.chars().count(), 80);
+    }
+
+    #[test]
+    fn test_group_by_recognizer_listing() {
+        let result = |id: u32, name: &str, size: u64| {
+            result_fixture(id, name, format!("/projects/{}", id), size, &[])
+        };
+        let results = vec![
+            result(0, "Rust", 1_000),
+            result(1, "NodeJS", 5_000),
+            result(2, "Rust", 3_000),
+        ];
+        let mut state = AppState::new();
+        state.group_by_recognizer = true;
+        let mut out = Vec::new();
+        render_garbage_results(
+            &results,
+            &state,
+            &BuildContext::new(Size::new(80, 10)),
+            &mut out,
+        )
+        .expect("Failed to render listing");
+
+        let out = String::from_utf8(out).expect("Listing is not UTF-8");
+        let lines: Vec<&str> = out
+            .lines()
+            .filter(|line| line.contains(" project") || line.starts_with('┌'))
+            .map(|line| line.trim_end_matches(['─', '┐']))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                "NodeJS (1 project, 5.00 kB)",
+                "┌ [1] NodeJS ",
+                "Rust (2 projects, 4.00 kB)",
+                "┌ [0] Rust ",
+                "┌ [2] Rust ",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_new_garbage_since_cache() {
+        let path = temp_dir().join("wsg_dev_new_only");
+        for project in ["unchanged", "grown"] {
+            let project = path.join(project);
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+            fs::write(project.join("target").join("binary"), [0; 100])
+                .expect("Failed to create test file");
+        }
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let before = find_garbage_in_directory(&path, &state).expect("Failed to scan");
+        replace_garbage_result_vec_cache(&path, &before).expect("Failed to write cache");
+
+        fs::write(path.join("grown").join("target").join("debug"), [0; 50])
+            .expect("Failed to create test file");
+        let new_project = path.join("new");
+        fs::create_dir_all(new_project.join("target")).expect("Failed to create test project");
+        File::create(new_project.join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(new_project.join("target").join("binary"), [0; 10])
+            .expect("Failed to create test file");
+
+        let (changes, timed_out) =
+            new_garbage_since_cache(&state, &path).expect("Failed to compare scans");
+        assert!(!timed_out);
+        let changed: Vec<_> = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.result.directory.clone(),
+                    change.previous_size,
+                    change.growth(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                (path.join("grown"), Some(100), 50),
+                (new_project.clone(), None, 10),
+            ]
+        );
+
+        let mut out = Vec::new();
+        display_garbage_changes(
+            &changes,
+            OutputEncoding::Utf8,
+            SizeFormat::default(),
+            &mut out,
+        )
+        .expect("Failed to render changes");
+        let out = String::from_utf8(out).expect("Listing is not UTF-8");
+        assert!(out.contains("grown: +50.00 B (was 100.00 B)"));
+        assert!(out.contains("new: +10.00 B (new)"));
+        assert!(!out.contains("unchanged"));
+
+        let (changes, _) = new_garbage_since_cache(&state, &path).expect("Failed to compare scans");
+        assert!(changes.is_empty());
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_prompt_timeout_fails() {
+        struct NoResponse;
+        impl Read for NoResponse {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                loop {
+                    thread::park();
+                }
+            }
+        }
+
+        let path = temp_dir().join("wsg_dev_prompt_timeout");
+        let target = path.join("project").join("target");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(path.join("project").join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let garbage = find_garbage_in_directory(&path, &state).expect("Failed to scan");
+        replace_garbage_result_vec_cache(&path, &garbage).expect("Failed to write cache");
+
+        let timeout = Duration::from_millis(200);
+        let started = Instant::now();
+        let mut input = TimedLineReader::spawn(io::BufReader::new(NoResponse), timeout);
+        let error = arg_clean(&state, &path, &[GarbageIndex::All], false, &mut input)
+            .expect_err("An unanswered prompt must fail");
+        assert_eq!(error.code(), "prompt_timed_out");
+        assert!(started.elapsed() >= timeout);
+        assert!(target.exists());
+
+        let mut input = TimedLineReader::spawn(Cursor::new("y\n"), timeout);
+        arg_clean(&state, &path, &[GarbageIndex::All], false, &mut input).expect("Failed to clean");
+        assert!(!target.exists());
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_warn_size_emphasizes_large_entries() {
+        let result =
+            |id: u32, size: u64| result_fixture(id, "Rust", format!("/projects/{}", id), size, &[]);
+        let results = vec![result(0, 5_000_000), result(1, 500)];
+        let render = |color: ColorChoice| {
+            let mut state = AppState::new();
+            state.color = color.enabled(false);
+            state.warn_size = Some(1_000_000);
+            let mut out = Vec::new();
+            render_garbage_results(
+                &results,
+                &state,
+                &BuildContext::new(Size::new(80, 10)),
+                &mut out,
+            )
+            .expect("Failed to render listing");
+            String::from_utf8(out).expect("Listing is not UTF-8")
+        };
+
+        let out = render(ColorChoice::Always);
+        let (large, small) = out.split_at(out.find("[1] Rust").expect("Missing entry"));
+        let emphasis = "\x1b[38;5;11m\x1b[1m┌ [0] Rust";
+        assert!(large.contains(emphasis), "{:?}", large);
+        assert!(large.contains("\x1b[0m"));
+        assert!(!small.contains('\x1b'));
+
+        assert!(!render(ColorChoice::Never).contains('\x1b'));
+    }
+
+    #[test]
+    fn test_display_clean_results_colors() {
+        let selections = vec![DeleteOperationSelection::new(
+            "Rust",
+            300,
+            vec![
+                DeleteOperationResult::success(PathBuf::from("/projects/api/target"), 3),
+                DeleteOperationResult::failure(
+                    PathBuf::from("/projects/web/target"),
+                    Some("Permission denied".to_string()),
+                ),
+            ],
+        )];
+        let render = |color: ColorChoice| {
+            let mut state = AppState::new();
+            state.color = color.enabled(false);
+            let mut out = Vec::new();
+            display_clean_results(&selections, &state, &mut out).expect("Failed to render");
+            String::from_utf8(out).expect("Clean results are not UTF-8")
+        };
+
+        assert_eq!(
+            render(ColorChoice::Always),
+            "\x1b[38;5;10m✓\x1b[0m /projects/api/target\n"
+        );
+        assert_eq!(render(ColorChoice::Never), "✓ /projects/api/target\n");
+        assert_eq!(
+            render(ColorChoice::Auto),
+            render(ColorChoice::Never),
+            "Colored without a terminal"
+        );
+    }
+
+    #[test]
+    fn test_prune_scans_and_cleans_in_one_go() {
+        let path = temp_dir().join("wsg_dev_prune");
+        let target = path.join("project").join("target");
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let create_project = || {
+            fs::create_dir_all(&target).expect("Failed to create test project");
+            File::create(path.join("project").join("Cargo.toml"))
+                .expect("Failed to create test file");
+            fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+        };
+        create_project();
+        let _ = delete_garbage_result_vec_cache(&path);
+
+        let mut input = Cursor::new("n\n");
+        arg_prune(&state, &path, &mut input).expect("Failed to prune");
+        assert!(target.exists());
+
+        let mut input = Cursor::new("y\n");
+        arg_prune(&state, &path, &mut input).expect("Failed to prune");
+        assert!(!target.exists());
+
+        create_project();
+        state.yes = true;
+        let mut input = Cursor::new("");
+        arg_prune(&state, &path, &mut input).expect("Failed to prune");
+        assert!(!target.exists());
+
+        let _ = delete_garbage_result_vec_cache(&path);
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_since_file_cleans_only_untouched_garbage() {
+        let path = temp_dir().join("wsg_dev_since_file");
+        let marker = path.join("state").join("last-clean");
+        let projects = path.join("projects");
+        let mut state = AppState::new();
+        state.yes = true;
+        state.since_file = Some(marker.clone());
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let create_project = |name: &str, modified: SystemTime| {
+            let target = projects.join(name).join("target");
+            fs::create_dir_all(&target).expect("Failed to create test project");
+            File::create(projects.join(name).join("Cargo.toml"))
+                .expect("Failed to create test file");
+            fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+            for touched in [target.join("binary"), target.clone()] {
+                File::open(touched)
+                    .and_then(|file| file.set_modified(modified))
+                    .expect("Failed to set the modification time");
+            }
+            target
+        };
+        let _ = delete_garbage_result_vec_cache(&projects);
+
+        // without a marker everything is cleaned
+        let now = SystemTime::now();
+        let fresh = create_project("fresh", now);
+        arg_prune(&state, &projects, &mut Cursor::new("")).expect("Failed to prune");
+        assert!(!fresh.exists());
+        let last_clean = read_since_marker(&marker)
+            .expect("Failed to read marker")
+            .expect("No marker written");
+        assert!(last_clean <= SystemTime::now());
+        assert!(last_clean > now - Duration::from_secs(60));
+
+        let day = Duration::from_secs(24 * 60 * 60);
+        let stale = create_project("stale", last_clean - day);
+        let fresh = create_project("fresh", last_clean + day);
+        arg_prune(&state, &projects, &mut Cursor::new("")).expect("Failed to prune");
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+
+        // a rebuild writing inside an old target directory counts as a touch
+        let rebuilt = create_project("rebuilt", last_clean - day);
+        File::open(rebuilt.join("binary"))
+            .and_then(|file| file.set_modified(last_clean + day))
+            .expect("Failed to set the modification time");
+        assert!(matches!(
+            arg_prune(&state, &projects, &mut Cursor::new("")),
+            Err(ApplicationError::NothingToClean(_))
+        ));
+        assert!(rebuilt.exists());
+
+        let _ = delete_garbage_result_vec_cache(&projects);
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_refuses_listing_outside_scan_root() {
+        let path = temp_dir().join("wsg_dev_clean_outside_root");
+        let scanned = path.join("scanned");
+        let other = path.join("other");
+        let target = other.join("project").join("target");
+        fs::create_dir_all(&scanned).expect("Failed to create test directory");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(other.join("project").join("Cargo.toml")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        // the listing of another path, as if read through a colliding cache file name
+        let garbage = find_garbage_in_directory(&other, &state).expect("Failed to scan");
+        replace_garbage_result_vec_cache(&scanned, &garbage).expect("Failed to write cache");
+
+        let refused = arg_clean(
+            &state,
+            &scanned,
+            &[GarbageIndex::All],
+            false,
+            &mut Cursor::new("y\n"),
+        );
+        assert!(matches!(
+            refused,
+            Err(ApplicationError::OutsideScanRoot(deletable, _)) if deletable.starts_with(fs::canonicalize(&other).expect("Failed to canonicalize"))
+        ));
+        assert!(target.exists());
+
+        let _ = delete_garbage_result_vec_cache(&scanned);
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_refuses_without_fresh_listing() {
+        let path = temp_dir().join("wsg_dev_clean_no_listing");
+        let target = path.join("project").join("target");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(path.join("project").join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let _ = delete_garbage_result_vec_cache(&path);
+
+        let refused = arg_clean(
+            &state,
+            &path,
+            &[GarbageIndex::All],
+            false,
+            &mut Cursor::new("y\n"),
+        );
+        assert!(matches!(refused, Err(ApplicationError::NoFreshListing(_))));
+        assert!(target.exists());
+
+        // listed, but already past the TTL of the recognizer
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(
+            GarbageRecognizer::new(
+                "Rust",
+                Some(vec![FileType::File("Cargo.toml".into())]),
+                Some(vec![FileType::Directory("target".into())]),
+            )
+            .with_cache_ttl(Duration::ZERO),
+        );
+        let garbage = find_garbage_in_directory(&path, &state).expect("Failed to scan");
+        replace_garbage_result_vec_cache(&path, &garbage).expect("Failed to write cache");
+        let refused = arg_clean(
+            &state,
+            &path,
+            &[GarbageIndex::All],
+            false,
+            &mut Cursor::new("y\n"),
+        );
+        assert!(matches!(refused, Err(ApplicationError::NoFreshListing(_))));
+        assert!(target.exists());
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_with_force_rescans_and_cleans() {
+        let path = temp_dir().join("wsg_dev_clean_force");
+        let target = path.join("project").join("target");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(path.join("project").join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        // a fresh listing that no longer matches the disk is not what gets cleaned
+        replace_garbage_result_vec_cache(&path, &vec![]).expect("Failed to write cache");
+
+        arg_clean(
+            &state,
+            &path,
+            &[GarbageIndex::All],
+            true,
+            &mut Cursor::new("y\n"),
+        )
+        .expect("Failed to clean");
+        assert!(!target.exists());
+
+        let cached = read_garbage_result_vec_cache(&path, None).expect("Failed to read cache");
+        assert!(cached.is_empty());
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_clean_with_nothing_listed_does_not_prompt() {
+        let path = temp_dir().join("wsg_dev_clean_nothing");
+        fs::create_dir_all(&path).expect("Failed to create test directory");
+        replace_garbage_result_vec_cache(&path, &vec![]).expect("Failed to write cache");
+
+        let state = AppState::new();
+        let mut input = Cursor::new("y\n");
+        let nothing = arg_clean(&state, &path, &[GarbageIndex::All], false, &mut input)
+            .expect_err("Cleaning nothing must not succeed");
+
+        assert!(matches!(nothing, ApplicationError::NothingToClean(_)));
+        assert_eq!(
+            nothing.to_string(),
+            format!("Nothing to clean for {}.", path.display())
+        );
+        assert_eq!(input.position(), 0, "The clean prompt was answered");
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_fail_on_partial_clean() {
+        let path = temp_dir().join("wsg_dev_partial_clean");
+        let mut state = AppState::new();
+        state.include_empty = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let locked = path.join("locked");
+        let clean = |state: &AppState| {
+            for project in ["locked", "open"] {
+                fs::create_dir_all(path.join(project).join("target"))
+                    .expect("Failed to create test project");
+                File::create(path.join(project).join("Cargo.toml"))
+                    .expect("Failed to create test file");
+            }
+            let garbage = find_garbage_in_directory(&path, state).expect("Failed to scan");
+            let selections = clean_garbage_from_vec_with(
+                garbage,
+                DeleteOrder::Discovery,
+                None,
+                |deletable: &Path, _: &fs::Metadata| match deletable.starts_with(&locked) {
+                    true => DeleteOperationResult::failure(
+                        deletable.to_path_buf(),
+                        Some("Permission denied".to_string()),
+                    ),
+                    false => DeleteOperationResult::success(deletable.to_path_buf(), 0),
+                },
+            )
+            .expect("Failed to clean");
+            let mut report = Vec::new();
+            let outcome = clean_outcome(&selections, state.fail_on_partial, &mut report);
+            (
+                outcome,
+                String::from_utf8(report).expect("Report is not UTF-8"),
+            )
+        };
+        let (outcome, report) = clean(&state);
+        assert!(outcome.is_ok());
+        assert_eq!(
+            report,
+            format!(
+                "Failed to delete {}: Permission denied\n",
+                locked.join("target").display()
+            )
+        );
+
+        state.fail_on_partial = true;
+        let (outcome, _) = clean(&state);
+        assert!(matches!(outcome, Err(GarbageError::PartialClean(1))));
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_render_garbage_tree() {
+        let result =
+            |id: u32, directory: &str, size: u64| result_fixture(id, "Rust", directory, size, &[]);
+        let results = vec![
+            result(0, "/code/wsg", 1_000_000),
+            result(1, "/code/wsg/crates/ui", 2_000_000),
+            result(2, "/code/work/clients/acme/api", 3_000_000),
+            result(3, "/code/work/clients/acme/web", 500_000),
+            result(4, "/code/scratch/deep/down/demo", 4_000_000),
+        ];
+        let mut buffer = Vec::new();
+
+        render_garbage_tree(&results, Path::new("/code"), &AppState::new(), &mut buffer)
+            .expect("Failed to render into buffer");
+
+        assert_eq!(
+            String::from_utf8(buffer).expect("Rendered tree is not UTF-8"),
+            "/code (10.50 MB)\n\
+             \x20 scratch/deep/down/demo (4.00 MB) [4] Rust\n\
+             \x20 work/clients/acme (3.50 MB)\n\
+             \x20   api (3.00 MB) [2] Rust\n\
+             \x20   web (500.00 kB) [3] Rust\n\
+             \x20 wsg (3.00 MB) [0] Rust\n\
+             \x20   crates/ui (2.00 MB) [1] Rust\n"
+        );
+    }
+
+    #[test]
+    fn test_render_garbage_oneline() {
+        let result = |id: u32, recognizer: &str, directory: &str, size: u64| {
+            result_fixture(id, recognizer, directory, size, &[])
+        };
+        let mut results = vec![
+            result(0, "Rust", "/code/wsg", 1_000_000),
+            result(9, "NodeJS", "/code/web", 500),
+            result(
+                12,
+                "Rust",
+                "/home/testuser/clients/acme/monorepo/services/billing",
+                20_000_000,
+            ),
+        ];
+        sort_garbage(&mut results, ListSort::Size);
+        let mut buffer = Vec::new();
+
+        render_garbage_oneline(&results, &AppState::new(), 50, &mut buffer)
+            .expect("Failed to render into buffer");
+
+        let rendered = String::from_utf8(buffer).expect("Rendered listing is not UTF-8");
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            lines[..3],
+            [
+                "[12] 20.00 MB  Rust    ...onorepo/services/billing",
+                "[0]   1.00 MB  Rust    /code/wsg",
+                "[9]  500.00 B  NodeJS  /code/web",
+            ]
+        );
+        assert!(lines[..3].iter().all(|line| line.chars().count() <= 50));
+        assert_eq!(truncate_left("/a/b/c", 10), "/a/b/c");
+    }
+
+    #[test]
+    fn test_strict_fails_on_unknown_recognizer() {
+        let mut state = AppState::new();
+        let args = Args::parse_from(["wsg", "--include-recognizer", "rust,cobol"]);
+        register_garbage_recognizer(&mut state, &args).expect("Failed to register recognizers");
+        assert_eq!(state.garbage_recognizer.len(), 1);
+
+        let mut state = AppState::new();
+        let args = Args::parse_from(["wsg", "--include-recognizer", "rust,cobol", "--strict"]);
+        assert!(matches!(
+            register_garbage_recognizer(&mut state, &args),
+            Err(ApplicationError::GarbageError(GarbageError::Strict(warning))) if warning.contains("cobol")
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_strict_fails_on_skipped_directory() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let path = temp_dir().join("wsg_dev_strict_skipped");
+        let target = path.join("project").join("target");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(path.join("project").join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let scan = |state: &AppState| {
+            let _ = delete_garbage_result_vec_cache(&path);
+            scan_garbage(state, &path)
+        };
+
+        // a link that can't be followed is skipped like an unreadable directory
+        symlink(path.join("gone"), path.join("dangling")).expect("Failed to create link");
+        state.follow_links = true;
+        let garbage = scan(&state).expect("Failed to scan").results;
+        assert_eq!(garbage.len(), 1);
+        state.strict = true;
+        assert!(matches!(scan(&state), Err(GarbageError::Strict(_))));
+        fs::remove_file(path.join("dangling")).expect("Failed to remove link");
+
+        let locked = path.join("locked");
+        fs::create_dir_all(&locked).expect("Failed to create test directory");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000))
+            .expect("Failed to lock test directory");
+        // root reads the directory regardless of its permissions
+        if fs::read_dir(&locked).is_err() {
+            state.strict = false;
+            let skipped = scan(&state).expect("Failed to scan").skipped;
+            assert_eq!(
+                skipped,
+                vec![(locked.clone(), io::ErrorKind::PermissionDenied)]
+            );
+            state.strict = true;
+            assert!(matches!(scan(&state), Err(GarbageError::Strict(_))));
+        }
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755))
+            .expect("Failed to unlock test directory");
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_explain_size_lists_largest_entry_first() {
+        let path = temp_dir().join("wsg_dev_explain_size");
+        let project = path.join("app");
+        let target = project.join("target");
+        fs::create_dir_all(target.join("debug").join("deps"))
+            .expect("Failed to create test project");
+        File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(target.join("app.pdb"), vec![0; 50_000]).expect("Failed to create test file");
+        fs::write(
+            target.join("debug").join("deps").join("lib.rlib"),
+            vec![0; 2_000],
+        )
+        .expect("Failed to create test file");
+        fs::write(target.join(".rustc_info.json"), [0; 10]).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let garbage = find_garbage_in_directory(&path, &state).expect("Failed to scan");
+        replace_garbage_result_vec_cache(&path, &garbage).expect("Failed to write cache");
+
+        let mut buffer = Vec::new();
+        arg_explain_size(&state, &path, 0, &mut buffer).expect("Failed to explain size");
+        let output = String::from_utf8(buffer).expect("Breakdown is not UTF-8");
+        let lines: Vec<&str> = output.lines().filter(|line| !line.is_empty()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                format!("[0] {}: 52.01 kB", project.display()).as_str(),
+                "  50.00 kB  target/app.pdb",
+                "   2.00 kB  target/debug",
+                "   10.00 B  target/.rustc_info.json",
+            ]
+        );
+        assert!(matches!(
+            arg_explain_size(&state, &path, 7, &mut Vec::new()),
+            Err(ApplicationError::IdNotExists(_))
+        ));
+
+        let _ = delete_garbage_result_vec_cache(&path);
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_project_is_scanned_listed_and_cleaned() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = temp_dir().join("wsg_dev_non_utf8");
+        let project = path.join(OsStr::from_bytes(b"pr\xffoject"));
+        fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+        File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(project.join("target").join("binary"), [0; 100])
+            .expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.yes = true;
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        for recognize_by in [RecognizeBy::Exists, RecognizeBy::Listing] {
+            state.recognize_by = recognize_by;
+            let garbage = find_garbage_in_directory(&path, &state).expect("Failed to scan");
+            assert_eq!(garbage.len(), 1);
+            assert_eq!(garbage[0].directory, project);
+        }
+
+        let garbage = find_garbage_in_directory(&path, &state).expect("Failed to scan");
+        replace_garbage_result_vec_cache(&path, &garbage).expect("Failed to write cache");
+        let cached = read_garbage_result_vec_cache(&path, None).expect("Failed to read cache");
+        assert_eq!(cached[0].deletable[0].path, project.join("target"));
+
+        let mut out = Vec::new();
+        render_garbage_results(
+            &cached,
+            &state,
+            &BuildContext::new(Size::new(80, 10)),
+            &mut out,
+        )
+        .expect("Failed to render listing");
+        let out = String::from_utf8(out).expect("Listing is not UTF-8");
+        assert!(out.contains(&format!("Project folder: {}", project.display())));
+
+        arg_clean(
+            &state,
+            &path,
+            &[GarbageIndex::All],
+            false,
+            &mut Cursor::new(""),
+        )
+        .expect("Failed to clean");
+        assert!(!project.join("target").exists());
+
+        let _ = delete_garbage_result_vec_cache(&path);
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_also_delete_adds_directory_to_every_recognizer() {
+        let path = temp_dir().join("wsg_dev_also_delete");
+        fs::create_dir_all(path.join("target")).expect("Failed to create test project");
+        fs::create_dir_all(path.join(".cache")).expect("Failed to create test project");
+        File::create(path.join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(path.join(".cache").join("index"), vec![0; 1_000])
+            .expect("Failed to create test file");
+
+        let deletables = |args: &[&str]| -> Vec<PathBuf> {
+            let mut state = AppState::new();
+            state.include_empty = true;
+            let args = Args::parse_from(args);
+            register_garbage_recognizer(&mut state, &args).expect("Failed to register recognizers");
+            find_garbage_in_directory(&path, &state)
+                .expect("Failed to scan")
+                .into_iter()
+                .flat_map(|result| result.deletable)
+                .map(|deletable| deletable.path.file_name().expect("No file name").into())
+                .collect()
+        };
+
+        assert_eq!(deletables(&["wsg"]), vec![PathBuf::from("target")]);
+        assert_eq!(
+            deletables(&["wsg", "--also-delete", ".cache,tmp"]),
+            vec![PathBuf::from("target"), PathBuf::from(".cache")]
+        );
+
+        for dangerous in ["src", ".git"] {
+            let mut state = AppState::new();
+            let names = format!(".cache,{}", dangerous);
+            let args = Args::parse_from(["wsg", "--also-delete", &names]);
+            assert!(matches!(
+                register_garbage_recognizer(&mut state, &args),
+                Err(ApplicationError::DangerousRecognizer(_, target)) if target == dangerous
+            ));
+        }
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+        let _ = delete_garbage_result_vec_cache(&path);
+    }
+
+    #[test]
+    fn test_check_fails_on_garbage_and_stops_early_with_fail_fast() {
+        let path = temp_dir().join("wsg_dev_check");
+        for project in ["alpha", "beta"] {
+            let project = path.join(project);
+            fs::create_dir_all(project.join("target")).expect("Failed to create test project");
+            File::create(project.join("Cargo.toml")).expect("Failed to create test file");
+            fs::write(project.join("target").join("app"), vec![0; 1_000])
+                .expect("Failed to create test file");
+        }
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+
+        let mut buffer = Vec::new();
+        assert!(matches!(
+            arg_check(&state, &path, false, &mut buffer),
+            Err(GarbageError::GarbageFound(2))
+        ));
+        let output = String::from_utf8(buffer).expect("Output is not UTF-8");
+        assert!(output.contains("Total: 2 projects"), "{}", output);
+
+        state.no_size = true;
+        let mut buffer = Vec::new();
+        assert!(matches!(
+            arg_check(&state, &path, true, &mut buffer),
+            Err(GarbageError::GarbageFound(1))
+        ));
+        let output = String::from_utf8(buffer).expect("Output is not UTF-8");
+        assert_eq!(output.lines().count(), 1, "{}", output);
+        assert!(output.contains("(not measured)"), "{}", output);
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+        let _ = delete_garbage_result_vec_cache(&path);
+    }
+
+    #[test]
+    fn test_keep_newest_applies_to_the_whole_listing() {
+        let now = SystemTime::now();
+        let garbage: Vec<_> = (0..3)
+            .map(|id| GarbageRecognizerResult {
+                modified: Some(now - Duration::from_secs(60 * (3 - id as u64))),
+                ..result_fixture(id, "Rust", format!("/projects/{}", id), 100, &[100])
+            })
+            .collect();
+        let mut state = AppState::new();
+        state.keep_newest = Some(1);
+        let selected = |ids: &[GarbageIndex]| -> Result<Vec<String>, ApplicationError> {
+            select_garbage(&state, Path::new("/projects"), &garbage, ids).map(|selected| {
+                selected
+                    .iter()
+                    .map(|result| result.index.to_string())
+                    .collect()
+            })
+        };
+
+        assert_eq!(selected(&[GarbageIndex::All]).unwrap(), vec!["0", "1"]);
+        assert_eq!(selected(&[GarbageIndex::Id(0)]).unwrap(), vec!["0"]);
+        assert!(matches!(
+            selected(&[GarbageIndex::Id(2)]),
+            Err(ApplicationError::NothingToClean(_))
+        ));
+    }
+
+    #[test]
+    fn test_enforce_under_keeps_what_the_clean_keeps() {
+        let path = temp_dir().join("wsg_dev_enforce_under");
+        let target = path.join("project").join("target");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(path.join("project").join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        // the only project is the newest one
+        state.keep_newest = Some(1);
+        arg_enforce_under(&state, &path, 1_000, || Ok(0)).expect("Failed to enforce");
+        assert!(target.exists());
+
+        state.keep_newest = None;
+        arg_enforce_under(&state, &path, 1_000, || Ok(0)).expect("Failed to enforce");
+        assert!(!target.exists());
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+        let _ = delete_garbage_result_vec_cache(&path);
+    }
+
+    #[test]
+    fn test_confirm_over_measures_unmeasured_results() {
+        let path = temp_dir().join("wsg_dev_confirm_over_unmeasured");
+        let target = path.join("project").join("target");
+        fs::create_dir_all(&target).expect("Failed to create test project");
+        File::create(path.join("project").join("Cargo.toml")).expect("Failed to create test file");
+        fs::write(target.join("binary"), [0; 1_000]).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        state.no_size = true;
+        state.confirm_over = Some(500);
+        let garbage = find_garbage_in_directory(&path, &state).expect("Failed to scan");
+        assert!(garbage[0].size_unmeasured);
+        replace_garbage_result_vec_cache(&path, &garbage).expect("Failed to write cache");
+
+        // a plain yes is not enough once the result is measured over the threshold
+        arg_clean(
+            &state,
+            &path,
+            &[GarbageIndex::All],
+            false,
+            &mut Cursor::new("y\n"),
+        )
+        .expect("Failed to clean");
+        assert!(target.exists());
+
+        arg_clean(
+            &state,
+            &path,
+            &[GarbageIndex::All],
+            false,
+            &mut Cursor::new("y\n0.00\n"),
+        )
+        .expect("Failed to clean");
+        assert!(!target.exists());
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+        let _ = delete_garbage_result_vec_cache(&path);
+    }
+
+    #[test]
+    fn test_clean_history_is_opt_in() {
+        let path = temp_dir().join("wsg_dev_history_opt_in");
+        let history = path.join("state").join("clean-history.ndjson");
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(GarbageRecognizer::new(
+            "Rust",
+            Some(vec![FileType::File("Cargo.toml".into())]),
+            Some(vec![FileType::Directory("target".into())]),
+        ));
+        let clean_project = |state: &AppState| {
+            let target = path.join("project").join("target");
+            fs::create_dir_all(&target).expect("Failed to create test project");
+            File::create(path.join("project").join("Cargo.toml"))
+                .expect("Failed to create test file");
+            fs::write(target.join("binary"), [0; 100]).expect("Failed to create test file");
+            let garbage = find_garbage_in_directory(&path, state).expect("Failed to scan");
+            replace_garbage_result_vec_cache(&path, &garbage).expect("Failed to write cache");
+            arg_clean(
+                state,
+                &path,
+                &[GarbageIndex::All],
+                false,
+                &mut Cursor::new("y\n"),
+            )
+            .expect("Failed to clean");
+            assert!(!target.exists());
+        };
+
+        clean_project(&state);
+        assert!(!history.exists());
+
+        state.history_file = Some(history.clone());
+        clean_project(&state);
+        let entries = read_history(&history).expect("Failed to read history");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].projects, 1);
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+        let _ = delete_garbage_result_vec_cache(&path);
+    }
+
+    #[test]
+    fn test_emit_script_requires_a_clean() {
+        assert!(Args::try_parse_from(["wsg", "--emit-script", "sh"]).is_err());
+        assert!(Args::try_parse_from([
+            "wsg",
+            "--clean",
+            "all",
+            "--emit-script",
+            "sh",
+            "--enforce-under",
+            "1GB",
+            "--yes",
+        ])
+        .is_err());
+        assert!(Args::try_parse_from(["wsg", "--clean", "all", "--emit-script", "sh"]).is_ok());
+        assert!(Args::try_parse_from(["wsg", "--prune", "--emit-script", "sh"]).is_ok());
+    }
+
+    #[test]
+    fn test_default_config_is_loaded_when_created() {
+        let path = temp_dir().join("wsg_dev_default_config");
+        fs::create_dir_all(&path).expect("Failed to create test directory");
+        let default_config = path.join("recognizers.toml");
+        let imported = path.join("team.toml");
+        let args = Args::parse_from(["wsg"]);
+
+        assert_eq!(
+            recognizer_config_path(&args, Some(default_config.clone())),
+            None
+        );
+        File::create(&default_config).expect("Failed to create test file");
+        assert_eq!(
+            recognizer_config_path(&args, Some(default_config.clone())),
+            Some(default_config.clone())
+        );
+
+        let args = Args::parse_from(["wsg", "--import-recognizers", imported.to_str().unwrap()]);
+        assert_eq!(
+            recognizer_config_path(&args, Some(default_config)),
+            Some(imported)
+        );
+
+        fs::remove_dir_all(&path).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_roots_from_only_lists() {
+        assert!(Args::try_parse_from(["wsg", "--roots-from", "roots.txt"]).is_ok());
+        assert!(
+            Args::try_parse_from(["wsg", "--roots-from", "roots.txt", "--clean", "all"]).is_err()
+        );
+        assert!(Args::try_parse_from(["wsg", "--roots-from", "roots.txt", "--prune"]).is_err());
+    }
+
+    #[test]
+    fn test_allowed_commands_are_rebuilt_on_reload() {
+        let mut state = AppState::new();
+        state.allowed_commands.insert("flutter".to_string());
+
+        let args = Args::parse_from(["wsg", "--allow-command", "cargo"]);
+        register_garbage_recognizer(&mut state, &args).expect("Failed to register recognizers");
+        assert_eq!(Vec::from_iter(&state.allowed_commands), vec!["cargo"]);
+
+        let args = Args::parse_from(["wsg"]);
+        register_garbage_recognizer(&mut state, &args).expect("Failed to register recognizers");
+        assert!(state.allowed_commands.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failed_size_command_is_warned() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = temp_dir().join("wsg_dev_failed_size_command");
+        fs::create_dir_all(temp_dir.join("project")).expect("Failed to create test project");
+        File::create(temp_dir.join("project").join("build.marker"))
+            .expect("Failed to create test file");
+        let script = temp_dir.join("failing-size");
+        fs::write(&script, "#!/bin/sh\nexit 1\n").expect("Can't write test script");
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))
+            .expect("Failed to make test script executable");
+
+        let mut state = AppState::new();
+        state.register_garbage_recognizer(
+            GarbageRecognizer::new(
+                "Fake",
+                Some(vec![FileType::File("build.marker".into())]),
+                None,
+            )
+            .with_command(RecognizerCommand::new(
+                script.to_string_lossy(),
+                vec![],
+                vec![],
+            )),
+        );
+
+        let scan = scan_garbage(&state, &temp_dir).expect("Failed to scan");
+        assert!(scan.results.is_empty());
+        state.strict = true;
+        assert!(matches!(
+            scan_garbage(&state, &temp_dir),
+            Err(GarbageError::Strict(_))
+        ));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
     }
 }
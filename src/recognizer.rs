@@ -1,4 +1,11 @@
-use crate::garbage::{FileType, GarbageRecognizer};
+use crate::error::GarbageError;
+use crate::garbage::{FileType, GarbageRecognizer, SizeStrategy};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
 
 pub fn available_recognizer() -> Vec<GarbageRecognizer> {
     vec![
@@ -6,21 +13,863 @@ pub fn available_recognizer() -> Vec<GarbageRecognizer> {
             "Flutter",
             Some(vec![FileType::File("pubspec.yaml".into())]),
             Some(vec![FileType::Directory("build".into())]),
-        ),
+        )
+        .with_requirement("build", vec![FileType::File("pubspec.lock".into())])
+        .with_aliases(&["dart"]),
         GarbageRecognizer::new(
             "NodeJS",
             Some(vec![FileType::File("package.json".into())]),
             Some(vec![FileType::Directory("node_modules".into())]),
-        ),
+        )
+        .with_aliases(&["node", "npm", "js"])
+        .with_size_strategy(SizeStrategy::Blocks),
         GarbageRecognizer::new(
             "Composer",
             Some(vec![FileType::File("composer.json".into())]),
             Some(vec![FileType::Directory("vendor".into())]),
-        ),
+        )
+        .with_aliases(&["php"]),
         GarbageRecognizer::new(
             "Rust",
             Some(vec![FileType::File("Cargo.toml".into())]),
             Some(vec![FileType::Directory("target".into())]),
+        )
+        .with_aliases(&["rs", "cargo"]),
+        GarbageRecognizer::new(
+            "CMake",
+            Some(vec![FileType::File("CMakeLists.txt".into())]),
+            Some(vec![
+                FileType::Directory("build".into()),
+                FileType::Glob("cmake-build-*".into()),
+                FileType::Directory("CMakeFiles".into()),
+            ]),
+        ),
+        GarbageRecognizer::new(
+            "Docker",
+            Some(vec![
+                FileType::File("Dockerfile".into()),
+                FileType::File("docker-compose.yml".into()),
+            ]),
+            Some(vec![FileType::Directory(".docker-cache".into())]),
+        ),
+        GarbageRecognizer::new(
+            "Elixir",
+            Some(vec![FileType::File("mix.exs".into())]),
+            Some(vec![
+                FileType::Directory("_build".into()),
+                FileType::Directory("deps".into()),
+            ]),
+        ),
+        GarbageRecognizer::new(
+            "Haskell",
+            Some(vec![
+                FileType::Glob("*.cabal".into()),
+                FileType::File("stack.yaml".into()),
+                FileType::File("package.yaml".into()),
+            ]),
+            Some(vec![
+                FileType::Directory("dist-newstyle".into()),
+                FileType::Directory(".stack-work".into()),
+                FileType::Directory("dist".into()),
+            ]),
+        ),
+        GarbageRecognizer::new(
+            "SwiftPM",
+            Some(vec![FileType::File("Package.swift".into())]),
+            Some(vec![
+                FileType::Directory(".build".into()),
+                FileType::Directory(".swiftpm".into()),
+            ]),
+        ),
+        GarbageRecognizer::new(
+            "Bazel",
+            Some(vec![
+                FileType::File("WORKSPACE".into()),
+                FileType::File("WORKSPACE.bazel".into()),
+                FileType::File("MODULE.bazel".into()),
+            ]),
+            // the convenience symlinks into the output base, which is shared below ~/.cache/bazel
+            Some(vec![FileType::Symlink("bazel-*".into())]),
+        ),
+        GarbageRecognizer::new(
+            "Terraform",
+            Some(vec![
+                FileType::Glob("*.tf".into()),
+                FileType::File("terragrunt.hcl".into()),
+            ]),
+            Some(vec![
+                FileType::Directory(".terraform".into()),
+                FileType::Directory(".terragrunt-cache".into()),
+            ]),
         ),
     ]
 }
+
+/// Space Docker reports as reclaimable for one resource type, like images or the build
+/// cache. Docker only knows these totals machine wide, not per project.
+#[derive(Debug, PartialEq)]
+pub struct DockerReclaimable {
+    pub resource: String,
+    pub reclaimable: String,
+}
+
+/// Asks `docker system df` for the reclaimable space. Only a fixed argument list is run,
+/// without a shell and without access to stdin.
+pub fn docker_reclaimable_space() -> Result<Vec<DockerReclaimable>, GarbageError> {
+    let output = Command::new("docker")
+        .args(["system", "df", "--format", "{{.Type}}\t{{.Reclaimable}}"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(GarbageError::CommandFailed("docker".to_string()));
+    }
+    Ok(parse_docker_system_df(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_docker_system_df(output: &str) -> Vec<DockerReclaimable> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(resource, reclaimable)| DockerReclaimable {
+            resource: resource.trim().to_string(),
+            reclaimable: reclaimable.trim().to_string(),
+        })
+        .collect()
+}
+
+/// Asks for a new recognizer on `out`, reading the answers line by line from `input`.
+/// Entries containing `*` or `?` become globs.
+pub fn prompt_recognizer(
+    input: &mut impl BufRead,
+    out: &mut impl Write,
+) -> Result<GarbageRecognizer, GarbageError> {
+    let mut ask = |question: &str| -> Result<String, GarbageError> {
+        write!(out, "{}: ", question)?;
+        out.flush()?;
+        let mut answer = String::new();
+        input.read_line(&mut answer)?;
+        Ok(answer.trim().to_string())
+    };
+    let list = |answer: String, file_type: fn(String) -> FileType| -> Vec<FileType> {
+        answer
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.contains(['*', '?']) {
+                true => FileType::Glob(entry.to_string()),
+                false => file_type(entry.to_string()),
+            })
+            .collect()
+    };
+
+    let name = ask("Name")?;
+    if name.is_empty() {
+        return Err(GarbageError::RecognizerFileError(
+            "A recognizer needs a name".to_string(),
+        ));
+    }
+    let mut recognize = list(ask("Marker files, comma separated")?, FileType::File);
+    recognize.extend(list(
+        ask("Marker directories, comma separated")?,
+        FileType::Directory,
+    ));
+    let delete = list(
+        ask("Deletable directories, comma separated")?,
+        FileType::Directory,
+    );
+    // without markers the recognizer matches wherever one of the deletables is
+    if delete.is_empty() {
+        return Err(GarbageError::RecognizerFileError(
+            "A recognizer needs at least one deletable".to_string(),
+        ));
+    }
+    let size_strategy = match ask("Size strategy, walk, apparent or blocks [walk]")?.as_str() {
+        "" | "walk" => SizeStrategy::Walk,
+        "apparent" => SizeStrategy::Apparent,
+        "blocks" => SizeStrategy::Blocks,
+        other => {
+            return Err(GarbageError::RecognizerFileError(format!(
+                "Unknown size strategy {:?}",
+                other
+            )))
+        }
+    };
+
+    Ok(GarbageRecognizer::new(name, Some(recognize), Some(delete))
+        .with_size_strategy(size_strategy))
+}
+
+/// Adds the recognizer to the file, creating it if needed. TOML files are appended to so
+/// their comments survive, JSON files are rewritten.
+pub fn append_recognizer(path: &Path, recognizer: &GarbageRecognizer) -> Result<(), GarbageError> {
+    let (existing, safe_mode) = match path.exists() {
+        true => {
+            let file = read_recognizer_file(path)?;
+            (file.recognizer, file.safe_mode)
+        }
+        false => (Vec::new(), false),
+    };
+    if existing
+        .iter()
+        .any(|r| r.name.eq_ignore_ascii_case(&recognizer.name))
+    {
+        return Err(GarbageError::RecognizerFileError(format!(
+            "{} already contains a recognizer named {}",
+            path.display(),
+            recognizer.name
+        )));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if is_toml(path) {
+        let separator = if existing.is_empty() { "" } else { "\n" };
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        write!(
+            file,
+            "{}{}",
+            separator,
+            serialize_recognizers(path, [recognizer], false)?
+        )?;
+        Ok(())
+    } else {
+        let content = serialize_recognizers(path, existing.iter().chain([recognizer]), safe_mode)?;
+        fs::write(path, content)?;
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RecognizerFile {
+    /// Turns `--clean` and `--prune` into dry runs, like `WSG_SAFE=1`.
+    #[serde(default)]
+    safe_mode: bool,
+    #[serde(default)]
+    recognizer: Vec<GarbageRecognizer>,
+}
+
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+}
+
+/// Writes the recognizers as TOML when the file ends with `.toml`, as JSON otherwise.
+pub fn export_recognizers<'a>(
+    path: &Path,
+    recognizers: impl IntoIterator<Item = &'a GarbageRecognizer>,
+) -> Result<(), GarbageError> {
+    fs::write(path, serialize_recognizers(path, recognizers, false)?)?;
+    Ok(())
+}
+
+fn serialize_recognizers<'a>(
+    path: &Path,
+    recognizers: impl IntoIterator<Item = &'a GarbageRecognizer>,
+    safe_mode: bool,
+) -> Result<String, GarbageError> {
+    let mut recognizers: Vec<&GarbageRecognizer> = recognizers.into_iter().collect();
+    recognizers.sort_by(|a, b| a.name.cmp(&b.name));
+
+    #[derive(Serialize)]
+    struct RecognizerFileRef<'a> {
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        safe_mode: bool,
+        recognizer: Vec<&'a GarbageRecognizer>,
+    }
+    let file = RecognizerFileRef {
+        safe_mode,
+        recognizer: recognizers,
+    };
+
+    if is_toml(path) {
+        toml::to_string_pretty(&file)
+            .map_err(|error| GarbageError::RecognizerFileError(error.to_string()))
+    } else {
+        Ok(serde_json::to_string_pretty(&file)?)
+    }
+}
+
+const STARTER_CONFIG_HEADER: &str = "\
+# wsg recognizer configuration
+#
+# Every [[recognizer]] describes a kind of project: `recognize` lists the marker files
+# or directories that identify it, `delete` the garbage that can be removed. Without
+# `recognize`, any directory containing one of the `delete` entries matches. Load this
+# file with `wsg --import-recognizers <file>`, recognizers with the name of a built-in
+# one replace it.
+#
+# Entries are `file`, `directory`, `glob` (with `*` and `?`) or `symlink`, a glob that
+# only matches symbolic links.
+#
+# Optional keys: `size_strategy` (\"walk\", \"apparent\" or \"blocks\"), `priority`,
+# `cache_ttl` (e.g. \"1h\"), `delete_max_depth` to find deletables in subdirectories
+# of the project, `requires` for entries a deletable needs inside or next to it and
+# `command` to let the build tool clean up itself. A command has a `program`, the
+# `size_args` and `clean_args` to run it with and a `size_pattern` like \"{} total\"
+# locating the size in its output, which otherwise has to start with the bytes.
+#
+# Uncomment `safe_mode = true` to turn --clean and --prune into dry runs unless
+# --really-clean is given, like WSG_SAFE=1 does. It has to stay above the recognizers.
+#
+# safe_mode = true
+#
+# The built-in recognizers are listed below as examples.
+
+";
+
+/// `$XDG_CONFIG_HOME/wsg/recognizers.toml`, falling back to `~/.config/wsg/recognizers.toml`.
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("wsg").join("recognizers.toml"))
+}
+
+/// Writes a commented starter config containing the built-in recognizers.
+pub fn init_config(path: &Path, overwrite: bool) -> Result<(), GarbageError> {
+    if path.exists() && !overwrite {
+        return Err(GarbageError::RecognizerFileError(format!(
+            "{} already exists, use --force to overwrite it",
+            path.display()
+        )));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let recognizers = available_recognizer();
+    let content = serialize_recognizers(Path::new("recognizers.toml"), &recognizers, false)?;
+    fs::write(path, format!("{}{}", STARTER_CONFIG_HEADER, content))?;
+    Ok(())
+}
+
+pub fn import_recognizers(path: &Path) -> Result<Vec<GarbageRecognizer>, GarbageError> {
+    Ok(read_recognizer_file(path)?.recognizer)
+}
+
+/// Whether the config at `path` asks for safe mode, a missing config doesn't.
+pub fn config_safe_mode(path: &Path) -> Result<bool, GarbageError> {
+    match path.exists() {
+        true => Ok(read_recognizer_file(path)?.safe_mode),
+        false => Ok(false),
+    }
+}
+
+fn read_recognizer_file(path: &Path) -> Result<RecognizerFile, GarbageError> {
+    let content = fs::read_to_string(path)?;
+
+    if is_toml(path) {
+        toml::from_str(&content)
+            .map_err(|error| GarbageError::RecognizerFileError(error.to_string()))
+    } else {
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+/// Detects modifications of a recognizer file between two scans by its mtime.
+pub struct RecognizerFileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+}
+
+impl RecognizerFileWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: Self::modified_of(path),
+        }
+    }
+
+    /// Returns true once for every change since the last call.
+    pub fn has_changed(&mut self) -> bool {
+        let modified = Self::modified_of(&self.path);
+        let changed = modified != self.modified;
+        self.modified = modified;
+        changed
+    }
+
+    fn modified_of(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::garbage::{
+        clean_garbage_from_vec, find_garbage_in_directory, DeletableKind, DeleteOrder, FileType,
+        GarbageRecognizer, SizeStrategy,
+    };
+    use crate::recognizer::{
+        append_recognizer, available_recognizer, config_safe_mode, export_recognizers,
+        import_recognizers, init_config, parse_docker_system_df, prompt_recognizer,
+        DockerReclaimable,
+    };
+    use crate::AppState;
+    use std::collections::HashSet;
+    use std::env::temp_dir;
+    use std::fs;
+    use std::fs::File;
+
+    #[test]
+    fn test_export_import_recognizers() {
+        let temp_dir = temp_dir().join("wsg_dev_export_recognizers");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+
+        let builtin: HashSet<GarbageRecognizer> = available_recognizer().into_iter().collect();
+
+        for file_name in ["recognizers.toml", "recognizers.json"] {
+            let file = temp_dir.join(file_name);
+            export_recognizers(&file, &builtin).expect("Failed to export recognizers");
+
+            let imported: HashSet<GarbageRecognizer> = import_recognizers(&file)
+                .expect("Failed to import recognizers")
+                .into_iter()
+                .collect();
+            assert_eq!(imported, builtin);
+        }
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_haskell_recognizer_sums_cabal_and_stack_builds() {
+        let temp_dir = temp_dir().join("wsg_dev_haskell_project");
+        let project = temp_dir.join("parser");
+        for (deletable, size) in [("dist-newstyle", 300), (".stack-work", 200)] {
+            fs::create_dir_all(project.join(deletable)).expect("Failed to create test project");
+            fs::write(project.join(deletable).join("artifact"), vec![0; size])
+                .expect("Failed to create test file");
+        }
+        fs::create_dir_all(project.join("src")).expect("Failed to create test project");
+        File::create(project.join("parser.cabal")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .filter(|recognizer| recognizer.name == "Haskell")
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].directory, project);
+        let deletable: Vec<_> = garbage[0].deletable.iter().map(|d| &d.path).collect();
+        assert_eq!(
+            deletable,
+            vec![&project.join("dist-newstyle"), &project.join(".stack-work")]
+        );
+        assert_eq!(garbage[0].size, 500);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bazel_recognizer_deletes_links_not_the_shared_cache() {
+        use std::os::unix::fs::symlink;
+
+        let temp_dir = temp_dir().join("wsg_dev_bazel_workspace");
+        let workspace = temp_dir.join("monorepo");
+        let output_base = temp_dir.join("cache").join("execroot");
+        for output in ["bin", "out"] {
+            fs::create_dir_all(output_base.join(output)).expect("Failed to create test cache");
+            fs::write(output_base.join(output).join("artifact"), vec![0; 10_000])
+                .expect("Failed to create test file");
+        }
+        fs::create_dir_all(workspace.join("src")).expect("Failed to create test project");
+        File::create(workspace.join("MODULE.bazel")).expect("Failed to create test file");
+        symlink(output_base.join("bin"), workspace.join("bazel-bin"))
+            .expect("Failed to create test link");
+        symlink(output_base.join("out"), workspace.join("bazel-out"))
+            .expect("Failed to create test link");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .filter(|recognizer| recognizer.name == "Bazel")
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let garbage = find_garbage_in_directory(&workspace, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        let deletable: Vec<_> = garbage[0]
+            .deletable
+            .iter()
+            .map(|d| (&d.path, d.kind))
+            .collect();
+        assert_eq!(
+            deletable,
+            vec![
+                (&workspace.join("bazel-bin"), Some(DeletableKind::Symlink)),
+                (&workspace.join("bazel-out"), Some(DeletableKind::Symlink)),
+            ]
+        );
+        // the links themselves, nothing of the shared cache they point to
+        assert!(garbage[0].size < 10_000);
+
+        clean_garbage_from_vec(garbage, DeleteOrder::Discovery, None).expect("Failed to clean");
+        assert!(fs::symlink_metadata(workspace.join("bazel-bin")).is_err());
+        assert!(output_base.join("bin").join("artifact").exists());
+        assert!(output_base.join("out").join("artifact").exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bazel_recognizer_keeps_source_directories_named_like_links() {
+        let temp_dir = temp_dir().join("wsg_dev_bazel_sources");
+        let workspace = temp_dir.join("monorepo");
+        let skylib = workspace.join("bazel-skylib");
+        fs::create_dir_all(&skylib).expect("Failed to create test project");
+        fs::write(skylib.join("BUILD"), vec![0; 1_000]).expect("Failed to create test file");
+        File::create(workspace.join("MODULE.bazel")).expect("Failed to create test file");
+        let output_base = temp_dir.join("cache");
+        fs::create_dir_all(&output_base).expect("Failed to create test cache");
+        std::os::unix::fs::symlink(&output_base, workspace.join("bazel-out"))
+            .expect("Failed to create test link");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .filter(|recognizer| recognizer.name == "Bazel")
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let garbage = find_garbage_in_directory(&workspace, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        let deletable: Vec<_> = garbage[0].deletable.iter().map(|d| &d.path).collect();
+        assert_eq!(deletable, vec![&workspace.join("bazel-out")]);
+
+        clean_garbage_from_vec(garbage, DeleteOrder::Discovery, None).expect("Failed to clean");
+        assert!(skylib.join("BUILD").exists());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_swiftpm_recognizer_lists_package_once() {
+        let temp_dir = temp_dir().join("wsg_dev_swiftpm_project");
+        let package = temp_dir.join("server");
+        let app = temp_dir.join("app");
+        for project in [&package, &app] {
+            fs::create_dir_all(project.join(".build")).expect("Failed to create test project");
+            fs::write(project.join(".build").join("artifact"), vec![0; 100])
+                .expect("Failed to create test file");
+            File::create(project.join("Package.swift")).expect("Failed to create test file");
+        }
+        fs::create_dir_all(app.join("App.xcodeproj")).expect("Failed to create test project");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let mut garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        garbage.sort_by(|a, b| a.directory.cmp(&b.directory));
+        assert_eq!(garbage.len(), 2);
+        assert_eq!(
+            garbage.iter().map(|g| &g.directory).collect::<Vec<_>>(),
+            vec![&app, &package]
+        );
+        for (result, project) in garbage.iter().zip([&app, &package]) {
+            assert_eq!(result.recognizer_name, "SwiftPM");
+            let deletable: Vec<_> = result.deletable.iter().map(|d| &d.path).collect();
+            assert_eq!(deletable, vec![&project.join(".build")]);
+        }
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_flutter_build_requires_pubspec_lock() {
+        let temp_dir = temp_dir().join("wsg_dev_flutter_requires_lock");
+        let locked = temp_dir.join("locked");
+        let unlocked = temp_dir.join("unlocked");
+        for project in [&locked, &unlocked] {
+            fs::create_dir_all(project.join("build")).expect("Failed to create test project");
+            fs::write(project.join("build").join("artifact"), vec![0; 100])
+                .expect("Failed to create test file");
+            File::create(project.join("pubspec.yaml")).expect("Failed to create test file");
+        }
+        File::create(locked.join("pubspec.lock")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].recognizer_name, "Flutter");
+        assert_eq!(garbage[0].directory, locked);
+        assert_eq!(garbage[0].deletable[0].path, locked.join("build"));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_init_config() {
+        let temp_dir = temp_dir().join("wsg_dev_init_config");
+        let file = temp_dir.join("wsg").join("recognizers.toml");
+
+        init_config(&file, false).expect("Failed to write starter config");
+        let builtin: HashSet<GarbageRecognizer> = available_recognizer().into_iter().collect();
+        let imported: HashSet<GarbageRecognizer> = import_recognizers(&file)
+            .expect("Failed to import starter config")
+            .into_iter()
+            .collect();
+        assert_eq!(imported, builtin);
+
+        assert!(init_config(&file, false).is_err());
+        init_config(&file, true).expect("Failed to overwrite starter config");
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_terraform_recognizer_finds_nested_modules() {
+        let temp_dir = temp_dir().join("wsg_dev_terraform");
+        for module in ["network", "database"] {
+            let module = temp_dir.join("modules").join(module);
+            fs::create_dir_all(module.join(".terraform").join("providers"))
+                .expect("Failed to create test module");
+            File::create(module.join("main.tf")).expect("Failed to create test file");
+            File::create(module.join(".terraform").join("providers").join("plugin"))
+                .expect("Failed to create test file")
+                .set_len(1_000)
+                .expect("Failed to resize test file");
+        }
+        File::create(temp_dir.join("modules").join("README.md"))
+            .expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .filter(|recognizer| recognizer.name == "Terraform")
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 2);
+        assert_eq!(garbage.iter().map(|result| result.size).sum::<u64>(), 2_000);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_cmake_recognizer_matches_build_directories_by_glob() {
+        let temp_dir = temp_dir().join("wsg_dev_cmake");
+        for build in ["cmake-build-debug", "cmake-build-release"] {
+            fs::create_dir_all(temp_dir.join(build)).expect("Failed to create test project");
+            File::create(temp_dir.join(build).join("app.o"))
+                .expect("Failed to create test file")
+                .set_len(500)
+                .expect("Failed to resize test file");
+        }
+        fs::create_dir_all(temp_dir.join("src")).expect("Failed to create test project");
+        File::create(temp_dir.join("CMakeLists.txt")).expect("Failed to create test file");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .filter(|recognizer| recognizer.name == "CMake")
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        let deletable: Vec<_> = garbage[0].deletable.iter().map(|d| &d.path).collect();
+        assert_eq!(
+            deletable,
+            vec![
+                &temp_dir.join("cmake-build-debug"),
+                &temp_dir.join("cmake-build-release")
+            ]
+        );
+        assert_eq!(garbage[0].size, 1_000);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_docker_recognizer_finds_local_build_cache() {
+        let temp_dir = temp_dir().join("wsg_dev_docker");
+        for (project, marker) in [("api", "Dockerfile"), ("stack", "docker-compose.yml")] {
+            let cache = temp_dir.join(project).join(".docker-cache");
+            fs::create_dir_all(&cache).expect("Failed to create test project");
+            File::create(temp_dir.join(project).join(marker)).expect("Failed to create test file");
+            File::create(cache.join("layer"))
+                .expect("Failed to create test file")
+                .set_len(300)
+                .expect("Failed to resize test file");
+        }
+        fs::create_dir_all(temp_dir.join("plain").join(".docker-cache"))
+            .expect("Failed to create test project");
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .filter(|recognizer| recognizer.name == "Docker")
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        let directories: Vec<_> = garbage.iter().map(|result| &result.directory).collect();
+        assert_eq!(
+            directories,
+            vec![&temp_dir.join("api"), &temp_dir.join("stack")]
+        );
+        assert_eq!(garbage.iter().map(|result| result.size).sum::<u64>(), 600);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_parse_docker_system_df() {
+        let output = "Images\t1.2GB (48%)\nBuild Cache\t530MB\n\nmalformed\n";
+        assert_eq!(
+            parse_docker_system_df(output),
+            vec![
+                DockerReclaimable {
+                    resource: "Images".to_string(),
+                    reclaimable: "1.2GB (48%)".to_string(),
+                },
+                DockerReclaimable {
+                    resource: "Build Cache".to_string(),
+                    reclaimable: "530MB".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prompt_and_append_recognizer() {
+        let mut input = std::io::Cursor::new(
+            "Gradle\nbuild.gradle, settings.gradle.kts\n\nbuild, .gradle, *.hprof\nblocks\n",
+        );
+        let mut out = Vec::new();
+        let recognizer = prompt_recognizer(&mut input, &mut out).expect("Failed to prompt");
+
+        assert_eq!(recognizer.name, "Gradle");
+        assert_eq!(
+            recognizer.recognize,
+            vec![
+                FileType::File("build.gradle".into()),
+                FileType::File("settings.gradle.kts".into()),
+            ]
+        );
+        assert_eq!(
+            recognizer.delete,
+            vec![
+                FileType::Directory("build".into()),
+                FileType::Directory(".gradle".into()),
+                FileType::Glob("*.hprof".into()),
+            ]
+        );
+        assert_eq!(recognizer.size_strategy, SizeStrategy::Blocks);
+        assert!(String::from_utf8(out)
+            .expect("Prompts are not UTF-8")
+            .starts_with("Name: "));
+
+        let temp_dir = temp_dir().join("wsg_dev_new_recognizer");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+        let file = temp_dir.join("recognizers.toml");
+        init_config(&file, false).expect("Failed to write starter config");
+        append_recognizer(&file, &recognizer).expect("Failed to append recognizer");
+
+        let content = fs::read_to_string(&file).expect("Failed to read config");
+        assert!(content.starts_with("# wsg recognizer configuration"));
+        let imported = import_recognizers(&file).expect("Failed to import config");
+        assert_eq!(imported.last(), Some(&recognizer));
+        assert!(append_recognizer(&file, &recognizer).is_err());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_elixir_recognizer_finds_umbrella_apps() {
+        let temp_dir = temp_dir().join("wsg_dev_elixir_umbrella");
+        let apps = ["accounts", "web"].map(|app| temp_dir.join("apps").join(app));
+        for project in [&temp_dir, &apps[0], &apps[1]] {
+            for deletable in ["_build", "deps"] {
+                fs::create_dir_all(project.join(deletable)).expect("Failed to create test project");
+                fs::write(project.join(deletable).join("artifact"), [0; 100])
+                    .expect("Failed to create test file");
+            }
+            File::create(project.join("mix.exs")).expect("Failed to create test file");
+        }
+
+        let mut state = AppState::new();
+        available_recognizer()
+            .into_iter()
+            .filter(|recognizer| recognizer.name == "Elixir")
+            .for_each(|recognizer| state.register_garbage_recognizer(recognizer));
+
+        let mut garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        garbage.sort_by(|a, b| a.directory.cmp(&b.directory));
+        let projects: Vec<_> = garbage.iter().map(|result| &result.directory).collect();
+        assert_eq!(projects, vec![&temp_dir, &apps[0], &apps[1]]);
+        for result in &garbage {
+            let deletable: Vec<_> = result.deletable.iter().map(|d| &d.path).collect();
+            assert_eq!(
+                deletable,
+                vec![
+                    &result.directory.join("_build"),
+                    &result.directory.join("deps")
+                ]
+            );
+        }
+
+        state.root_markers_only = true;
+        let garbage = find_garbage_in_directory(&temp_dir, &state).expect("Failed to scan");
+        assert_eq!(garbage.len(), 1);
+        assert_eq!(garbage[0].directory, temp_dir);
+        assert_eq!(garbage[0].size, 200);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_config_safe_mode() {
+        let temp_dir = temp_dir().join("wsg_dev_config_safe_mode");
+        fs::create_dir_all(&temp_dir).expect("Failed to create test directory");
+        let recognizer = GarbageRecognizer::new(
+            "Zig",
+            Some(vec![FileType::File("build.zig".into())]),
+            Some(vec![FileType::Directory("zig-out".into())]),
+        );
+
+        assert!(!config_safe_mode(&temp_dir.join("missing.toml")).unwrap());
+
+        let toml = temp_dir.join("recognizers.toml");
+        init_config(&toml, false).expect("Failed to write config");
+        assert!(!config_safe_mode(&toml).unwrap());
+        let content = fs::read_to_string(&toml).expect("Failed to read config");
+        fs::write(
+            &toml,
+            content.replace("# safe_mode = true", "safe_mode = true"),
+        )
+        .expect("Failed to write config");
+        assert!(config_safe_mode(&toml).unwrap());
+        assert_eq!(
+            import_recognizers(&toml).unwrap().len(),
+            available_recognizer().len()
+        );
+
+        // rewriting a JSON config keeps the setting
+        let json = temp_dir.join("recognizers.json");
+        fs::write(&json, r#"{"safe_mode": true, "recognizer": []}"#)
+            .expect("Failed to write config");
+        append_recognizer(&json, &recognizer).expect("Failed to append recognizer");
+        assert!(config_safe_mode(&json).unwrap());
+        assert_eq!(import_recognizers(&json).unwrap(), vec![recognizer]);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+}
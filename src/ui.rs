@@ -1,20 +1,98 @@
 #![allow(unused)]
+use std::io::{self, Write};
 use std::str::Chars;
 
+/// Characters of code page 437 above the ASCII range, in byte order 0x80..=0xFF.
+const CP437_UPPER_HALF: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+#[derive(clap::ValueEnum, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum OutputEncoding {
+    #[default]
+    Utf8,
+    Cp437,
+    Ascii,
+}
+
+impl OutputEncoding {
+    /// Replaces every character the console can't represent, transliterating where a
+    /// close equivalent exists and falling back to '?' otherwise.
+    pub fn sanitize(&self, text: &str) -> String {
+        text.chars().map(|ch| self.sanitize_char(ch)).collect()
+    }
+
+    pub fn sanitize_char(&self, ch: char) -> char {
+        match self {
+            OutputEncoding::Utf8 => ch,
+            _ if ch.is_ascii() => ch,
+            OutputEncoding::Cp437 if CP437_UPPER_HALF.contains(ch) => ch,
+            OutputEncoding::Cp437 if ch == '•' => '∙',
+            _ => transliterate_to_ascii(ch),
+        }
+    }
+}
+
+fn transliterate_to_ascii(ch: char) -> char {
+    const TABLE: [(&str, char); 16] = [
+        ("àáâãäåā", 'a'), ("ÀÁÂÃÄÅĀ", 'A'), ("çćč", 'c'), ("ÇĆČ", 'C'),
+        ("èéêëē", 'e'), ("ÈÉÊËĒ", 'E'), ("ìíîïī", 'i'), ("ÌÍÎÏĪ", 'I'),
+        ("òóôõöøō", 'o'), ("ÒÓÔÕÖØŌ", 'O'), ("ùúûüū", 'u'), ("ÙÚÛÜŪ", 'U'),
+        ("ñń", 'n'), ("ÑŃ", 'N'), ("┌┐└┘├┤┬┴┼", '+'), ("•∙·", '*'),
+    ];
+
+    match ch {
+        '─' => '-',
+        '│' => '|',
+        ch => TABLE.iter()
+            .find(|(from, _)| from.contains(ch))
+            .map(|(_, to)| *to)
+            .unwrap_or('?'),
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color when writing to a terminal and `NO_COLOR` is not set.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal && std::env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
 pub struct BuildContext {
     pub size: Option<Size>,
     pub terminal_size: Size,
+    pub encoding: OutputEncoding,
+    /// Spaces before the content of a box, a padded box also gets a blank line above and below.
+    pub padding: usize,
 }
 
 impl BuildContext {
     pub fn new(terminal_size: Size) -> Self {
-        Self { size: None, terminal_size, }
+        Self { size: None, terminal_size, encoding: OutputEncoding::default(), padding: 0, }
     }
 
     pub fn size(&mut self, size: Size) {
         self.size = Some(size);
     }
 
+    pub fn encoding(&mut self, encoding: OutputEncoding) {
+        self.encoding = encoding;
+    }
+
+    pub fn padding(&mut self, padding: usize) {
+        self.padding = padding;
+    }
+
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -47,93 +125,200 @@ impl UIBox<'_> {
     pub fn new<T: Into<String>, C: Into<String>>(context: &BuildContext, title: T, content: C) -> UIBox<'_> {
         UIBox {
             context,
-            title: title.into(),
-            content: content.into(),
+            title: context.encoding.sanitize(&title.into()),
+            content: context.encoding.sanitize(&content.into()),
         }
     }
 
     pub fn render(self) {
+        // stdout going away is not something a listing can recover from
+        let _ = self.render_to(&mut std::io::stdout().lock());
+    }
+
+    pub fn render_to(self, out: &mut impl Write) -> io::Result<()> {
         let computed_width = self.computed_width();
 
-        self.render_header(computed_width);
-        self.render_content(computed_width);
-        self.render_footer(computed_width);
+        self.render_header(out, computed_width)?;
+        self.render_content(out, computed_width)?;
+        self.render_footer(out, computed_width)
     }
 
-    fn render_header(&self, computed_width: usize) {
-        self.render_horizontal(computed_width, '┌', '┐', '─', self.title.as_ref());
+    fn render_header(&self, out: &mut dyn Write, computed_width: usize) -> io::Result<()> {
+        self.render_horizontal(out, computed_width, '┌', '┐', '─', self.title.as_ref())
     }
 
-    fn render_content(&self, computed_width: usize) {
-        let mut chars = &mut self.content.chars();
+    fn render_content(&self, out: &mut dyn Write, computed_width: usize) -> io::Result<()> {
+        let padding = self.context.padding;
+        let line_width = computed_width.saturating_sub(2 + padding).max(1);
         let mut line_char_count = 0;
+        let mut line_open = false;
 
-        fn fill_whitespace_and_close(line_char_count: usize, computed_width: usize) {
-            let remaining_length = computed_width - line_char_count - 2;
+        let vertical = self.context.encoding.sanitize_char('│');
+
+        fn open_line(out: &mut dyn Write, padding: usize, vertical: char) -> io::Result<()> {
+            write!(out, "{}{:padding$}", vertical, "")
+        }
+
+        fn fill_whitespace_and_close(out: &mut dyn Write, line_char_count: usize, line_width: usize, vertical: char) -> io::Result<()> {
+            let remaining_length = line_width.saturating_sub(line_char_count);
             for _ in 0..remaining_length {
-                print!(" ");
+                write!(out, " ")?;
             }
-            println!("│");
+            writeln!(out, "{}", vertical)
         }
 
-        for ch in chars {
-            if line_char_count == 0 {
-                print!("│");
+        if padding > 0 {
+            open_line(out, padding, vertical)?;
+            fill_whitespace_and_close(out, 0, line_width, vertical)?;
+        }
+
+        for ch in self.content.chars() {
+            if !line_open {
+                open_line(out, padding, vertical)?;
+                line_open = true;
             }
 
             if ch == '\n' {
-                fill_whitespace_and_close(line_char_count, computed_width);
+                fill_whitespace_and_close(out, line_char_count, line_width, vertical)?;
                 line_char_count = 0;
+                line_open = false;
                 continue;
             }
 
-            if line_char_count >= computed_width -2 {
-                print!("│");
+            // wrap, the character goes on to the next line
+            if line_char_count >= line_width {
+                fill_whitespace_and_close(out, line_char_count, line_width, vertical)?;
+                open_line(out, padding, vertical)?;
                 line_char_count = 0;
-                continue;
             }
 
-            print!("{}", ch);
+            write!(out, "{}", ch)?;
 
             line_char_count += 1;
         }
-        fill_whitespace_and_close(line_char_count, computed_width);
+        if !line_open {
+            open_line(out, padding, vertical)?;
+        }
+        fill_whitespace_and_close(out, line_char_count, line_width, vertical)?;
+
+        if padding > 0 {
+            open_line(out, padding, vertical)?;
+            fill_whitespace_and_close(out, 0, line_width, vertical)?;
+        }
+        Ok(())
     }
 
-    fn render_footer(&self, computed_width: usize) {
-        self.render_horizontal(computed_width, '└', '┘', '─', None);
+    fn render_footer(&self, out: &mut dyn Write, computed_width: usize) -> io::Result<()> {
+        self.render_horizontal(out, computed_width, '└', '┘', '─', None)
     }
 
-    fn render_horizontal<'a, T: Into<Option<&'a str>>>(&self, computed_width: usize, start_char: char, end_char: char, between_char: char, text: T) {
+    fn render_horizontal<'a, T: Into<Option<&'a str>>>(&self, out: &mut dyn Write, computed_width: usize, start_char: char, end_char: char, between_char: char, text: T) -> io::Result<()> {
         let text = text.into().unwrap_or("");
+        let encoding = self.context.encoding;
+        let (start_char, end_char, between_char) = (
+            encoding.sanitize_char(start_char),
+            encoding.sanitize_char(end_char),
+            encoding.sanitize_char(between_char),
+        );
         let mut chars = text.chars();
 
         for width in 0..computed_width {
             if width == 0 {
-                print!("{}", start_char);
+                write!(out, "{}", start_char)?;
                 continue;
             }
 
             if width == computed_width - 1 {
-                println!("{}", end_char);
+                writeln!(out, "{}", end_char)?;
                 continue;
             }
 
             if let Some(char) = chars.next() {
-                print!("{}", char);
+                write!(out, "{}", char)?;
             } else {
-                print!("{}", between_char);
+                write!(out, "{}", between_char)?;
             }
         }
+        Ok(())
     }
 
     fn computed_width(&self) -> usize {
         let terminal_width = self.context.terminal_size.width.unwrap();
-        let width = match self.context.size {
+        match self.context.size {
             Some(size) => size.width.unwrap_or(terminal_width),
             None => terminal_width,
-        };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::{BuildContext, OutputEncoding, Size, UIBox};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_output_encoding_sanitize() {
+        assert_eq!(OutputEncoding::Utf8.sanitize("┌ café •"), "┌ café •");
+        assert_eq!(OutputEncoding::Cp437.sanitize("┌ café • ☃"), "┌ café ∙ ?");
+        assert_eq!(OutputEncoding::Ascii.sanitize("┌─ café • ☃ │"), "+- cafe * ? |");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_render_non_ascii_path_as_ascii() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = PathBuf::from(OsStr::from_bytes(b"/home/j\xc3\xbcrgen/pr\xffoject"));
+        let mut context = BuildContext::new(Size::new(40, 10));
+        context.encoding(OutputEncoding::Ascii);
+
+        let content = format!("Project folder: {}", path.display());
+        assert!(context.encoding.sanitize(&content).is_ascii());
+        UIBox::new(&context, " [0] Rust ", content).render();
+    }
+
+    #[test]
+    fn test_render_to_buffer() {
+        let mut context = BuildContext::new(Size::new(80, 10));
+        context.size(Size::only_width(16));
+        let mut buffer = Vec::new();
+
+        UIBox::new(&context, " [0] Rust ", "12 MB\ntarget")
+            .render_to(&mut buffer)
+            .expect("Failed to render into buffer");
+
+        assert_eq!(
+            String::from_utf8(buffer).expect("Rendered box is not UTF-8"),
+            "┌ [0] Rust ────┐\n│12 MB         │\n│target        │\n└──────────────┘\n"
+        );
+    }
+
+    #[test]
+    fn test_render_with_padding() {
+        let mut context = BuildContext::new(Size::new(80, 10));
+        context.size(Size::only_width(16));
+        context.padding(2);
+        let mut buffer = Vec::new();
+
+        UIBox::new(&context, " [0] Rust ", "12 MB\ntarget/debug/deps")
+            .render_to(&mut buffer)
+            .expect("Failed to render into buffer");
 
-        width
+        let rendered = String::from_utf8(buffer).expect("Rendered box is not UTF-8");
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "┌ [0] Rust ────┐",
+                "│              │",
+                "│  12 MB       │",
+                "│  target/debug│",
+                "│  /deps       │",
+                "│              │",
+                "└──────────────┘",
+            ]
+        );
+        assert!(lines.iter().all(|line| line.chars().count() == 16));
     }
 }
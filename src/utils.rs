@@ -1,29 +1,595 @@
 use crate::error::GarbageError;
-use crate::garbage::GarbageRecognizerResult;
+use crate::garbage::{GarbageRecognizerResult, SizeStrategy};
 use base64::{engine::general_purpose, Engine as _};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
 use std::fs;
 use std::fs::File;
 use std::hash::{Hash, Hasher};
-use std::io::{Read, Write};
+use std::io::{BufRead, BufWriter, Read, Write};
 use std::ops::Add;
 use std::path::{Path, PathBuf};
-use std::time::{Duration, SystemTime};
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
+#[cfg(test)]
 pub fn dir_size(path: impl Into<PathBuf>) -> std::io::Result<u64> {
+    dir_size_with_deadline(path, SizeStrategy::Walk, None)
+}
+
+/// The form of `path` to hand to the file system. On Windows absolute paths get the
+/// `\\?\` extended-length prefix so trees nested deeper than MAX_PATH (260 characters),
+/// as in `node_modules`, can be measured and deleted. Elsewhere the path is unchanged.
+pub fn long_path(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    if let Some(extended) = path.to_str().and_then(extended_length_path) {
+        return Cow::Owned(PathBuf::from(extended));
+    }
+    Cow::Borrowed(path)
+}
+
+/// Extended-length form of an absolute Windows path, `None` for paths that are relative,
+/// already extended or contain `.` or `..`, which the extended syntax doesn't resolve.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn extended_length_path(path: &str) -> Option<String> {
+    if path.starts_with(r"\\?\") {
+        return None;
+    }
+    let path = path.replace('/', "\\");
+    if path
+        .split('\\')
+        .any(|component| component == "." || component == "..")
+    {
+        return None;
+    }
+    if let Some(share) = path.strip_prefix(r"\\") {
+        return Some(format!(r"\\?\UNC\{}", share));
+    }
+
+    let bytes = path.as_bytes();
+    let has_drive =
+        bytes.len() >= 3 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && bytes[2] == b'\\';
+    has_drive.then(|| format!(r"\\?\{}", path))
+}
+
+fn walk_dir_size(
+    path: PathBuf,
+    deadline: Option<Instant>,
+    throttle: Option<&IoThrottle>,
+) -> std::io::Result<u64> {
+    check_limits(deadline, throttle)?;
+    let mut dir: fs::ReadDir = fs::read_dir(path)?;
+    dir.try_fold(0, |acc, file| {
+        let file = file?;
+        let size = match file.metadata()? {
+            data if data.is_dir() => walk_dir_size(file.path(), deadline, throttle)?,
+            data => data.len(),
+        };
+        Ok(acc + size)
+    })
+}
+
+/// The latest modification time of `path` and everything below it, links are not
+/// followed. `None` when nothing could be read.
+pub fn newest_modified_in(path: &Path) -> Option<SystemTime> {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok()?.modified().ok())
+        .max()
+}
+
+pub fn dir_file_count(path: impl Into<PathBuf>) -> std::io::Result<u64> {
     let mut dir: fs::ReadDir = fs::read_dir(path.into())?;
+    dir.try_fold(0, |acc, file| {
+        let file = file?;
+        let count = match file.metadata()? {
+            data if data.is_dir() => dir_file_count(file.path())?,
+            _ => 1,
+        };
+        Ok(acc + count)
+    })
+}
+
+pub fn dir_size_with_strategy(
+    path: impl Into<PathBuf>,
+    strategy: SizeStrategy,
+) -> std::io::Result<u64> {
+    dir_size_with_deadline(path, strategy, None)
+}
+
+/// The size of every entry directly inside `path`, each directory measured as a whole,
+/// sorted from the largest. With [`SizeStrategy::Blocks`] a file hard linked from two
+/// of the entries counts for both.
+pub fn dir_size_breakdown(
+    path: &Path,
+    strategy: SizeStrategy,
+    throttle: Option<&IoThrottle>,
+) -> std::io::Result<Vec<(PathBuf, u64)>> {
+    check_limits(None, throttle)?;
+    let mut sizes = Vec::new();
+    for entry in fs::read_dir(long_path(path))? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let size = match (metadata.is_dir(), strategy) {
+            (true, SizeStrategy::Apparent) => {
+                metadata.len() + throttled_dir_size(entry.path(), strategy, None, throttle)?
+            }
+            (true, strategy) => throttled_dir_size(entry.path(), strategy, None, throttle)?,
+            (false, SizeStrategy::Blocks) => allocated_size(&metadata),
+            (false, _) => metadata.len(),
+        };
+        sizes.push((path.join(entry.file_name()), size));
+    }
+    sizes.sort_by(|(a_path, a_size), (b_path, b_size)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+    Ok(sizes)
+}
+
+#[cfg(unix)]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// Like [`dir_size_with_strategy`], but fails with [`std::io::ErrorKind::TimedOut`] once
+/// the deadline has passed.
+pub fn dir_size_with_deadline(
+    path: impl Into<PathBuf>,
+    strategy: SizeStrategy,
+    deadline: Option<Instant>,
+) -> std::io::Result<u64> {
+    throttled_dir_size(path, strategy, deadline, None)
+}
+
+/// Like [`dir_size_with_deadline`], waiting for the throttle before reading a directory.
+fn throttled_dir_size(
+    path: impl Into<PathBuf>,
+    strategy: SizeStrategy,
+    deadline: Option<Instant>,
+    throttle: Option<&IoThrottle>,
+) -> std::io::Result<u64> {
+    let path = long_path(&path.into()).into_owned();
+    match strategy {
+        SizeStrategy::Walk => walk_dir_size(path, deadline, throttle),
+        SizeStrategy::Apparent => apparent_dir_size(path, deadline, throttle),
+        SizeStrategy::Blocks => block_dir_size(&path, &mut HashSet::new(), deadline, throttle),
+    }
+}
+
+/// Entries a directory needs before [`dir_size_with_parallelism`] splits it across threads.
+pub const PARALLEL_SIZE_THRESHOLD: usize = 1000;
+
+/// Spreads measuring a directory with more than `threshold` entries over up to `threads`
+/// threads, one share of its subdirectories each.
+#[derive(Debug, Clone, Copy)]
+pub struct SizeParallelism {
+    pub threads: usize,
+    pub threshold: usize,
+}
+
+/// Like [`dir_size_with_deadline`], fanning out over the immediate subdirectories of
+/// `path` when `parallelism` asks for it. The partial sums add up to exactly the
+/// sequential size. [`SizeStrategy::Blocks`] always runs sequentially since its hard link
+/// deduplication spans the whole tree.
+pub fn dir_size_with_parallelism(
+    path: impl Into<PathBuf>,
+    strategy: SizeStrategy,
+    deadline: Option<Instant>,
+    parallelism: Option<SizeParallelism>,
+    throttle: Option<&IoThrottle>,
+) -> std::io::Result<u64> {
+    let path = long_path(&path.into()).into_owned();
+    match parallelism {
+        Some(parallelism) => fanned_out_dir_size(&path, strategy, deadline, parallelism, throttle)
+            .map(|(size, _)| size),
+        None => throttled_dir_size(path, strategy, deadline, throttle),
+    }
+}
+
+/// The size of `path` and how many threads measured it.
+fn fanned_out_dir_size(
+    path: &Path,
+    strategy: SizeStrategy,
+    deadline: Option<Instant>,
+    parallelism: SizeParallelism,
+    throttle: Option<&IoThrottle>,
+) -> std::io::Result<(u64, usize)> {
+    if strategy == SizeStrategy::Blocks || parallelism.threads < 2 {
+        return Ok((throttled_dir_size(path, strategy, deadline, throttle)?, 1));
+    }
+    check_limits(deadline, throttle)?;
+    let entries = fs::read_dir(path)?.collect::<std::io::Result<Vec<_>>>()?;
+    if entries.len() <= parallelism.threshold {
+        return Ok((throttled_dir_size(path, strategy, deadline, throttle)?, 1));
+    }
+
+    let mut size = 0;
+    let mut directories = Vec::new();
+    for entry in entries {
+        let metadata = entry.metadata()?;
+        if !metadata.is_dir() {
+            size += metadata.len();
+            continue;
+        }
+        if strategy == SizeStrategy::Apparent {
+            size += metadata.len();
+        }
+        directories.push(entry.path());
+    }
+    if directories.is_empty() {
+        return Ok((size, 1));
+    }
+
+    let share = directories.len().div_ceil(parallelism.threads);
+    let (partial_size, threads) = thread::scope(|scope| {
+        let workers: Vec<_> = directories
+            .chunks(share)
+            .map(|directories| {
+                scope.spawn(move || {
+                    directories.iter().try_fold(0, |acc, directory| {
+                        Ok(acc + throttled_dir_size(directory, strategy, deadline, throttle)?)
+                    })
+                })
+            })
+            .collect();
+        let threads = workers.len();
+        let partial_size = workers
+            .into_iter()
+            .map(|worker| worker.join().expect("Size thread panicked"))
+            .sum::<std::io::Result<u64>>();
+        (partial_size, threads)
+    });
+    Ok((size + partial_size?, threads))
+}
+
+/// Measures the size of a deletable.
+pub trait SizeBackend {
+    fn size(
+        &self,
+        path: &Path,
+        strategy: SizeStrategy,
+        deadline: Option<Instant>,
+    ) -> std::io::Result<u64>;
+}
+
+/// Walks the tree itself, see [`dir_size_with_parallelism`].
+#[derive(Debug, Clone, Default)]
+pub struct WalkBackend {
+    pub parallelism: Option<SizeParallelism>,
+    pub throttle: Option<Arc<IoThrottle>>,
+}
+
+impl SizeBackend for WalkBackend {
+    fn size(
+        &self,
+        path: &Path,
+        strategy: SizeStrategy,
+        deadline: Option<Instant>,
+    ) -> std::io::Result<u64> {
+        dir_size_with_parallelism(
+            path,
+            strategy,
+            deadline,
+            self.parallelism,
+            self.throttle.as_deref(),
+        )
+    }
+}
+
+/// Asks GNU `du` for the total, falling back to the walk whenever that fails. `du`
+/// counts directory entries as well, so [`SizeStrategy::Walk`], the file lengths alone,
+/// is always walked.
+pub struct DuBackend {
+    fallback: WalkBackend,
+}
+
+impl DuBackend {
+    /// The backend if a GNU `du` is on the path, which the BSD `du` of macOS is not.
+    pub fn detect(fallback: WalkBackend) -> Option<Self> {
+        let status = Command::new("du")
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        matches!(status, Ok(status) if status.success()).then_some(Self { fallback })
+    }
+
+    /// The total of `du`, `None` when it can't tell. `du` is killed once the deadline
+    /// has passed.
+    fn du_size(
+        &self,
+        path: &Path,
+        strategy: SizeStrategy,
+        deadline: Option<Instant>,
+    ) -> std::io::Result<Option<u64>> {
+        let size_arg = match strategy {
+            SizeStrategy::Blocks => "--block-size=1",
+            SizeStrategy::Apparent => "--bytes",
+            SizeStrategy::Walk => return Ok(None),
+        };
+        let Ok(mut child) = Command::new("du")
+            .args(["--summarize", size_arg, "--"])
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            return Ok(None);
+        };
+        // the single line of output fits in the pipe, du never blocks on it
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) => {}
+                Err(_) => return Ok(None),
+            }
+            if let Err(error) = check_deadline(deadline) {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(error);
+            }
+            thread::sleep(DU_POLL_INTERVAL);
+        };
+        let mut output = String::new();
+        if !status.success() {
+            return Ok(None);
+        }
+        if let Some(mut stdout) = child.stdout.take() {
+            let _ = stdout.read_to_string(&mut output);
+        }
+        Ok(parse_du_output(&output))
+    }
+}
+
+/// How often a running `du` is checked against the deadline.
+const DU_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+impl SizeBackend for DuBackend {
+    fn size(
+        &self,
+        path: &Path,
+        strategy: SizeStrategy,
+        deadline: Option<Instant>,
+    ) -> std::io::Result<u64> {
+        check_deadline(deadline)?;
+        match self.du_size(path, strategy, deadline)? {
+            Some(size) => Ok(size),
+            None => self.fallback.size(path, strategy, deadline),
+        }
+    }
+}
+
+/// The byte total of `du --summarize`, printed as `<size>\t<path>`.
+fn parse_du_output(output: &str) -> Option<u64> {
+    output.split_whitespace().next()?.parse().ok()
+}
+
+/// Caps how many directories are read per second with a token bucket, shared by the scan
+/// and every thread measuring deletables. Up to a second worth of reads may burst.
+#[derive(Debug)]
+pub struct IoThrottle {
+    reads_per_second: f64,
+    bucket: Mutex<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    /// Negative while reads wait for tokens that are yet to be refilled.
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl IoThrottle {
+    pub fn new(reads_per_second: u32) -> Self {
+        Self::starting_at(reads_per_second, Instant::now())
+    }
+
+    fn starting_at(reads_per_second: u32, now: Instant) -> Self {
+        Self {
+            reads_per_second: reads_per_second.max(1) as f64,
+            bucket: Mutex::new(TokenBucket {
+                tokens: 1.0,
+                refilled_at: now,
+            }),
+        }
+    }
+
+    /// Blocks until the next directory may be read.
+    pub fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Takes a token at `now` and returns how long to wait until it is refilled.
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self
+            .bucket
+            .lock()
+            .unwrap_or_else(|error| error.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.reads_per_second)
+            .min(self.reads_per_second);
+        bucket.refilled_at = bucket.refilled_at.max(now);
+        bucket.tokens -= 1.0;
+
+        match bucket.tokens {
+            tokens if tokens >= 0.0 => Duration::ZERO,
+            tokens => Duration::from_secs_f64(-tokens / self.reads_per_second),
+        }
+    }
+}
+
+/// Waits for the throttle if there is one, then fails like [`check_deadline`].
+fn check_limits(deadline: Option<Instant>, throttle: Option<&IoThrottle>) -> std::io::Result<()> {
+    if let Some(throttle) = throttle {
+        throttle.acquire();
+    }
+    check_deadline(deadline)
+}
+
+fn check_deadline(deadline: Option<Instant>) -> std::io::Result<()> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "scan deadline exceeded",
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn apparent_dir_size(
+    path: PathBuf,
+    deadline: Option<Instant>,
+    throttle: Option<&IoThrottle>,
+) -> std::io::Result<u64> {
+    check_limits(deadline, throttle)?;
+    let mut dir: fs::ReadDir = fs::read_dir(path)?;
     dir.try_fold(0, |acc, file| {
         let file = file?;
         let size = match file.metadata()? {
-            data if data.is_dir() => dir_size(file.path())?,
+            data if data.is_dir() => {
+                data.len() + apparent_dir_size(file.path(), deadline, throttle)?
+            }
             data => data.len(),
         };
         Ok(acc + size)
     })
 }
 
+#[cfg(unix)]
+fn block_dir_size(
+    path: &Path,
+    seen_inodes: &mut HashSet<(u64, u64)>,
+    deadline: Option<Instant>,
+    throttle: Option<&IoThrottle>,
+) -> std::io::Result<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    check_limits(deadline, throttle)?;
+    let mut size = 0;
+    for file in fs::read_dir(path)? {
+        let file = file?;
+        let metadata = file.metadata()?;
+
+        if !metadata.is_dir()
+            && metadata.nlink() > 1
+            && !seen_inodes.insert((metadata.dev(), metadata.ino()))
+        {
+            continue;
+        }
+
+        size += metadata.blocks() * 512;
+        if metadata.is_dir() {
+            size += block_dir_size(&file.path(), seen_inodes, deadline, throttle)?;
+        }
+    }
+    Ok(size)
+}
+
+#[cfg(not(unix))]
+fn block_dir_size(
+    path: &Path,
+    _seen_inodes: &mut HashSet<(u64, u64)>,
+    deadline: Option<Instant>,
+    throttle: Option<&IoThrottle>,
+) -> std::io::Result<u64> {
+    apparent_dir_size(path.to_path_buf(), deadline, throttle)
+}
+
+/// Space allocated on disk, `None` where the platform doesn't expose block counts.
+pub fn disk_dir_size(path: &Path) -> Option<u64> {
+    if cfg!(unix) {
+        block_dir_size(&long_path(path), &mut HashSet::new(), None, None).ok()
+    } else {
+        None
+    }
+}
+
+/// Space allocated on disk for everything below `paths` together, where a file hard
+/// linked from several of them, as pnpm does for `node_modules`, counts once. `None`
+/// where the platform doesn't expose inodes.
+#[cfg(unix)]
+pub fn unique_disk_size<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut seen_inodes = HashSet::new();
+    let mut size = 0;
+    for path in paths {
+        let path = long_path(path);
+        size += match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.is_dir() => {
+                block_dir_size(&path, &mut seen_inodes, None, None).unwrap_or_default()
+            }
+            Ok(metadata) if seen_inodes.insert((metadata.dev(), metadata.ino())) => {
+                metadata.blocks() * 512
+            }
+            _ => 0,
+        };
+    }
+    Some(size)
+}
+
+#[cfg(not(unix))]
+pub fn unique_disk_size<'a>(_paths: impl IntoIterator<Item = &'a Path>) -> Option<u64> {
+    None
+}
+
+/// Matches a file name against a pattern where `*` matches any run of characters and
+/// `?` exactly one.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 pub fn format_bytes(bytes: u64) -> String {
+    format_bytes_prec(bytes, 2)
+}
+
+/// Like [`format_bytes`] with the given number of decimals. A value that rounds up to 1000
+/// is moved to the next unit, 999,990 bytes with one decimal is `1.0 MB`, not `1000.0 kB`.
+pub fn format_bytes_prec(bytes: u64, decimals: usize) -> String {
     let units = ["B", "kB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
     let mut value = bytes as f64;
     let mut unit_index = 0;
@@ -32,10 +598,296 @@ pub fn format_bytes(bytes: u64) -> String {
         value /= 1000.0;
         unit_index += 1;
     }
+    // decide on the printed digits, rounding the float separately could disagree with them
+    let rounded: f64 = format!("{:.*}", decimals, value).parse().unwrap_or(value);
+    if rounded >= 1000.0 && unit_index < units.len() - 1 {
+        value /= 1000.0;
+        unit_index += 1;
+    }
+
+    format!("{:.*} {}", decimals, value, units[unit_index])
+}
+
+/// Like [`format_bytes_prec`] followed by the exact byte count, e.g.
+/// `4.20 GB (4,203,145,216 bytes)`. The humanized value is computed without floating
+/// point and truncated, so it never rounds up into the next unit.
+pub fn format_bytes_exact(bytes: u64, decimals: usize) -> String {
+    let units = ["B", "kB", "MB", "GB", "TB", "PB", "EB"];
+    let mut divisor: u64 = 1;
+    let mut unit_index = 0;
+
+    while bytes / divisor >= 1000 && unit_index < units.len() - 1 {
+        divisor *= 1000;
+        unit_index += 1;
+    }
+    // digits past what a u64 remainder holds are always zero
+    let digits = decimals.min(19);
+    let fraction = (bytes % divisor) as u128 * 10u128.pow(digits as u32) / divisor as u128;
+    let value = match decimals {
+        0 => (bytes / divisor).to_string(),
+        _ => format!(
+            "{}.{:0width$}{}",
+            bytes / divisor,
+            fraction,
+            "0".repeat(decimals - digits),
+            width = digits
+        ),
+    };
+
+    format!(
+        "{} {} ({} bytes)",
+        value,
+        units[unit_index],
+        format_number(bytes)
+    )
+}
+
+/// A fixed unit for the printed sizes, instead of the one [`format_bytes`] scales to.
+#[derive(clap::ValueEnum, Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SizeUnit {
+    B,
+    #[value(name = "kB")]
+    KB,
+    MB,
+    GB,
+    TB,
+}
+
+impl SizeUnit {
+    fn symbol(&self) -> &'static str {
+        match self {
+            SizeUnit::B => "B",
+            SizeUnit::KB => "kB",
+            SizeUnit::MB => "MB",
+            SizeUnit::GB => "GB",
+            SizeUnit::TB => "TB",
+        }
+    }
+
+    fn bytes(&self) -> u64 {
+        match self {
+            SizeUnit::B => 1,
+            SizeUnit::KB => 1_000,
+            SizeUnit::MB => 1_000_000,
+            SizeUnit::GB => 1_000_000_000,
+            SizeUnit::TB => 1_000_000_000_000,
+        }
+    }
+}
+
+/// Like [`format_bytes_prec`] but always in the given unit, e.g. `0.05 GB` for 50 MB.
+pub fn format_bytes_in(bytes: u64, unit: SizeUnit, decimals: usize) -> String {
+    format!(
+        "{:.*} {}",
+        decimals,
+        bytes as f64 / unit.bytes() as f64,
+        unit.symbol()
+    )
+}
+
+/// How the printed sizes look, set by `--size-unit` and `--precision`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SizeFormat {
+    /// A fixed unit, scaled for each size if not chosen.
+    pub unit: Option<SizeUnit>,
+    pub decimals: usize,
+}
+
+impl Default for SizeFormat {
+    fn default() -> Self {
+        Self {
+            unit: None,
+            decimals: 2,
+        }
+    }
+}
+
+/// Formats in the fixed unit if one was chosen, scaled by [`format_bytes_prec`] otherwise.
+pub fn format_size(bytes: u64, format: SizeFormat) -> String {
+    match format.unit {
+        Some(unit) => format_bytes_in(bytes, unit, format.decimals),
+        None => format_bytes_prec(bytes, format.decimals),
+    }
+}
+
+/// Parses a human readable size like `500MB`, `1.5 GB` or `42` (bytes) using the same
+/// decimal units as [`format_bytes`], or binary ones like the `1.2GiB` of cargo.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split_at = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split_at);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("Invalid size {:?}, expected e.g. 500MB or 2GB", value))?;
+    let multiplier: f64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "kb" | "k" => 1e3,
+        "mb" | "m" => 1e6,
+        "gb" | "g" => 1e9,
+        "tb" | "t" => 1e12,
+        "pb" | "p" => 1e15,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        "gib" => 1024.0 * 1024.0 * 1024.0,
+        "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        unit => return Err(format!("Unknown size unit {:?}", unit)),
+    };
+
+    Ok((number * multiplier).round() as u64)
+}
+
+/// Parses a human readable duration like `30m`, `2h`, `7d`, `2w`, `3mo` or `1y`. A unit
+/// is required, months count as 30 days and years as 365 days.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split_at = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split_at);
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("Invalid duration {:?}, expected e.g. 30m or 7d", value))?;
+    let seconds: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "mo" => 30 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        "" => {
+            return Err(format!(
+                "Missing unit for duration {:?}, expected e.g. 30m or 7d",
+                value
+            ))
+        }
+        unit => return Err(format!("Unknown duration unit {:?}", unit)),
+    };
+
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("Duration {:?} is too large", value))
+}
+
+/// Formats an integer with a comma as thousands separator, e.g. `12,431`.
+pub fn format_number(number: u64) -> String {
+    let digits = number.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
 
-    format!("{:.2} {}", value, units[unit_index])
+    formatted
 }
 
+/// A count with the noun it counts, e.g. `1 project` or `12,431 projects`.
+pub fn format_count(count: u64, singular: &str, plural: &str) -> String {
+    let noun = match count {
+        1 => singular,
+        _ => plural,
+    };
+    format!("{} {}", format_number(count), noun)
+}
+
+/// Formats seconds since the Unix epoch as a UTC date, e.g. `2024-03-09`.
+pub fn format_date(timestamp: u64) -> String {
+    // days to civil date, see https://howardhinnant.github.io/date_algorithms.html
+    let days = timestamp / (24 * 60 * 60) + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Reads the lines of `source` on another thread and fails with
+/// [`std::io::ErrorKind::TimedOut`] when none arrives in time, so a prompt can give up
+/// instead of blocking forever. The thread is left behind blocked on `source`.
+pub struct TimedLineReader {
+    lines: mpsc::Receiver<Vec<u8>>,
+    timeout: Duration,
+    line: Vec<u8>,
+    position: usize,
+}
+
+impl TimedLineReader {
+    pub fn spawn(mut source: impl BufRead + Send + 'static, timeout: Duration) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || loop {
+            let mut line = Vec::new();
+            match source.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) if sender.send(line).is_err() => break,
+                Ok(_) => {}
+            }
+        });
+
+        Self {
+            lines,
+            timeout,
+            line: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for TimedLineReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let length = available.len().min(buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        self.consume(length);
+        Ok(length)
+    }
+}
+
+impl BufRead for TimedLineReader {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.position >= self.line.len() {
+            match self.lines.recv_timeout(self.timeout) {
+                Ok(line) => {
+                    self.line = line;
+                    self.position = 0;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        "no response",
+                    ))
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(&[]),
+            }
+        }
+        Ok(&self.line[self.position..])
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.position += amount;
+    }
+}
+
+/// How long a cached listing stays valid unless the recognizers ask for something else.
+pub const DEFAULT_CACHE_DURABILITY: Duration = Duration::from_secs(60 * 5);
+
 pub fn write_garbage_result_vec_cache(
     from_path: &Path,
     result_list: &Vec<GarbageRecognizerResult>,
@@ -46,56 +898,203 @@ pub fn write_garbage_result_vec_cache(
     let cache_file_path = cache_dir_path.join(path_hash);
 
     if !cache_dir_path.exists() {
-        fs::create_dir(cache_dir_path)?;
+        fs::create_dir_all(cache_dir_path)?;
     }
 
     if cache_file_path.exists() && cache_file_path.is_file() {
         let estimated_time = cache_file_path
             .metadata()?
             .modified()?
-            .add(cache_durability.unwrap_or(Duration::from_secs(60 * 5)));
+            .add(cache_durability.unwrap_or(DEFAULT_CACHE_DURABILITY));
 
         if is_cache_durable(estimated_time) {
             return Ok(cache_file_path);
         }
     }
 
-    let mut file = File::create(&cache_file_path)?;
-    let json_string = serde_json::to_string_pretty(result_list)?;
-    file.write_all(json_string.as_bytes())?;
+    write_cache_file(&cache_file_path, from_path, result_list)?;
+    Ok(cache_file_path)
+}
+
+fn write_cache_file(
+    cache_file_path: &Path,
+    from_path: &Path,
+    result_list: &Vec<GarbageRecognizerResult>,
+) -> Result<(), GarbageError> {
+    // serialized straight into the file, a huge listing is never held as one string
+    let mut writer = BufWriter::new(File::create(cache_file_path)?);
+    serde_json::to_writer_pretty(
+        &mut writer,
+        &CachedListing {
+            source: from_path.to_path_buf(),
+            results: result_list,
+        },
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Overwrites the cache for the path regardless of its remaining durability.
+pub fn replace_garbage_result_vec_cache(
+    from_path: &Path,
+    result_list: &Vec<GarbageRecognizerResult>,
+) -> Result<PathBuf, GarbageError> {
+    write_garbage_result_vec_cache(from_path, result_list, Some(Duration::ZERO))
+}
+
+/// Overwrites the cache for the path with what is left of its results, keeping the time
+/// they were scanned at so the cache expires when the scan would have.
+pub fn refresh_garbage_result_vec_cache(
+    from_path: &Path,
+    result_list: &Vec<GarbageRecognizerResult>,
+) -> Result<PathBuf, GarbageError> {
+    refresh_garbage_result_vec_cache_in(&std::env::temp_dir().join("wsg/"), from_path, result_list)
+}
+
+fn refresh_garbage_result_vec_cache_in(
+    cache_dir_path: &Path,
+    from_path: &Path,
+    result_list: &Vec<GarbageRecognizerResult>,
+) -> Result<PathBuf, GarbageError> {
+    let cache_file_path = cache_dir_path.join(generate_base64_from_path(from_path));
+    let scanned_at = fs::metadata(&cache_file_path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    fs::create_dir_all(cache_dir_path)?;
+    write_cache_file(&cache_file_path, from_path, result_list)?;
+    if let Some(scanned_at) = scanned_at {
+        File::options()
+            .write(true)
+            .open(&cache_file_path)?
+            .set_modified(scanned_at)?;
+    }
 
     Ok(cache_file_path)
 }
 
-pub fn read_garbage_result_vec_cache(
-    from_path: &Path,
-    cache_durability: Option<Duration>,
-) -> Result<Vec<GarbageRecognizerResult>, GarbageError> {
-    let path_hash = generate_base64_from_path(from_path);
-    let cache_dir_path = std::env::temp_dir().join("wsg/");
-    let cache_file_path = cache_dir_path.join(path_hash);
+pub fn read_garbage_result_vec_cache(
+    from_path: &Path,
+    cache_durability: Option<Duration>,
+) -> Result<Vec<GarbageRecognizerResult>, GarbageError> {
+    read_garbage_result_vec_cache_with(from_path, |_| cache_durability)
+}
+
+/// Like [`read_garbage_result_vec_cache`] with the durability derived from the cached
+/// results, e.g. from the recognizers that produced them.
+pub fn read_garbage_result_vec_cache_with(
+    from_path: &Path,
+    cache_durability: impl FnOnce(&[GarbageRecognizerResult]) -> Option<Duration>,
+) -> Result<Vec<GarbageRecognizerResult>, GarbageError> {
+    let (result_list, modified) = read_garbage_result_vec_cache_any_age(from_path)?;
+
+    let estimated_time =
+        modified.add(cache_durability(&result_list).unwrap_or(DEFAULT_CACHE_DURABILITY));
+    if !is_cache_durable(estimated_time) {
+        return Err(GarbageError::InvalidCache);
+    }
+
+    Ok(result_list)
+}
+
+/// Reads the cache for the path however old it is, along with when it was written.
+pub fn read_garbage_result_vec_cache_any_age(
+    from_path: &Path,
+) -> Result<(Vec<GarbageRecognizerResult>, SystemTime), GarbageError> {
+    let path_hash = generate_base64_from_path(from_path);
+    let cache_dir_path = std::env::temp_dir().join("wsg/");
+    let cache_file_path = cache_dir_path.join(path_hash);
+
+    let mut file = File::open(&cache_file_path)?;
+    let modified = file.metadata()?.modified()?;
+
+    let mut json_string = String::new();
+    file.read_to_string(&mut json_string)?;
+    let listing: CachedListing<Vec<GarbageRecognizerResult>> =
+        match serde_json::from_str(&json_string) {
+            Ok(listing) => listing,
+            // written before the source was recorded, it can't be told apart from a collision
+            Err(_) if json_string.trim_start().starts_with('[') => {
+                return Err(GarbageError::InvalidCache)
+            }
+            Err(error) => return Err(error.into()),
+        };
+    // the file name is a short hash, another path may have written it
+    if listing.source != from_path {
+        return Err(GarbageError::InvalidCache);
+    }
+
+    Ok((listing.results, modified))
+}
+
+/// Serializes a path as a string, or as its raw bytes if it isn't valid UTF-8, which
+/// file names on Unix don't have to be. Elsewhere such a path fails to serialize.
+pub mod os_path {
+    use schemars::JsonSchema;
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+    use std::path::{Path, PathBuf};
+
+    /// How a path is written, for the JSON schema.
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    #[serde(untagged)]
+    pub enum PathRepr {
+        Utf8(String),
+        /// The bytes of a Unix path that isn't valid UTF-8.
+        Bytes(Vec<u8>),
+    }
+
+    pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+        match (path.to_str(), raw_bytes(path)) {
+            (Some(path), _) => serializer.serialize_str(path),
+            (None, Some(bytes)) => bytes.serialize(serializer),
+            (None, None) => Err(ser::Error::custom("path contains invalid UTF-8 characters")),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+        match PathRepr::deserialize(deserializer)? {
+            PathRepr::Utf8(path) => Ok(PathBuf::from(path)),
+            PathRepr::Bytes(bytes) => from_raw_bytes(bytes)
+                .ok_or_else(|| de::Error::custom("raw path bytes are only supported on Unix")),
+        }
+    }
+
+    #[cfg(unix)]
+    fn raw_bytes(path: &Path) -> Option<&[u8]> {
+        use std::os::unix::ffi::OsStrExt;
+        Some(path.as_os_str().as_bytes())
+    }
 
-    let mut file = File::open(&cache_file_path)?;
-    let estimated_time = file
-        .metadata()?
-        .modified()?
-        .add(cache_durability.unwrap_or(Duration::from_secs(60 * 5)));
+    #[cfg(not(unix))]
+    fn raw_bytes(_path: &Path) -> Option<&[u8]> {
+        None
+    }
 
-    if !is_cache_durable(estimated_time) {
-        return Err(GarbageError::InvalidCache);
+    #[cfg(unix)]
+    fn from_raw_bytes(bytes: Vec<u8>) -> Option<PathBuf> {
+        use std::os::unix::ffi::OsStringExt;
+        Some(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
     }
 
-    let mut json_string = String::new();
-    file.read_to_string(&mut json_string)?;
+    #[cfg(not(unix))]
+    fn from_raw_bytes(_bytes: Vec<u8>) -> Option<PathBuf> {
+        None
+    }
+}
 
-    let result_list: Vec<GarbageRecognizerResult> = serde_json::from_str(&json_string)?;
-    Ok(result_list)
+/// The content of a cache file, the scanned path along with the results found in it.
+#[derive(Serialize, Deserialize)]
+struct CachedListing<R> {
+    #[serde(with = "os_path")]
+    source: PathBuf,
+    results: R,
 }
 
 fn is_cache_durable(estimated_time: SystemTime) -> bool {
     SystemTime::now() < estimated_time
 }
 
+#[cfg(test)]
 pub fn delete_garbage_result_vec_cache(from_path: &Path) -> Result<(), GarbageError> {
     let path_hash = generate_base64_from_path(from_path);
     let cache_dir_path = std::env::temp_dir().join("wsg/");
@@ -110,20 +1109,175 @@ pub fn delete_garbage_result_vec_cache(from_path: &Path) -> Result<(), GarbageEr
     Ok(())
 }
 
+/// Name of the recognizer stats store in the [`state_dir`]. Older versions kept it in the
+/// cache directory, so clearing or pruning the cache leaves it alone.
+const RECOGNIZER_STATS_FILE_NAME: &str = "recognizer-stats.ndjson";
+
+/// Name of the clean history in the [`state_dir`], kept like the stats store.
+const CLEAN_HISTORY_FILE_NAME: &str = "clean-history.ndjson";
+
+/// `$XDG_STATE_HOME/wsg`, falling back to `~/.local/state/wsg`. Unlike the cache, what
+/// is kept there is private to the user and outlives reboots.
+pub fn state_dir() -> Option<PathBuf> {
+    let state_dir = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(state_dir.join("wsg"))
+}
+
+pub fn recognizer_stats_store_path() -> Option<PathBuf> {
+    Some(state_dir()?.join(RECOGNIZER_STATS_FILE_NAME))
+}
+
+pub fn clean_history_path() -> Option<PathBuf> {
+    Some(state_dir()?.join(CLEAN_HISTORY_FILE_NAME))
+}
+
+/// Where a scan of `from_path` keeps its progress for `--resume`, next to its cache file.
+pub fn scan_checkpoint_path(from_path: &Path) -> PathBuf {
+    let path_hash = generate_base64_from_path(from_path);
+    std::env::temp_dir()
+        .join("wsg/")
+        .join(format!("{}.scan", path_hash))
+}
+
+/// The time a `--since-file` marker holds, in seconds since the Unix epoch. `None` when
+/// there is no marker yet.
+pub fn read_since_marker(path: &Path) -> std::io::Result<Option<SystemTime>> {
+    let content = match fs::read_to_string(path) {
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        content => content?,
+    };
+    let seconds: u64 = content.trim().parse().map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} does not hold a timestamp", path.display()),
+        )
+    })?;
+    Ok(Some(UNIX_EPOCH + Duration::from_secs(seconds)))
+}
+
+pub fn write_since_marker(path: &Path, time: SystemTime) -> std::io::Result<()> {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_err(std::io::Error::other)?
+        .as_secs();
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, format!("{}\n", seconds))
+}
+
 pub fn delete_all_cache_files() -> Result<(), GarbageError> {
     let cache_dir_path = std::env::temp_dir().join("wsg/");
+    for (path, _) in cache_files(&cache_dir_path)? {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// Removes cache and lock files last modified longer ago than `max_age`, returning how
+/// many were removed.
+pub fn prune_cache_files(max_age: Duration) -> Result<usize, GarbageError> {
+    prune_cache_files_in(&std::env::temp_dir().join("wsg/"), max_age)
+}
+
+fn prune_cache_files_in(cache_dir_path: &Path, max_age: Duration) -> Result<usize, GarbageError> {
+    if !cache_dir_path.exists() {
+        return Ok(0);
+    }
+
+    let now = SystemTime::now();
+    let mut pruned = 0;
+    for (path, metadata) in cache_files(cache_dir_path)? {
+        let age = now.duration_since(metadata.modified()?).unwrap_or_default();
+        if age > max_age && !is_held_lock(&path)? {
+            fs::remove_file(path)?;
+            pruned += 1;
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Whether `path` is the lock file of a cache a concurrent run is holding.
+fn is_held_lock(path: &Path) -> Result<bool, GarbageError> {
+    if path.extension().is_none_or(|extension| extension != "lock") {
+        return Ok(false);
+    }
+    match CacheLock::acquire(path, false) {
+        Ok(_) => Ok(false),
+        Err(GarbageError::CacheLocked) => Ok(true),
+        Err(error) => Err(error),
+    }
+}
+
+fn cache_files(cache_dir_path: &Path) -> Result<Vec<(PathBuf, fs::Metadata)>, GarbageError> {
+    let mut files = Vec::new();
     for entry in WalkDir::new(cache_dir_path)
         .follow_links(false)
         .max_depth(1)
     {
         let entry = entry?;
         let metadata = entry.metadata()?;
-        if metadata.is_file() {
-            fs::remove_file(entry.path())?;
+        let kept = [RECOGNIZER_STATS_FILE_NAME, CLEAN_HISTORY_FILE_NAME]
+            .iter()
+            .any(|name| entry.file_name() == *name);
+        if metadata.is_file() && !kept {
+            files.push((entry.into_path(), metadata));
         }
     }
 
-    Ok(())
+    Ok(files)
+}
+
+pub struct CacheLock {
+    file: File,
+}
+
+impl CacheLock {
+    pub fn acquire(lock_file_path: &Path, wait: bool) -> Result<Self, GarbageError> {
+        let file = File::create(lock_file_path)?;
+
+        if wait {
+            file.lock_exclusive()?;
+        } else if let Err(error) = file.try_lock_exclusive() {
+            if error.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                return Err(GarbageError::CacheLocked);
+            }
+            return Err(GarbageError::IOError(error));
+        }
+
+        Ok(Self { file })
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+pub fn lock_garbage_result_vec_cache(
+    from_path: &Path,
+    wait: bool,
+) -> Result<CacheLock, GarbageError> {
+    let path_hash = generate_base64_from_path(from_path);
+    let cache_dir_path = std::env::temp_dir().join("wsg/");
+    let lock_file_path = cache_dir_path.join(format!("{}.lock", path_hash));
+
+    if !cache_dir_path.exists() {
+        fs::create_dir_all(cache_dir_path)?;
+    }
+
+    CacheLock::acquire(&lock_file_path, wait)
 }
 
 fn generate_base64_from_path(p: &Path) -> String {
@@ -132,15 +1286,25 @@ fn generate_base64_from_path(p: &Path) -> String {
         p.hash(&mut hasher);
         hasher.finish().to_be_bytes()
     };
-    general_purpose::STANDARD_NO_PAD.encode(&bytes)
+    // the name of a file in the cache directory, the standard alphabet has a `/`
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::garbage::{GarbageIndex, GarbageRecognizerResult};
+    use crate::error::GarbageError;
+    use crate::garbage::{result_fixture, GarbageIndex, GarbageRecognizerResult, SizeStrategy};
     use crate::utils::{
-        delete_garbage_result_vec_cache, dir_size, format_bytes, generate_base64_from_path,
-        is_cache_durable, read_garbage_result_vec_cache, write_garbage_result_vec_cache,
+        delete_garbage_result_vec_cache, dir_size, dir_size_breakdown, dir_size_with_parallelism,
+        dir_size_with_strategy, disk_dir_size, extended_length_path, fanned_out_dir_size,
+        format_bytes, format_bytes_exact, format_bytes_in, format_bytes_prec, format_count,
+        format_date, format_number, format_size, generate_base64_from_path, glob_match,
+        is_cache_durable, parse_du_output, parse_duration, parse_size, prune_cache_files_in,
+        read_garbage_result_vec_cache, refresh_garbage_result_vec_cache_in,
+        replace_garbage_result_vec_cache, unique_disk_size, write_cache_file,
+        write_garbage_result_vec_cache, CacheLock, CachedListing, DuBackend, IoThrottle,
+        SizeBackend, SizeFormat, SizeParallelism, SizeUnit, WalkBackend, CLEAN_HISTORY_FILE_NAME,
+        RECOGNIZER_STATS_FILE_NAME,
     };
     use std::env::temp_dir;
     use std::fs;
@@ -148,7 +1312,9 @@ mod tests {
     use std::io::Write;
     use std::ops::{Add, Sub};
     use std::path::{Path, PathBuf};
-    use std::time::{Duration, SystemTime};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::{Duration, Instant, SystemTime};
 
     #[test]
     fn test_dir_size() {
@@ -176,23 +1342,45 @@ mod tests {
         fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_dir_size_with_strategy_sparse_file() {
+        let temp_dir = temp_dir().join("wsg_dev_sparse");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev directory");
+
+        File::create(temp_dir.join("sparse"))
+            .expect("Failed to create test file")
+            .set_len(10_000_000)
+            .expect("Failed to extend sparse test file");
+
+        let walk = dir_size_with_strategy(&temp_dir, SizeStrategy::Walk).unwrap();
+        let apparent = dir_size_with_strategy(&temp_dir, SizeStrategy::Apparent).unwrap();
+        let blocks = dir_size_with_strategy(&temp_dir, SizeStrategy::Blocks).unwrap();
+
+        assert_eq!(walk, 10_000_000);
+        assert_eq!(apparent, 10_000_000);
+        assert!(blocks < apparent);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
     #[test]
     fn test_format_bytes() {
         let test_cases = [
             (0, "0.00 B"),
             (100, "100.00 B"),
-            (1000, "1.00 kB"),
-            (1000_00, "100.00 kB"),
-            (1000_000, "1.00 MB"),
-            (1000_000_00, "100.00 MB"),
-            (1000_000_000, "1.00 GB"),
-            (1000_000_000_00, "100.00 GB"),
-            (1000_000_000_000, "1.00 TB"),
-            (1000_000_000_000_00, "100.00 TB"),
-            (1000_000_000_000_000, "1.00 PB"),
-            (1000_000_000_000_000_00, "100.00 PB"),
-            (1000_000_000_000_000_000, "1.00 EB"),
-            (1000_000_000_000_000_000_0, "10.00 EB"),
+            (1_000, "1.00 kB"),
+            (100_000, "100.00 kB"),
+            (1_000_000, "1.00 MB"),
+            (100_000_000, "100.00 MB"),
+            (1_000_000_000, "1.00 GB"),
+            (100_000_000_000, "100.00 GB"),
+            (1_000_000_000_000, "1.00 TB"),
+            (100_000_000_000_000, "100.00 TB"),
+            (1_000_000_000_000_000, "1.00 PB"),
+            (100_000_000_000_000_000, "100.00 PB"),
+            (1_000_000_000_000_000_000, "1.00 EB"),
+            (10_000_000_000_000_000_000, "10.00 EB"),
         ];
 
         for (input, expected_output) in test_cases {
@@ -201,51 +1389,225 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_glob_match() {
+        let test_cases = [
+            ("*.tf", "main.tf", true),
+            ("*.tf", "main.tfvars", false),
+            ("*.tf", ".tf", true),
+            ("terragrunt.hcl", "terragrunt.hcl", true),
+            ("*.csproj", "App.csproj", true),
+            ("?.txt", "a.txt", true),
+            ("?.txt", "ab.txt", false),
+            ("a*b*c", "aXXbYYc", true),
+            ("a*b*c", "aXXbYY", false),
+            ("*", "", true),
+        ];
+
+        for (pattern, name, expected) in test_cases {
+            assert_eq!(
+                glob_match(pattern, name),
+                expected,
+                "{} ~ {}",
+                pattern,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        const DAY: u64 = 24 * 60 * 60;
+        let test_cases = [
+            ("45s", 45),
+            ("30m", 30 * 60),
+            ("2h", 2 * 60 * 60),
+            ("7d", 7 * DAY),
+            ("2w", 14 * DAY),
+            ("3mo", 90 * DAY),
+            ("1y", 365 * DAY),
+            (" 12 H ", 12 * 60 * 60),
+            ("0d", 0),
+        ];
+
+        for (input, seconds) in test_cases {
+            assert_eq!(
+                parse_duration(input),
+                Ok(Duration::from_secs(seconds)),
+                "{:?}",
+                input
+            );
+        }
+
+        for invalid in [
+            "",
+            "30",
+            "d",
+            "-1d",
+            "1.5h",
+            "3 fortnights",
+            "99999999999999999y",
+        ] {
+            assert!(
+                parse_duration(invalid).is_err(),
+                "{:?} must be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        let test_cases = [
+            ("0", Ok(0)),
+            ("42", Ok(42)),
+            ("42B", Ok(42)),
+            ("1kB", Ok(1_000)),
+            ("500MB", Ok(500_000_000)),
+            ("1.5 GB", Ok(1_500_000_000)),
+            ("50gb", Ok(50_000_000_000)),
+            ("2T", Ok(2_000_000_000_000)),
+        ];
+
+        for (input, expected_output) in test_cases {
+            assert_eq!(parse_size(input), expected_output, "{:?}", input);
+        }
+
+        for invalid in ["", "GB", "-1GB", "5 parsecs", "1.2.3MB"] {
+            assert!(
+                parse_size(invalid).is_err(),
+                "{:?} must be rejected",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_bytes_exact() {
+        let test_cases = [
+            (0, "0.00 B (0 bytes)"),
+            (999, "999.00 B (999 bytes)"),
+            (1_000, "1.00 kB (1,000 bytes)"),
+            (123_456, "123.45 kB (123,456 bytes)"),
+            (999_999_999, "999.99 MB (999,999,999 bytes)"),
+            (1_000_000_000, "1.00 GB (1,000,000,000 bytes)"),
+            (4_203_145_216, "4.20 GB (4,203,145,216 bytes)"),
+            (u64::MAX, "18.44 EB (18,446,744,073,709,551,615 bytes)"),
+        ];
+
+        for (input, expected_output) in test_cases {
+            assert_eq!(format_bytes_exact(input, 2), expected_output);
+        }
+
+        assert_eq!(format_bytes_exact(1_999, 0), "1 kB (1,999 bytes)");
+        assert_eq!(format_bytes_exact(123_456, 3), "123.456 kB (123,456 bytes)");
+        assert_eq!(format_bytes_exact(999_999, 1), "999.9 kB (999,999 bytes)");
+    }
+
+    #[test]
+    fn test_format_number() {
+        let test_cases = [
+            (0, "0"),
+            (999, "999"),
+            (1_000, "1,000"),
+            (12_431, "12,431"),
+            (4_203_145_216, "4,203,145,216"),
+        ];
+
+        for (input, expected_output) in test_cases {
+            assert_eq!(format_number(input), expected_output);
+        }
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0, "project", "projects"), "0 projects");
+        assert_eq!(format_count(1, "project", "projects"), "1 project");
+        assert_eq!(format_count(2, "project", "projects"), "2 projects");
+        assert_eq!(format_count(12_431, "file", "files"), "12,431 files");
+        assert_eq!(format_count(1, "directory", "directories"), "1 directory");
+    }
+
     #[test]
     fn test_garbage_result_vec_cache() {
         let path = Path::new("/Users/testuser/Projects");
         let garbage_results = vec![
-            GarbageRecognizerResult {
-                index: GarbageIndex::Id(0),
-                recognizer_name: "Rust".to_string(),
-                directory: Default::default(),
-                size: 0,
-                deletable: vec![],
-            },
-            GarbageRecognizerResult {
-                index: GarbageIndex::Id(1),
-                recognizer_name: "Flutter".to_string(),
-                directory: PathBuf::from("/Users/testuser/Projects/example"),
-                size: 0,
-                deletable: vec![PathBuf::from("/Users/testuser/Projects/example/target")],
-            },
+            result_fixture(0, "Rust", PathBuf::new(), 0, &[]),
+            result_fixture(1, "Flutter", "/Users/testuser/Projects/example", 0, &[0]),
         ];
 
         let write_result = write_garbage_result_vec_cache(path, &garbage_results, None);
         assert!(write_result.is_ok());
 
-        let read_result = read_garbage_result_vec_cache(path, None);
+        let _ = read_garbage_result_vec_cache(path, None);
         assert!(write_result.is_ok());
     }
 
     #[test]
-    fn test_is_cache_durable() {
-        assert_eq!(
-            is_cache_durable(SystemTime::now().add(Duration::from_secs(5))),
-            true
-        );
-        assert_eq!(
-            is_cache_durable(SystemTime::now().add(Duration::from_secs(10))),
-            true
-        );
-        assert_eq!(
-            is_cache_durable(SystemTime::now().sub(Duration::from_secs(5))),
-            false
-        );
+    fn test_cache_file_is_streamed_listing() {
+        let cache_dir = temp_dir().join("wsg_dev_cache_stream");
+        let path = Path::new("/Users/testuser/wsg_dev_cache_stream");
+        let garbage_results = vec![
+            result_fixture(0, "Rust", PathBuf::new(), 0, &[]),
+            result_fixture(1, "Flutter", path.join("example"), 0, &[0]),
+        ];
+        fs::create_dir_all(&cache_dir).expect("Failed to create temporary wsg_dev directory");
+        let cache_file_path = cache_dir.join(generate_base64_from_path(path));
+
+        write_cache_file(&cache_file_path, path, &garbage_results).expect("Failed to write cache");
+
+        let expected = serde_json::to_string_pretty(&CachedListing {
+            source: path.to_path_buf(),
+            results: &garbage_results,
+        })
+        .expect("Failed to serialize");
         assert_eq!(
-            is_cache_durable(SystemTime::now().sub(Duration::from_secs(10))),
-            false
+            fs::read(&cache_file_path).expect("Failed to read cache"),
+            expected.as_bytes()
         );
+
+        fs::remove_dir_all(&cache_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_cache_of_another_path_is_invalid() {
+        let path = Path::new("/Users/testuser/wsg_dev_cache_source");
+        let colliding = Path::new("/Users/testuser/wsg_dev_cache_collision");
+        let cache_dir_path = std::env::temp_dir().join("wsg/");
+        let written = replace_garbage_result_vec_cache(path, &vec![]).expect("Failed to write");
+        let colliding_file = cache_dir_path.join(generate_base64_from_path(colliding));
+        fs::copy(&written, &colliding_file).expect("Failed to copy cache");
+
+        assert!(read_garbage_result_vec_cache(path, None).is_ok());
+        assert!(matches!(
+            read_garbage_result_vec_cache(colliding, None),
+            Err(GarbageError::InvalidCache)
+        ));
+
+        fs::write(&colliding_file, "[]").expect("Failed to write cache");
+        assert!(matches!(
+            read_garbage_result_vec_cache(colliding, None),
+            Err(GarbageError::InvalidCache)
+        ));
+
+        fs::remove_file(written).expect("Can't delete cache file");
+        fs::remove_file(colliding_file).expect("Can't delete cache file");
+    }
+
+    #[test]
+    fn test_is_cache_durable() {
+        assert!(is_cache_durable(
+            SystemTime::now().add(Duration::from_secs(5))
+        ));
+        assert!(is_cache_durable(
+            SystemTime::now().add(Duration::from_secs(10))
+        ));
+        assert!(!is_cache_durable(
+            SystemTime::now().sub(Duration::from_secs(5))
+        ));
+        assert!(!is_cache_durable(
+            SystemTime::now().sub(Duration::from_secs(10))
+        ));
     }
 
     #[test]
@@ -264,25 +1626,405 @@ mod tests {
         fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
     }
 
+    #[test]
+    fn test_refresh_garbage_result_vec_cache_keeps_scan_time() {
+        let cache_dir = temp_dir().join("wsg_dev_cache_refresh");
+        let path = Path::new("/Users/testuser/wsg_dev_cache_refresh");
+        let garbage: Vec<GarbageRecognizerResult> = (0..3)
+            .map(|id| {
+                result_fixture(
+                    id,
+                    "Rust",
+                    path.join(format!("project{}", id)),
+                    1000,
+                    &[1000],
+                )
+            })
+            .collect();
+        let cache_file_path = cache_dir.join(generate_base64_from_path(path));
+        let scanned_at = SystemTime::now().sub(Duration::from_secs(60));
+
+        fs::create_dir_all(&cache_dir).expect("Failed to create temporary wsg_dev directory");
+        write_cache_file(&cache_file_path, path, &garbage).expect("Failed to write cache");
+        File::options()
+            .write(true)
+            .open(&cache_file_path)
+            .and_then(|file| file.set_modified(scanned_at))
+            .expect("Failed to set modification time");
+
+        let remaining = vec![garbage[0].clone(), garbage[2].clone()];
+        refresh_garbage_result_vec_cache_in(&cache_dir, path, &remaining)
+            .expect("Failed to refresh cache");
+
+        let modified = fs::metadata(&cache_file_path)
+            .and_then(|metadata| metadata.modified())
+            .expect("Failed to read modification time");
+        assert_eq!(modified, scanned_at);
+        let listing: CachedListing<Vec<GarbageRecognizerResult>> =
+            serde_json::from_slice(&fs::read(&cache_file_path).expect("Failed to read cache"))
+                .expect("Failed to parse cache");
+        let cached_ids: Vec<_> = listing
+            .results
+            .into_iter()
+            .map(|result| result.index)
+            .collect();
+        assert_eq!(cached_ids, vec![GarbageIndex::Id(0), GarbageIndex::Id(2)]);
+
+        fs::remove_dir_all(&cache_dir).expect("Can't delete wsg_dev directory");
+    }
+
     #[test]
     fn test_generate_base64_from_path() {
         assert_eq!(
             generate_base64_from_path(Path::new("/Users/testuser/Projects"),),
-            "HU+HKWhsmqU"
+            "B-3k4613OkY"
         );
         assert_eq!(
             generate_base64_from_path(Path::new("/Users/testuser/Projects/"),),
-            "HU+HKWhsmqU"
+            "B-3k4613OkY"
         );
         assert_eq!(
             generate_base64_from_path(Path::new("C:/Users/TestUser/Projects"),),
-            "9sOUzqnWnG0"
+            "BBlC6a_2sH0"
         );
         assert_eq!(
             generate_base64_from_path(Path::new("C:/Users/TestUser/Projects/"),),
-            "9sOUzqnWnG0"
+            "BBlC6a_2sH0"
         );
         assert_eq!(generate_base64_from_path(Path::new(""),), "vWCstljHnkU");
         assert_eq!(generate_base64_from_path(Path::new("/"),), "vWCstljHnkU");
     }
+
+    #[test]
+    fn test_cache_lock_contention() {
+        let temp_dir = temp_dir().join("wsg_dev_lock");
+        fs::create_dir_all(&temp_dir).expect("Failed to create temporary wsg_dev_lock directory");
+        let lock_file_path = temp_dir.join("cache.lock");
+
+        let lock = CacheLock::acquire(&lock_file_path, false).expect("Failed to acquire lock");
+
+        let contended_path = lock_file_path.clone();
+        let contended =
+            thread::spawn(move || CacheLock::acquire(&contended_path, false).map(|_| ()))
+                .join()
+                .expect("Lock thread panicked");
+        assert!(matches!(contended, Err(GarbageError::CacheLocked)));
+
+        let (sender, receiver) = mpsc::channel();
+        let waiting_path = lock_file_path.clone();
+        let waiting = thread::spawn(move || {
+            let _lock = CacheLock::acquire(&waiting_path, true).expect("Failed to wait for lock");
+            sender
+                .send(Instant::now())
+                .expect("Failed to send lock time");
+        });
+
+        thread::sleep(Duration::from_millis(200));
+        let released_at = Instant::now();
+        drop(lock);
+
+        let acquired_at = receiver.recv().expect("Failed to receive lock time");
+        waiting.join().expect("Lock thread panicked");
+        assert!(acquired_at >= released_at);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev_lock directory");
+    }
+
+    #[test]
+    fn test_prune_cache_files() {
+        let cache_dir = temp_dir().join("wsg_dev_prune_cache");
+        fs::create_dir_all(&cache_dir).expect("Failed to create temporary wsg_dev directory");
+
+        let now = SystemTime::now();
+        for (name, age) in [
+            ("old", 40 * 24 * 60 * 60),
+            ("recent", 60 * 60),
+            (RECOGNIZER_STATS_FILE_NAME, 40 * 24 * 60 * 60),
+            (CLEAN_HISTORY_FILE_NAME, 40 * 24 * 60 * 60),
+        ] {
+            File::create(cache_dir.join(name))
+                .expect("Failed to create test file")
+                .set_modified(now.sub(Duration::from_secs(age)))
+                .expect("Failed to set modification time");
+        }
+
+        let pruned = prune_cache_files_in(&cache_dir, Duration::from_secs(30 * 24 * 60 * 60))
+            .expect("Failed to prune cache");
+
+        assert_eq!(pruned, 1);
+        assert!(!cache_dir.join("old").exists());
+        assert!(cache_dir.join("recent").exists());
+        assert!(cache_dir.join(RECOGNIZER_STATS_FILE_NAME).exists());
+        assert!(cache_dir.join(CLEAN_HISTORY_FILE_NAME).exists());
+
+        fs::remove_dir_all(&cache_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_prune_cache_files_keeps_held_locks() {
+        let cache_dir = temp_dir().join("wsg_dev_prune_locks");
+        fs::create_dir_all(&cache_dir).expect("Failed to create temporary wsg_dev directory");
+        let held = cache_dir.join("held.lock");
+        let released = cache_dir.join("released.lock");
+
+        let _lock = CacheLock::acquire(&held, false).expect("Failed to acquire cache lock");
+        drop(CacheLock::acquire(&released, false).expect("Failed to acquire cache lock"));
+        let old = SystemTime::now().sub(Duration::from_secs(40 * 24 * 60 * 60));
+        for lock_file in [&held, &released] {
+            File::options()
+                .write(true)
+                .open(lock_file)
+                .and_then(|file| file.set_modified(old))
+                .expect("Failed to set modification time");
+        }
+
+        let pruned = prune_cache_files_in(&cache_dir, Duration::from_secs(30 * 24 * 60 * 60))
+            .expect("Failed to prune cache");
+
+        assert_eq!(pruned, 1);
+        assert!(held.exists());
+        assert!(!released.exists());
+
+        fs::remove_dir_all(&cache_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_dir_size_with_parallelism() {
+        let temp_dir = temp_dir().join("wsg_dev_parallel_size");
+        for index in 0..40 {
+            let package = temp_dir.join(format!("package{:02}", index)).join("lib");
+            fs::create_dir_all(&package).expect("Failed to create test directory");
+            fs::write(package.join("index.js"), vec![0; 100 + index])
+                .expect("Failed to create test file");
+        }
+        fs::write(temp_dir.join("package.json"), [0; 42]).expect("Failed to create test file");
+
+        for strategy in [
+            SizeStrategy::Walk,
+            SizeStrategy::Apparent,
+            SizeStrategy::Blocks,
+        ] {
+            let sequential = dir_size_with_strategy(&temp_dir, strategy).unwrap();
+            let wide = SizeParallelism {
+                threads: 4,
+                threshold: 10,
+            };
+            let (parallel, threads) =
+                fanned_out_dir_size(&temp_dir, strategy, None, wide, None).unwrap();
+            assert_eq!(parallel, sequential);
+            let expected_threads = if strategy == SizeStrategy::Blocks {
+                1
+            } else {
+                4
+            };
+            assert_eq!(threads, expected_threads);
+
+            let narrow = SizeParallelism {
+                threads: 4,
+                threshold: 100,
+            };
+            let (size, threads) =
+                fanned_out_dir_size(&temp_dir, strategy, None, narrow, None).unwrap();
+            assert_eq!((size, threads), (sequential, 1));
+        }
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_format_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_709_942_399), "2024-03-08");
+        assert_eq!(format_date(1_709_942_400), "2024-03-09");
+    }
+
+    #[test]
+    fn test_extended_length_path() {
+        let deep = format!(r"C:\Users\dev\app{}", r"\node_modules\package".repeat(12));
+        assert!(deep.len() > 260);
+        assert_eq!(extended_length_path(&deep), Some(format!(r"\\?\{}", deep)));
+        assert_eq!(
+            extended_length_path("C:/Users/dev/app/node_modules"),
+            Some(r"\\?\C:\Users\dev\app\node_modules".to_string())
+        );
+        assert_eq!(
+            extended_length_path(r"\\server\share\app"),
+            Some(r"\\?\UNC\server\share\app".to_string())
+        );
+        assert_eq!(extended_length_path(&format!(r"\\?\{}", deep)), None);
+        assert_eq!(extended_length_path(r"app\node_modules"), None);
+        assert_eq!(extended_length_path(r"C:\app\..\node_modules"), None);
+    }
+
+    #[test]
+    fn test_parse_du_output() {
+        assert_eq!(
+            parse_du_output("123456\t/projects/app/target\n"),
+            Some(123_456)
+        );
+        assert_eq!(parse_du_output("du: cannot access"), None);
+        assert_eq!(parse_du_output(""), None);
+    }
+
+    #[test]
+    fn test_du_backend_agrees_with_walk_backend() {
+        // only GNU du is supported, elsewhere DuBackend is never used
+        let Some(du) = DuBackend::detect(WalkBackend::default()) else {
+            return;
+        };
+        let temp_dir = temp_dir().join("wsg_dev_du_backend");
+        for package in ["left-pad", "lodash", "react"] {
+            let package = temp_dir.join(package).join("dist");
+            fs::create_dir_all(&package).expect("Failed to create test directory");
+            fs::write(package.join("index.js"), vec![0; 50_000])
+                .expect("Failed to create test file");
+        }
+
+        for strategy in [
+            SizeStrategy::Walk,
+            SizeStrategy::Apparent,
+            SizeStrategy::Blocks,
+        ] {
+            let walked = WalkBackend::default()
+                .size(&temp_dir, strategy, None)
+                .unwrap();
+            let measured = du.size(&temp_dir, strategy, None).unwrap();
+            // du also counts the directory the walk starts from
+            let tolerance = 8 * 1024;
+            assert!(
+                walked.abs_diff(measured) <= tolerance,
+                "{:?}: walk {} du {}",
+                strategy,
+                walked,
+                measured
+            );
+        }
+
+        let missing = temp_dir.join("missing");
+        assert!(du.size(&missing, SizeStrategy::Walk, None).is_err());
+        assert!(du.size(&missing, SizeStrategy::Apparent, None).is_err());
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unique_disk_size_counts_hard_links_once() {
+        let temp_dir = temp_dir().join("wsg_dev_unique_size");
+        let projects = ["app", "web"].map(|project| temp_dir.join(project).join("node_modules"));
+        for node_modules in &projects {
+            fs::create_dir_all(node_modules).expect("Failed to create test directory");
+        }
+        fs::write(projects[0].join("react.js"), vec![1; 64 * 1024])
+            .expect("Failed to create test file");
+        fs::hard_link(projects[0].join("react.js"), projects[1].join("react.js"))
+            .expect("Failed to create hard link");
+        fs::write(projects[1].join("own.js"), vec![1; 16 * 1024])
+            .expect("Failed to create test file");
+
+        let naive: u64 = projects
+            .iter()
+            .map(|path| disk_dir_size(path).expect("Failed to measure"))
+            .sum();
+        let unique =
+            unique_disk_size(projects.iter().map(PathBuf::as_path)).expect("Failed to measure");
+        let shared = fs::metadata(projects[0].join("react.js"))
+            .map(|metadata| std::os::unix::fs::MetadataExt::blocks(&metadata) * 512)
+            .expect("Failed to read metadata");
+        assert!(unique < naive, "unique {} naive {}", unique, naive);
+        assert_eq!(unique, naive - shared);
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
+
+    #[test]
+    fn test_format_bytes_in() {
+        let bytes = 1_234_567_890;
+        let test_cases = [
+            (SizeUnit::B, "1234567890.00 B"),
+            (SizeUnit::KB, "1234567.89 kB"),
+            (SizeUnit::MB, "1234.57 MB"),
+            (SizeUnit::GB, "1.23 GB"),
+            (SizeUnit::TB, "0.00 TB"),
+        ];
+
+        for (unit, expected_output) in test_cases {
+            assert_eq!(format_bytes_in(bytes, unit, 2), expected_output);
+        }
+        assert_eq!(format_bytes_in(0, SizeUnit::GB, 2), "0.00 GB");
+        let in_gb = SizeFormat {
+            unit: Some(SizeUnit::GB),
+            ..SizeFormat::default()
+        };
+        assert_eq!(format_size(50_000_000, in_gb), "0.05 GB");
+        assert_eq!(format_size(50_000_000, SizeFormat::default()), "50.00 MB");
+        let compact = SizeFormat {
+            unit: Some(SizeUnit::GB),
+            decimals: 1,
+        };
+        assert_eq!(format_size(50_000_000, compact), "0.1 GB");
+    }
+
+    #[test]
+    fn test_format_bytes_prec() {
+        let test_cases = [
+            (1_234_567, 0, "1 MB"),
+            (1_234_567, 1, "1.2 MB"),
+            (1_234_567, 2, "1.23 MB"),
+            (1_234_567, 3, "1.235 MB"),
+            (999, 0, "999 B"),
+            (999_990, 1, "1.0 MB"),
+            (999_990, 2, "999.99 kB"),
+            (999_500, 0, "1 MB"),
+            (999_499, 0, "999 kB"),
+            (999_999_999, 2, "1.00 GB"),
+            (999_999_000, 3, "999.999 MB"),
+            (999_999_999, 3, "1.000 GB"),
+        ];
+
+        for (input, decimals, expected_output) in test_cases {
+            assert_eq!(format_bytes_prec(input, decimals), expected_output);
+        }
+    }
+
+    #[test]
+    fn test_io_throttle() {
+        let start = Instant::now();
+        let throttle = IoThrottle::starting_at(10, start);
+        let ms = Duration::from_millis;
+
+        assert_eq!(throttle.reserve(start), Duration::ZERO);
+        assert_eq!(throttle.reserve(start), ms(100));
+        assert_eq!(throttle.reserve(start), ms(200));
+        // the waiting reads have used up the tokens refilled in the meantime
+        assert_eq!(throttle.reserve(start + ms(250)), ms(50));
+        // an idle second refills the burst, but not beyond it
+        let idle = start + Duration::from_secs(5);
+        for _ in 0..10 {
+            assert_eq!(throttle.reserve(idle), Duration::ZERO);
+        }
+        assert_eq!(throttle.reserve(idle), ms(100));
+
+        let temp_dir = temp_dir().join("wsg_dev_io_throttle");
+        for index in 0..10 {
+            fs::create_dir_all(temp_dir.join(index.to_string()))
+                .expect("Failed to create test directory");
+        }
+        let throttle = IoThrottle::new(100);
+        let started = Instant::now();
+        dir_size_with_parallelism(&temp_dir, SizeStrategy::Walk, None, None, Some(&throttle))
+            .expect("Failed to measure");
+        // 11 directories at 100 per second, the first one is free
+        assert!(started.elapsed() >= ms(100));
+
+        let throttle = IoThrottle::new(100);
+        let started = Instant::now();
+        let sizes = dir_size_breakdown(&temp_dir, SizeStrategy::Walk, Some(&throttle))
+            .expect("Failed to measure");
+        assert_eq!(sizes.len(), 10);
+        assert!(started.elapsed() >= ms(100));
+
+        fs::remove_dir_all(&temp_dir).expect("Can't delete wsg_dev directory");
+    }
 }
@@ -0,0 +1,151 @@
+use std::env::temp_dir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A workspace with a Rust project in `projects`, and its own cache and config
+/// directories so the runs neither see nor touch those of the user.
+fn fixture(name: &str) -> PathBuf {
+    let workspace = temp_dir().join(name);
+    let project = workspace.join("projects").join("app");
+    fs::create_dir_all(project.join("target").join("debug"))
+        .expect("Failed to create test project");
+    fs::write(project.join("Cargo.toml"), "[package]").expect("Failed to create test file");
+    fs::write(
+        project.join("target").join("debug").join("app"),
+        vec![0; 1_000],
+    )
+    .expect("Failed to create test file");
+    fs::create_dir_all(workspace.join("tmp")).expect("Failed to create cache directory");
+    fs::create_dir_all(workspace.join("config")).expect("Failed to create config directory");
+    workspace
+}
+
+fn wsg(workspace: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_wsg"))
+        .args(args)
+        .current_dir(workspace)
+        .env("TMPDIR", workspace.join("tmp"))
+        .env("XDG_CONFIG_HOME", workspace.join("config"))
+        .env("XDG_STATE_HOME", workspace.join("state"))
+        .env_remove("WSG_SAFE")
+        .env_remove("WSG_REQUIRE_PATH")
+        .output()
+        .expect("Failed to run wsg")
+}
+
+#[test]
+fn test_list_then_clean_all() {
+    let workspace = fixture("wsg_dev_cli_list_clean");
+    let projects = workspace.join("projects");
+    let projects = projects.to_str().expect("Fixture path is not UTF-8");
+
+    let listed = wsg(&workspace, &["--list", "--color", "never", projects]);
+    assert_eq!(listed.status.code(), Some(0));
+    let stdout = String::from_utf8_lossy(&listed.stdout);
+    assert!(stdout.contains("[0] Rust"), "{}", stdout);
+    assert!(stdout.contains("target"), "{}", stdout);
+
+    let cleaned = wsg(&workspace, &["--clean", "all", "--yes", projects]);
+    assert_eq!(
+        cleaned.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&cleaned.stderr)
+    );
+    let project = workspace.join("projects").join("app");
+    assert!(!project.join("target").exists());
+    assert!(project.join("Cargo.toml").exists());
+
+    fs::remove_dir_all(&workspace).expect("Can't delete wsg_dev directory");
+}
+
+#[test]
+fn test_missing_path_fails() {
+    let workspace = fixture("wsg_dev_cli_missing_path");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_wsg"))
+        .arg("--list")
+        .current_dir(&workspace)
+        .env("TMPDIR", workspace.join("tmp"))
+        .env("XDG_CONFIG_HOME", workspace.join("config"))
+        .env("XDG_STATE_HOME", workspace.join("state"))
+        .env("WSG_REQUIRE_PATH", "1")
+        .output()
+        .expect("Failed to run wsg");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("A path must be specified"));
+
+    let missing = workspace.join("does-not-exist");
+    let output = wsg(
+        &workspace,
+        &[
+            "--list",
+            missing.to_str().expect("Fixture path is not UTF-8"),
+        ],
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("It must be a valid path"));
+
+    fs::remove_dir_all(&workspace).expect("Can't delete wsg_dev directory");
+}
+
+#[test]
+fn test_check_exit_code() {
+    let workspace = fixture("wsg_dev_cli_check");
+    let projects = workspace.join("projects");
+    let projects = projects.to_str().expect("Fixture path is not UTF-8");
+
+    let dirty = wsg(&workspace, &["--check", projects]);
+    assert_eq!(dirty.status.code(), Some(6));
+    assert!(String::from_utf8_lossy(&dirty.stdout).contains("Rust"));
+
+    let dirty = wsg(&workspace, &["--check", "--fail-fast", projects]);
+    assert_eq!(dirty.status.code(), Some(6));
+
+    fs::remove_dir_all(workspace.join("projects").join("app").join("target"))
+        .expect("Failed to delete the garbage");
+    let clean = wsg(&workspace, &["--check", projects]);
+    assert_eq!(
+        clean.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&clean.stderr)
+    );
+    assert!(String::from_utf8_lossy(&clean.stdout).contains("No garbage found"));
+
+    fs::remove_dir_all(&workspace).expect("Can't delete wsg_dev directory");
+}
+
+#[test]
+fn test_follow_links_with_strict_still_scans() {
+    let workspace = fixture("wsg_dev_cli_follow_links_strict");
+    let projects = workspace.join("projects");
+    let projects = projects.to_str().expect("Fixture path is not UTF-8");
+
+    let listed = wsg(
+        &workspace,
+        &[
+            "--list",
+            "--follow-links",
+            "--strict",
+            "--color",
+            "never",
+            projects,
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&listed.stderr);
+    assert_eq!(listed.status.code(), Some(0), "{}", stderr);
+    assert!(String::from_utf8_lossy(&listed.stdout).contains("[0] Rust"));
+    assert!(
+        stderr.contains("Warning: following symbolic links"),
+        "{}",
+        stderr
+    );
+
+    let schema = wsg(&workspace, &["--print-schema", "--follow-links"]);
+    assert_eq!(schema.status.code(), Some(0));
+    assert!(!String::from_utf8_lossy(&schema.stderr).contains("symbolic links"));
+
+    fs::remove_dir_all(&workspace).expect("Can't delete wsg_dev directory");
+}